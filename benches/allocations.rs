@@ -0,0 +1,114 @@
+use criterion::measurement::{Measurement, ValueFormatter};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tf_demo_parser::demo::parser::gamestateanalyser::GameStateAnalyser;
+use tf_demo_parser::{Demo, DemoParser, Stream};
+
+/// Allocator that counts the number of allocations made, including reallocations
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Criterion measurement of the number of allocations made by a benchmark
+struct Allocations;
+
+impl Measurement for Allocations {
+    type Intermediate = usize;
+    type Value = usize;
+
+    fn start(&self) -> Self::Intermediate {
+        ALLOCATIONS.load(Ordering::Relaxed)
+    }
+
+    fn end(&self, start: Self::Intermediate) -> Self::Value {
+        ALLOCATIONS.load(Ordering::Relaxed) - start
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        v1 + v2
+    }
+
+    fn zero(&self) -> Self::Value {
+        0
+    }
+
+    fn to_f64(&self, value: &Self::Value) -> f64 {
+        *value as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        &AllocationFormatter
+    }
+}
+
+struct AllocationFormatter;
+
+impl ValueFormatter for AllocationFormatter {
+    fn scale_values(&self, _typical_value: f64, _values: &mut [f64]) -> &'static str {
+        "allocations"
+    }
+
+    fn scale_throughputs(
+        &self,
+        _typical_value: f64,
+        _throughput: &Throughput,
+        _values: &mut [f64],
+    ) -> &'static str {
+        "allocations"
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "allocations"
+    }
+}
+
+fn bench_gamestate_allocations(input_file: &str, b: &mut Criterion<Allocations>) {
+    let file = fs::read(input_file).expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let stream: Stream = demo.get_stream();
+
+    b.bench_function(&format!("gamestate allocations {}", input_file), |b| {
+        b.iter(|| {
+            let (_, state) =
+                DemoParser::new_with_analyser(stream.clone(), GameStateAnalyser::default())
+                    .parse()
+                    .unwrap();
+            black_box(state);
+        })
+    });
+}
+
+fn bench_gamestate_allocations_gully(b: &mut Criterion<Allocations>) {
+    bench_gamestate_allocations("test_data/gully.dem", b);
+}
+fn bench_gamestate_allocations_comp(b: &mut Criterion<Allocations>) {
+    bench_gamestate_allocations("test_data/comp.dem", b);
+}
+
+criterion_group!(
+    name = benches;
+    config = Criterion::default().with_measurement(Allocations);
+    targets = bench_gamestate_allocations_comp, bench_gamestate_allocations_gully
+);
+criterion_main!(benches);
@@ -1,7 +1,36 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::collections::HashMap;
 use std::fs;
+use tf_demo_parser::demo::data::DemoTick;
+use tf_demo_parser::demo::message::packetentities::{EntityId, PacketEntity, UpdateType};
+use tf_demo_parser::demo::message::{Message, MessageType};
 use tf_demo_parser::demo::parser::gamestateanalyser::GameStateAnalyser;
-use tf_demo_parser::{Demo, DemoParser, Stream};
+use tf_demo_parser::demo::parser::MessageHandler;
+use tf_demo_parser::{Demo, DemoParser, ParserState, Stream};
+
+/// Collect all entity updates from a demo
+#[derive(Default)]
+struct EntityCollector {
+    entities: Vec<PacketEntity>,
+}
+
+impl MessageHandler for EntityCollector {
+    type Output = Vec<PacketEntity>;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        matches!(message_type, MessageType::PacketEntities)
+    }
+
+    fn handle_message(&mut self, message: &Message, _tick: DemoTick, _parser_state: &ParserState) {
+        if let Message::PacketEntities(message) = message {
+            self.entities.extend(message.entities.iter().cloned());
+        }
+    }
+
+    fn into_output(self, _state: &ParserState) -> Self::Output {
+        self.entities
+    }
+}
 
 fn bench_file(input_file: &str, b: &mut Criterion) {
     let file = fs::read(input_file).expect("Unable to read file");
@@ -32,6 +61,64 @@ fn bench_gamestate(input_file: &str, b: &mut Criterion) {
     });
 }
 
+fn bench_static_baselines(input_file: &str, b: &mut Criterion) {
+    let file = fs::read(input_file).expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, mut ticker) =
+        DemoParser::new_with_analyser(demo.get_stream(), GameStateAnalyser::default())
+            .ticker()
+            .unwrap();
+    while ticker.tick().unwrap() {}
+    let state = ticker.parser_state();
+
+    b.bench_function(&format!("decode static baselines {}", input_file), |b| {
+        b.iter(|| {
+            for (class_id, baseline) in state.static_baselines.iter() {
+                let send_table = &state.send_tables[usize::from(*class_id)];
+                black_box(baseline.parse(send_table).unwrap());
+            }
+        })
+    });
+
+    b.bench_function(&format!("cached static baselines {}", input_file), |b| {
+        b.iter(|| {
+            for class_id in state.static_baselines.keys() {
+                let send_table = &state.send_tables[usize::from(*class_id)];
+                black_box(state.get_static_baseline(*class_id, send_table).unwrap());
+            }
+        })
+    });
+}
+
+fn bench_apply_update(input_file: &str, b: &mut Criterion) {
+    let file = fs::read(input_file).expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, updates) = DemoParser::new_with_analyser(demo.get_stream(), EntityCollector::default())
+        .parse()
+        .unwrap();
+
+    b.bench_function(&format!("apply updates {}", input_file), |b| {
+        b.iter(|| {
+            let mut entities: HashMap<EntityId, PacketEntity> = HashMap::new();
+            for update in updates.iter() {
+                match (update.update_type, entities.get_mut(&update.entity_index)) {
+                    (UpdateType::Preserve | UpdateType::Enter, Some(entity)) => {
+                        entity.apply_update(&update.props)
+                    }
+                    (UpdateType::Delete, _) => {
+                        entities.remove(&update.entity_index);
+                    }
+                    (UpdateType::Leave, _) => {}
+                    _ => {
+                        entities.insert(update.entity_index, update.clone());
+                    }
+                }
+            }
+            black_box(entities);
+        })
+    });
+}
+
 fn bench_gully(b: &mut Criterion) {
     bench_file("test_data/gully.dem", b);
 }
@@ -46,11 +133,21 @@ fn bench_gamestate_comp(b: &mut Criterion) {
     bench_gamestate("test_data/comp.dem", b);
 }
 
+fn bench_static_baselines_gully(b: &mut Criterion) {
+    bench_static_baselines("test_data/gully.dem", b);
+}
+
+fn bench_apply_update_comp(b: &mut Criterion) {
+    bench_apply_update("test_data/comp.dem", b);
+}
+
 criterion_group!(
     benches,
     bench_comp,
     bench_gully,
     bench_gamestate_comp,
-    bench_gamestate_gully
+    bench_gamestate_gully,
+    bench_static_baselines_gully,
+    bench_apply_update_comp
 );
 criterion_main!(benches);
@@ -8,7 +8,9 @@ use std::fs;
 
 use std::collections::{HashMap, HashSet};
 use tf_demo_parser::demo::message::Message;
-use tf_demo_parser::demo::packet::datatable::{ParseSendTable, SendTableName};
+use tf_demo_parser::demo::packet::datatable::{
+    FlatPropsCache, ParseSendTable, SendTableName, ServerClass,
+};
 use tf_demo_parser::demo::packet::stringtable::StringTableEntry;
 use tf_demo_parser::demo::parser::MessageHandler;
 use tf_demo_parser::demo::sendprop::RawSendPropDefinition;
@@ -54,9 +56,70 @@ fn flatten_bench(input_file: &str, b: &mut Criterion) {
     });
 }
 
+#[derive(Default)]
+pub struct DataTableAnalyser {
+    tables: Vec<ParseSendTable>,
+    server_classes: Vec<ServerClass>,
+}
+
+impl MessageHandler for DataTableAnalyser {
+    type Output = (Vec<ParseSendTable>, Vec<ServerClass>);
+
+    fn does_handle(message_type: MessageType) -> bool {
+        false
+    }
+
+    fn handle_data_tables(
+        &mut self,
+        tables: &[ParseSendTable],
+        server_classes: &[ServerClass],
+        _parser_state: &ParserState,
+    ) {
+        self.tables = tables.to_vec();
+        self.server_classes = server_classes.to_vec();
+    }
+
+    fn into_output(self, _state: &ParserState) -> Self::Output {
+        (self.tables, self.server_classes)
+    }
+}
+
+/// Handle the data tables the same way the parser does when entities are parsed, with and without a warm cache
+fn flatten_cache_bench(input_file: &str, b: &mut Criterion) {
+    let file = fs::read(input_file).expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let stream = demo.get_stream();
+    let (_, (send_tables, server_classes)) =
+        DemoParser::new_with_analyser(stream.clone(), DataTableAnalyser::default())
+            .parse()
+            .unwrap();
+    let handle_data_tables = |cache: Option<FlatPropsCache>| {
+        let mut state = ParserState::new(24, |_| true, false);
+        state.flat_props_cache = cache;
+        state
+            .handle_data_table(send_tables.clone(), server_classes.clone())
+            .unwrap();
+        state
+    };
+
+    b.bench_function(&format!("data tables cold cache {}", input_file), |b| {
+        b.iter(|| black_box(handle_data_tables(None)))
+    });
+
+    let cache = FlatPropsCache::new();
+    handle_data_tables(Some(cache.clone()));
+    b.bench_function(&format!("data tables warm cache {}", input_file), |b| {
+        b.iter(|| black_box(handle_data_tables(Some(cache.clone()))))
+    });
+}
+
 fn sendprop_test_gully(b: &mut Criterion) {
     flatten_bench("test_data/gully.dem", b);
 }
 
-criterion_group!(benches, sendprop_test_gully);
+fn sendprop_cache_test_gully(b: &mut Criterion) {
+    flatten_cache_bench("test_data/gully.dem", b);
+}
+
+criterion_group!(benches, sendprop_test_gully, sendprop_cache_test_gully);
 criterion_main!(benches);
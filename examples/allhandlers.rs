@@ -0,0 +1,66 @@
+use main_error::MainError;
+use std::env;
+use std::fs;
+pub use tf_demo_parser::{Demo, DemoParser, Parse, ParseError, ParserState, Stream};
+
+#[path = "../tests/allhandlers/mod.rs"]
+mod allhandlers;
+
+use allhandlers::all_handlers;
+
+/// Parse a demo with all shipped analysers in a single pass and print a summary of their output
+fn main() -> Result<(), MainError> {
+    #[cfg(feature = "trace")]
+    tracing_subscriber::fmt::init();
+
+    let args: Vec<_> = env::args().collect();
+    if args.len() < 2 {
+        println!("1 argument required");
+        return Ok(());
+    }
+    let path = args[1].clone();
+    let file = fs::read(path)?;
+    let demo = Demo::new(&file);
+    let parser = DemoParser::new_with_analyser(demo.get_stream(), all_handlers());
+    let (
+        header,
+        (
+            (state, game_state, class_timeline, positions),
+            (shots, charges, heal_targets, scoreboard),
+            (team_scores, round_timer, buildings, taunts),
+            ((punch_angles, health, flags, disguises), (ground, ubers, tick_base, console)),
+        ),
+    ) = parser.parse()?;
+
+    println!("map: {}", header.map);
+    println!("users: {}", state.users.len());
+    println!("deaths: {}", state.deaths.len());
+    println!("rounds: {}", state.rounds.len());
+    println!("chat messages: {}", state.chat.len());
+    println!("players: {}", game_state.players.len());
+    println!("class changes: {}", class_timeline.classes.len());
+    println!(
+        "position samples: {}",
+        positions.values().map(Vec::len).sum::<usize>()
+    );
+    println!("shots: {}", shots.len());
+    println!("charge samples: {}", charges.len());
+    println!("heal target changes: {}", heal_targets.len());
+    println!("scoreboard samples: {}", scoreboard.samples.len());
+    println!(
+        "team score: {} red, {} blue",
+        team_scores.red_score, team_scores.blue_score
+    );
+    println!("round timer samples: {}", round_timer.len());
+    println!("building states: {}", buildings.len());
+    println!("taunts: {}", taunts.len());
+    println!("punch angles: {}", punch_angles.len());
+    println!("health samples: {}", health.len());
+    println!("flag states: {}", flags.len());
+    println!("disguise changes: {}", disguises.len());
+    println!("ground entity changes: {}", ground.len());
+    println!("uber advantages: {}", ubers.len());
+    println!("tick base offsets: {}", tick_base.len());
+    println!("console output: {}", console.len());
+    Ok(())
+}
@@ -6,7 +6,7 @@ use std::cmp::Ordering;
 use std::fmt::{Debug, Display, Formatter};
 use std::ops::{Add, Sub};
 
-pub use userinfo::UserInfo;
+pub use userinfo::{UserId, UserInfo};
 
 #[derive(Eq, PartialEq, Clone)]
 pub enum MaybeUtf8String {
@@ -154,6 +154,12 @@ impl ServerTick {
     }
 }
 
+impl Display for ServerTick {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
 impl PartialEq<u32> for ServerTick {
     fn eq(&self, other: &u32) -> bool {
         *other == self.0
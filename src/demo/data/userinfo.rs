@@ -1,8 +1,52 @@
 use crate::demo::message::packetentities::EntityId;
 use crate::demo::packet::stringtable::{ExtraData, StringTableEntry};
-use crate::demo::parser::analyser::UserId;
 use crate::{ReadResult, Stream};
-use bitbuffer::{BitRead, BitReadBuffer, BitReadStream, BitWrite, BitWriteStream, LittleEndian};
+use bitbuffer::{
+    BitRead, BitReadBuffer, BitReadStream, BitWrite, BitWriteStream, Endianness, LittleEndian,
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(
+    Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq, Hash, Ord, PartialOrd, Default,
+)]
+pub struct UserId(u16);
+
+impl<E: Endianness> BitWrite<E> for UserId {
+    fn write(&self, stream: &mut BitWriteStream<E>) -> ReadResult<()> {
+        (self.0 as u32).write(stream)
+    }
+}
+
+impl From<u32> for UserId {
+    fn from(int: u32) -> Self {
+        UserId(int as u16)
+    }
+}
+
+impl From<u16> for UserId {
+    fn from(int: u16) -> Self {
+        UserId(int)
+    }
+}
+
+impl From<UserId> for u16 {
+    fn from(id: UserId) -> Self {
+        id.0
+    }
+}
+
+impl From<UserId> for u32 {
+    fn from(id: UserId) -> Self {
+        id.0 as u32
+    }
+}
+
+impl PartialEq<u16> for UserId {
+    fn eq(&self, other: &u16) -> bool {
+        self.0 == *other
+    }
+}
 
 #[derive(BitRead, Debug)]
 struct RawPlayerInfo {
@@ -76,10 +120,7 @@ impl UserInfo {
             // extra decode step to gracefully handle malformed utf8 names
             let raw_info: RawPlayerInfo = data.read()?;
 
-            match text
-                .map(|text| text.parse::<u32>().map(|id| (id + 1).into()))
-                .unwrap_or_else(|| Ok((index as u32 + 1).into()))
-            {
+            match Self::entity_id_for_entry(index, text) {
                 Ok(entity_id) if !raw_info.steam_id.is_empty() => Ok(Some(UserInfo {
                     player_info: raw_info.into(),
                     entity_id,
@@ -91,6 +132,15 @@ impl UserInfo {
         }
     }
 
+    /// Get the player entity a `userinfo` string table entry belongs to
+    pub fn entity_id_for_entry(
+        index: u16,
+        text: Option<&str>,
+    ) -> Result<EntityId, std::num::ParseIntError> {
+        text.map(|text| text.parse::<u32>().map(|id| (id + 1).into()))
+            .unwrap_or_else(|| Ok((index as u32 + 1).into()))
+    }
+
     pub fn encode_to_string_table(&self) -> ReadResult<StringTableEntry<'static>> {
         let text = format!("{}", self.entity_id);
         let mut extra_data = Vec::with_capacity(132);
@@ -1,6 +1,7 @@
 use bitbuffer::{BitRead, BitWrite};
 use serde::{Deserialize, Serialize};
 
+/// The fixed size header at the start of every demo file
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(BitRead, BitWrite, Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct Header {
@@ -16,8 +17,10 @@ pub struct Header {
     pub map: String,
     #[size = 260]
     pub game: String,
+    /// Playback time of the demo in seconds
     pub duration: f32,
     pub ticks: u32,
     pub frames: u32,
+    /// Length of the signon data in bytes
     pub signon: u32,
 }
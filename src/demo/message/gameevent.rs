@@ -6,7 +6,7 @@ use crate::demo::gameevent_gen::GameEventType;
 use crate::demo::gamevent::{
     GameEvent, GameEventDefinition, GameEventEntry, GameEventValueType, RawGameEvent,
 };
-use crate::demo::parser::{Encode, ParseBitSkip};
+use crate::demo::parser::{Encode, ParseBitSkip, ParseWarning};
 use crate::{GameEventError, Parse, ParseError, ParserState, ReadResult, Result, Stream};
 
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
@@ -22,19 +22,25 @@ impl Parse<'_> for GameEventMessage {
         let mut data = stream.read_bits(length as usize)?;
         let event_type_id: GameEventTypeId = data.read()?;
 
+        let unknown_event = GameEvent::Unknown(RawGameEvent {
+            event_type: GameEventType::Unknown(String::new()),
+            values: Vec::new(),
+        });
+
         // game event definitions haven't been sent yet, ignore
         if state.event_definitions.is_empty() {
             return Ok(GameEventMessage {
                 event_type_id,
-                event: GameEvent::Unknown(RawGameEvent {
-                    event_type: GameEventType::Unknown(String::new()),
-                    values: Vec::new(),
-                }),
+                event: unknown_event,
             });
         }
 
         let event = match state.event_definitions.get(usize::from(event_type_id)) {
             Some(definition) => GameEvent::read(&mut data, definition)?,
+            None if state.lenient => {
+                state.add_warning(ParseWarning::UnknownGameEventType(event_type_id));
+                unknown_event
+            }
             None => {
                 return Err(ParseError::MalformedGameEvent(GameEventError::UnknownType(
                     event_type_id,
@@ -109,6 +115,47 @@ fn test_game_event_roundtrip() {
     );
 }
 
+#[test]
+fn test_game_event_unknown_type() {
+    use bitbuffer::{BitReadBuffer, BitReadStream};
+
+    let mut data = Vec::new();
+    {
+        let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+        stream
+            .reserve_length(11, |stream| GameEventTypeId(3).write(stream))
+            .unwrap();
+    }
+
+    // events from before the definitions are received are ignored
+    let mut state = ParserState::new(24, |_| false, false);
+    let mut stream = BitReadStream::new(BitReadBuffer::new(&data, LittleEndian));
+    let message = GameEventMessage::parse(&mut stream, &state).unwrap();
+    assert!(matches!(message.event, GameEvent::Unknown(_)));
+
+    state.event_definitions = vec![GameEventDefinition {
+        id: GameEventTypeId(0),
+        event_type: GameEventType::GameInit,
+        entries: vec![],
+    }];
+    let mut stream = BitReadStream::new(BitReadBuffer::new(&data, LittleEndian));
+    assert!(matches!(
+        GameEventMessage::parse(&mut stream, &state),
+        Err(ParseError::MalformedGameEvent(GameEventError::UnknownType(
+            GameEventTypeId(3)
+        )))
+    ));
+
+    state.lenient = true;
+    let mut stream = BitReadStream::new(BitReadBuffer::new(&data, LittleEndian));
+    let message = GameEventMessage::parse(&mut stream, &state).unwrap();
+    assert!(matches!(message.event, GameEvent::Unknown(_)));
+    assert_eq!(
+        vec![ParseWarning::UnknownGameEventType(GameEventTypeId(3))],
+        state.warnings()
+    );
+}
+
 impl ParseBitSkip<'_> for GameEventMessage {
     fn parse_skip(stream: &mut Stream, _state: &ParserState) -> Result<()> {
         let length: u16 = stream.read_sized(11)?;
@@ -134,6 +181,12 @@ impl ParseBitSkip<'_> for GameEventMessage {
 )]
 pub struct GameEventTypeId(#[size = 9] u16);
 
+impl From<u16> for GameEventTypeId {
+    fn from(id: u16) -> Self {
+        GameEventTypeId(id)
+    }
+}
+
 impl From<GameEventTypeId> for usize {
     fn from(id: GameEventTypeId) -> Self {
         id.0 as usize
@@ -5,8 +5,8 @@ use std::borrow::Cow;
 
 use crate::demo::message::stringtable::log_base2;
 use crate::demo::packet::datatable::{ClassId, SendTable};
-use crate::demo::parser::{Encode, ParseBitSkip};
-use crate::demo::sendprop::{SendProp, SendPropIdentifier, SendPropValue};
+use crate::demo::parser::{Encode, ParseBitSkip, ParseWarning, UnknownDeltaPolicy};
+use crate::demo::sendprop::{apply_prop_update, SendProp, SendPropIdentifier, SendPropValue};
 use crate::{Parse, ParseError, ParserState, ReadResult, Result, Stream};
 use parse_display::{Display, FromStr};
 use std::cmp::{min, Ordering};
@@ -110,10 +110,6 @@ impl fmt::Display for PacketEntity {
 }
 
 impl PacketEntity {
-    fn mut_prop_by_identifier(&mut self, index: &SendPropIdentifier) -> Option<&mut SendProp> {
-        self.props.iter_mut().find(|prop| prop.identifier == *index)
-    }
-
     pub fn get_prop_by_identifier(
         &self,
         index: &SendPropIdentifier,
@@ -124,12 +120,7 @@ impl PacketEntity {
     }
 
     pub fn apply_update(&mut self, props: &[SendProp]) {
-        for prop in props {
-            match self.mut_prop_by_identifier(&prop.identifier) {
-                Some(existing_prop) => existing_prop.value = prop.value.clone(),
-                None => self.props.push(prop.clone()),
-            }
-        }
+        apply_prop_update(&mut self.props, props);
     }
 
     pub fn get_prop_by_name(
@@ -161,11 +152,10 @@ impl PacketEntity {
         if self.update_type == UpdateType::Enter {
             let mut found_props = HashSet::<SendPropIdentifier>::new();
             let props = self.props.iter().cloned();
-            #[allow(clippy::unnecessary_to_owned)]
-            let baseline_props = self
-                .get_baseline_props(parser_state)
-                .into_owned()
-                .into_iter();
+            let baseline_props = match self.get_baseline_props(parser_state) {
+                Cow::Borrowed(props) => Either::Left(props.iter().cloned()),
+                Cow::Owned(props) => Either::Right(props.into_iter()),
+            };
             Either::Left(props.chain(baseline_props).filter(move |prop| {
                 let found = found_props.contains(&prop.identifier);
                 found_props.insert(prop.identifier);
@@ -237,6 +227,40 @@ fn test_bit_var_roundtrip() {
     }
 }
 
+#[test]
+fn test_bit_var_malformed() {
+    use bitbuffer::{BitReadBuffer, BitReadStream};
+
+    // data ending before the value
+    for (data, bits) in [(vec![], 0), (vec![0b11], 8), (vec![0b11, 0xFF, 0xFF], 24)] {
+        let mut read = BitReadStream::new(BitReadBuffer::new(&data, LittleEndian));
+        assert!(read_bit_var::<u32>(&mut read).is_err());
+        assert!(read.pos() <= bits);
+    }
+
+    // values too large for the requested type
+    let data = vec![0xFF; 8];
+    let mut read = BitReadStream::new(BitReadBuffer::new(&data, LittleEndian));
+    assert!(read_bit_var::<u16>(&mut read).is_err());
+
+    // random data can't make reading loop forever, every read consumes at least 6 bits
+    let mut seed: u32 = 0x8765_4321;
+    for len in 0..64usize {
+        let data: Vec<u8> = (0..len)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (seed >> 16) as u8
+            })
+            .collect();
+        let mut read = BitReadStream::new(BitReadBuffer::new(&data, LittleEndian));
+        let mut reads = 0;
+        while read_bit_var::<u32>(&mut read).is_ok() {
+            reads += 1;
+        }
+        assert!(reads * 6 <= len * 8);
+    }
+}
+
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
 pub struct PacketEntitiesMessage {
@@ -269,7 +293,7 @@ fn get_entity_for_update(
     Ok(PacketEntity {
         server_class: class_id,
         entity_index,
-        props: Vec::with_capacity(8),
+        props: Vec::new(),
         in_pvs: false,
         update_type,
         serial_number: 0,
@@ -290,34 +314,112 @@ impl Parse<'_> for PacketEntitiesMessage {
 
         let mut data = stream.read_bits(length as usize)?;
 
+        if delta.is_none() {
+            // a full update replaces the entire entity state
+            state.set_entity_state_incomplete(false);
+        } else if state.entity_state_incomplete() {
+            // the deltas would build on the entity updates that were skipped
+            return Ok(PacketEntitiesMessage {
+                max_entries,
+                delta,
+                base_line,
+                ..PacketEntitiesMessage::default()
+            });
+        }
+
+        if let Some(delta) = delta.filter(|delta| !state.is_known_delta_tick(*delta)) {
+            match state.unknown_delta_policy {
+                UnknownDeltaPolicy::Apply => {
+                    state.add_warning(ParseWarning::UnknownDeltaTick {
+                        delta,
+                        skipped: false,
+                    });
+                }
+                UnknownDeltaPolicy::Skip => {
+                    state.add_warning(ParseWarning::UnknownDeltaTick {
+                        delta,
+                        skipped: true,
+                    });
+                    state.set_entity_state_incomplete(true);
+                    return Ok(PacketEntitiesMessage {
+                        max_entries,
+                        delta: Some(delta),
+                        base_line,
+                        ..PacketEntitiesMessage::default()
+                    });
+                }
+                UnknownDeltaPolicy::Error => return Err(ParseError::UnknownDeltaTick(delta)),
+            }
+        }
+
         let mut entities = Vec::with_capacity(min(updated_entries, 128) as usize);
         let mut removed_entities = Vec::new();
+        // props are read into a shared buffer and then moved into an exactly sized vec for each entity,
+        // so the prop vecs of the entities don't need to grow while reading
+        let mut props = Vec::with_capacity(64);
 
         let mut last_index: i32 = -1;
+        let mut skipped = false;
 
-        for _ in 0..updated_entries {
+        for update_index in 0..updated_entries {
             let diff: u32 = read_bit_var(&mut data)?;
             last_index = last_index.saturating_add(diff as i32).saturating_add(1);
-            if last_index >= 2048 {
+            // diffs that don't fit in an i32 wrap around to negative indices
+            if !(0..2048).contains(&last_index) {
                 return Err(ParseError::InvalidDemo("invalid entity index"));
             }
             let entity_index = EntityId::from(last_index as u32);
 
             let update_type = data.read()?;
-            if update_type == UpdateType::Enter {
-                let mut entity =
-                    Self::read_enter(&mut data, entity_index, state, base_line as usize, delta)?;
-                let send_table = get_send_table(state, entity.server_class)?;
-                Self::read_update(&mut data, send_table, &mut entity.props, entity_index)?;
-
-                entities.push(entity);
-            } else if update_type == UpdateType::Preserve {
-                let mut entity = get_entity_for_update(state, entity_index, update_type, delta)?;
-                let send_table = get_send_table(state, entity.server_class)?;
-
-                Self::read_update(&mut data, send_table, &mut entity.props, entity_index)?;
+            if update_type == UpdateType::Enter || update_type == UpdateType::Preserve {
+                let entity = if update_type == UpdateType::Enter {
+                    Self::read_enter(&mut data, entity_index, state, base_line as usize, delta)
+                } else {
+                    get_entity_for_update(state, entity_index, update_type, delta)
+                };
+                let resolved = entity.and_then(|entity| {
+                    get_send_table(state, entity.server_class).map(|table| (entity, table))
+                });
+                let (mut entity, send_table) = match resolved {
+                    Ok(resolved) => resolved,
+                    Err(ParseError::UnknownEntity(_) | ParseError::UnknownServerClass(_))
+                        if state.lenient =>
+                    {
+                        // without the class we can't know where the next entity starts, so we skip the rest of the message
+                        state.add_warning(ParseWarning::GhostEntity(entity_index));
+                        state.set_entity_state_incomplete(true);
+                        skipped = true;
+                        break;
+                    }
+                    Err(e) => return Err(e),
+                };
 
-                entities.push(entity);
+                match Self::read_update(&mut data, send_table, &mut props, entity_index) {
+                    Ok(()) => {
+                        entity.props.append(&mut props);
+                        entities.push(entity);
+                    }
+                    Err(ParseError::PropIndexOutOfBounds {
+                        index,
+                        prop_count,
+                        table,
+                    }) if state.lenient => {
+                        // we can't know where the next entity starts, so we skip the rest of the message
+                        // including the partially read entity
+                        state.add_warning(ParseWarning::PropIndexOutOfBounds {
+                            entity: entity_index,
+                            index,
+                            prop_count,
+                            table,
+                            skipped_updates: updated_entries - update_index,
+                            removed_skipped: delta.is_some(),
+                        });
+                        state.set_entity_state_incomplete(true);
+                        skipped = true;
+                        break;
+                    }
+                    Err(e) => return Err(e),
+                }
             } else if state.entity_classes.contains_key(&entity_index) {
                 let entity = get_entity_for_update(state, entity_index, update_type, delta)?;
                 entities.push(entity);
@@ -336,7 +438,7 @@ impl Parse<'_> for PacketEntitiesMessage {
             }
         }
 
-        if delta.is_some() {
+        if delta.is_some() && !skipped {
             while data.read()? {
                 removed_entities.push(data.read_sized::<u32>(11)?.into())
             }
@@ -562,7 +664,7 @@ fn test_packet_entitier_message_roundtrip() {
             name: SendTableName::from("table1"),
             needs_decoder: false,
             raw_props: vec![],
-            flattened_props: vec![],
+            flattened_props: vec![].into(),
         },
         SendTable {
             name: SendTableName::from("table2"),
@@ -589,7 +691,8 @@ fn test_packet_entitier_message_roundtrip() {
                         definition: FloatDefinition::Coord,
                     },
                 },
-            ],
+            ]
+            .into(),
         },
     ];
     state
@@ -695,3 +798,274 @@ fn test_packet_entitier_message_roundtrip() {
         &state,
     );
 }
+
+#[test]
+fn test_packet_entities_lenient_out_of_bounds() {
+    use crate::demo::packet::datatable::{SendTableName, ServerClass, ServerClassName};
+    use crate::demo::sendprop::{SendPropDefinition, SendPropParseDefinition};
+    use bitbuffer::{BitReadBuffer, BitReadStream};
+
+    fn state_with_props(prop_count: usize) -> ParserState {
+        let mut state = ParserState::new(24, |_| false, false);
+        state.server_classes = vec![ServerClass {
+            id: ClassId::from(0),
+            name: ServerClassName::from("class1"),
+            data_table: SendTableName::from("table1"),
+        }];
+        state.send_tables = vec![SendTable {
+            name: SendTableName::from("table1"),
+            needs_decoder: false,
+            raw_props: vec![],
+            flattened_props: (0..prop_count)
+                .map(|_| SendPropDefinition {
+                    identifier: SendPropIdentifier::new("table1", "prop"),
+                    parse_definition: SendPropParseDefinition::Int {
+                        changes_often: false,
+                        bit_count: 8,
+                    },
+                })
+                .collect(),
+        }];
+        for entity in [3u32, 4, 5] {
+            state
+                .entity_classes
+                .insert(EntityId::from(entity), ClassId::from(0));
+        }
+        state
+    }
+
+    let entity = |index: u32, prop_indexes: &[u32]| PacketEntity {
+        server_class: ClassId::from(0),
+        entity_index: EntityId::from(index),
+        props: prop_indexes
+            .iter()
+            .map(|prop_index| SendProp {
+                index: *prop_index,
+                identifier: SendPropIdentifier::new("table1", "prop"),
+                value: SendPropValue::Integer(*prop_index as i64),
+            })
+            .collect(),
+        in_pvs: false,
+        update_type: UpdateType::Preserve,
+        serial_number: 0,
+        delay: None,
+        delta: None,
+        baseline_index: 0,
+    };
+    let message = |delta: Option<ServerTick>| PacketEntitiesMessage {
+        entities: vec![entity(3, &[0]), entity(4, &[0, 5]), entity(5, &[1])],
+        removed_entities: vec![],
+        max_entries: 6,
+        delta,
+        base_line: 0,
+        updated_base_line: false,
+    };
+    let encode = |message: PacketEntitiesMessage| {
+        let mut data = Vec::with_capacity(128);
+        {
+            let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+            message.encode(&mut stream, &state_with_props(6)).unwrap();
+        }
+        data
+    };
+    let data = encode(message(None));
+
+    let mut state = state_with_props(3);
+    let mut read = BitReadStream::new(BitReadBuffer::new(&data, LittleEndian));
+    assert!(matches!(
+        PacketEntitiesMessage::parse(&mut read, &state),
+        Err(ParseError::PropIndexOutOfBounds { index: 5, .. })
+    ));
+
+    state.lenient = true;
+    let mut read = BitReadStream::new(BitReadBuffer::new(&data, LittleEndian));
+    let parsed = PacketEntitiesMessage::parse(&mut read, &state).unwrap();
+    // the partially read entity isn't included
+    assert_eq!(vec![entity(3, &[0])], parsed.entities);
+    assert_eq!(
+        vec![ParseWarning::PropIndexOutOfBounds {
+            entity: EntityId::from(4u32),
+            index: 5,
+            prop_count: 3,
+            table: "table1".into(),
+            skipped_updates: 2,
+            removed_skipped: false,
+        }],
+        state.warnings()
+    );
+    assert!(state.entity_state_incomplete());
+
+    // deltas are skipped until the next full update
+    let delta = encode(PacketEntitiesMessage {
+        entities: vec![entity(3, &[1])],
+        ..message(Some(10u32.into()))
+    });
+    let mut read = BitReadStream::new(BitReadBuffer::new(&delta, LittleEndian));
+    let parsed = PacketEntitiesMessage::parse(&mut read, &state).unwrap();
+    assert!(parsed.entities.is_empty());
+
+    let full = encode(PacketEntitiesMessage {
+        entities: vec![entity(3, &[1])],
+        ..message(None)
+    });
+    let mut read = BitReadStream::new(BitReadBuffer::new(&full, LittleEndian));
+    let parsed = PacketEntitiesMessage::parse(&mut read, &state).unwrap();
+    assert_eq!(vec![entity(3, &[1])], parsed.entities);
+    assert!(!state.entity_state_incomplete());
+}
+
+#[test]
+fn test_packet_entities_unknown_delta_tick() {
+    use crate::demo::data::DemoTick;
+    use crate::demo::message::{Message, NetTickMessage};
+    use bitbuffer::{BitReadBuffer, BitReadStream};
+
+    fn empty_update(delta: Option<ServerTick>) -> PacketEntitiesMessage {
+        PacketEntitiesMessage {
+            entities: vec![],
+            removed_entities: vec![],
+            max_entries: 4,
+            delta,
+            base_line: 0,
+            updated_base_line: false,
+        }
+    }
+
+    let mut state = ParserState::new(24, |_| false, false);
+    for tick in [10u32, 11, 13] {
+        state.handle_message(
+            Message::NetTick(NetTickMessage {
+                tick: tick.into(),
+                frame_time: 0,
+                std_dev: 0,
+            }),
+            DemoTick::from(tick),
+        );
+        state.handle_message(
+            Message::PacketEntities(empty_update(None)),
+            DemoTick::from(tick),
+        );
+    }
+
+    let encode = |message: PacketEntitiesMessage| {
+        let mut data = Vec::with_capacity(16);
+        {
+            let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+            message.encode(&mut stream, &state).unwrap();
+        }
+        data
+    };
+
+    let known = encode(empty_update(Some(11u32.into())));
+    let mut read = BitReadStream::new(BitReadBuffer::new(&known, LittleEndian));
+    assert!(PacketEntitiesMessage::parse(&mut read, &state).is_ok());
+
+    // the update for tick 12 was never received
+    let missing = encode(empty_update(Some(12u32.into())));
+    let mut read = BitReadStream::new(BitReadBuffer::new(&missing, LittleEndian));
+    assert!(PacketEntitiesMessage::parse(&mut read, &state).is_ok());
+    assert_eq!(
+        vec![ParseWarning::UnknownDeltaTick {
+            delta: 12u32.into(),
+            skipped: false,
+        }],
+        state.warnings()
+    );
+    assert!(!state.entity_state_incomplete());
+
+    state.unknown_delta_policy = UnknownDeltaPolicy::Error;
+    let mut read = BitReadStream::new(BitReadBuffer::new(&missing, LittleEndian));
+    assert!(matches!(
+        PacketEntitiesMessage::parse(&mut read, &state),
+        Err(ParseError::UnknownDeltaTick(tick)) if tick == 12u32
+    ));
+
+    state.unknown_delta_policy = UnknownDeltaPolicy::Skip;
+    let mut read = BitReadStream::new(BitReadBuffer::new(&missing, LittleEndian));
+    assert!(PacketEntitiesMessage::parse(&mut read, &state).is_ok());
+    assert_eq!(
+        Some(&ParseWarning::UnknownDeltaTick {
+            delta: 12u32.into(),
+            skipped: true,
+        }),
+        state.warnings().last()
+    );
+    assert!(state.entity_state_incomplete());
+}
+
+#[test]
+fn test_packet_entities_lenient_ghost_entity() {
+    use crate::demo::packet::datatable::{SendTableName, ServerClass, ServerClassName};
+    use crate::demo::sendprop::{SendPropDefinition, SendPropParseDefinition};
+    use bitbuffer::{BitReadBuffer, BitReadStream};
+
+    let mut state = ParserState::new(24, |_| false, false);
+    state.server_classes = vec![ServerClass {
+        id: ClassId::from(0),
+        name: ServerClassName::from("class1"),
+        data_table: SendTableName::from("table1"),
+    }];
+    state.send_tables = vec![SendTable {
+        name: SendTableName::from("table1"),
+        needs_decoder: false,
+        raw_props: vec![],
+        flattened_props: vec![SendPropDefinition {
+            identifier: SendPropIdentifier::new("table1", "prop"),
+            parse_definition: SendPropParseDefinition::Int {
+                changes_often: false,
+                bit_count: 8,
+            },
+        }]
+        .into(),
+    }];
+    state
+        .entity_classes
+        .insert(EntityId::from(4u32), ClassId::from(0));
+
+    let entity = |index: u32| PacketEntity {
+        server_class: ClassId::from(0),
+        entity_index: EntityId::from(index),
+        props: vec![SendProp {
+            index: 0,
+            identifier: SendPropIdentifier::new("table1", "prop"),
+            value: SendPropValue::Integer(4),
+        }],
+        in_pvs: false,
+        update_type: UpdateType::Preserve,
+        serial_number: 0,
+        delay: None,
+        delta: None,
+        baseline_index: 0,
+    };
+    let message = PacketEntitiesMessage {
+        entities: vec![entity(4), entity(5)],
+        removed_entities: vec![],
+        max_entries: 4,
+        delta: None,
+        base_line: 0,
+        updated_base_line: false,
+    };
+
+    let mut data = Vec::with_capacity(128);
+    {
+        let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+        message.encode(&mut stream, &state).unwrap();
+    }
+
+    // entity 5 was never created
+    let mut read = BitReadStream::new(BitReadBuffer::new(&data, LittleEndian));
+    assert!(matches!(
+        PacketEntitiesMessage::parse(&mut read, &state),
+        Err(ParseError::UnknownEntity(entity)) if entity == EntityId::from(5u32)
+    ));
+
+    state.lenient = true;
+    let mut read = BitReadStream::new(BitReadBuffer::new(&data, LittleEndian));
+    let parsed = PacketEntitiesMessage::parse(&mut read, &state).unwrap();
+    assert_eq!(vec![entity(4)], parsed.entities);
+    assert_eq!(
+        vec![ParseWarning::GhostEntity(EntityId::from(5u32))],
+        state.warnings()
+    );
+    assert_eq!(vec![EntityId::from(5u32)], state.ghost_entities());
+}
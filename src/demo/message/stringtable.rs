@@ -703,9 +703,15 @@ fn test_table_entry_roundtrip() {
     );
 }
 
+/// The maximum number of bytes in a var int, enough to hold 32 bits with 7 bits per byte
+const MAX_VAR_INT_BYTES: u32 = 5;
+
+/// Read a var int of at most [`MAX_VAR_INT_BYTES`] bytes
+///
+/// The continuation bit of the last byte is ignored, so malformed data can't make this read more than 5 bytes.
 pub fn read_var_int(stream: &mut Stream) -> ReadResult<u32> {
     let mut result: u32 = 0;
-    for i in (0..35u32).step_by(7) {
+    for i in (0..MAX_VAR_INT_BYTES * 7).step_by(7) {
         let byte: u8 = stream.read()?;
         result |= (byte as u32 & 0x7F) << i;
 
@@ -784,6 +790,39 @@ fn test_var_int_fixed_roundtrip() {
     var_int_roundtrip(123125412);
 }
 
+#[test]
+fn test_var_int_malformed() {
+    // continuation bits on every byte
+    let data = vec![0xFF; 16];
+    let mut read = BitReadStream::new(BitReadBuffer::new(&data, LittleEndian));
+    assert_eq!(u32::MAX, read_var_int(&mut read).unwrap());
+    assert_eq!(MAX_VAR_INT_BYTES as usize * 8, read.pos());
+
+    // data ending while the continuation bit is set
+    for len in 0..MAX_VAR_INT_BYTES as usize {
+        let data = vec![0x80; len];
+        let mut read = BitReadStream::new(BitReadBuffer::new(&data, LittleEndian));
+        assert!(read_var_int(&mut read).is_err());
+    }
+
+    // random data can't make reading loop forever, every read consumes at least one byte
+    let mut seed: u32 = 0x1234_5678;
+    for len in 0..64 {
+        let data: Vec<u8> = (0..len)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (seed >> 16) as u8
+            })
+            .collect();
+        let mut read = BitReadStream::new(BitReadBuffer::new(&data, LittleEndian));
+        let mut reads = 0;
+        while read_var_int(&mut read).is_ok() {
+            reads += 1;
+        }
+        assert!(reads <= len);
+    }
+}
+
 pub fn log_base2<T: PrimInt + Unsigned>(num: T) -> u32 {
     // log(0) = inf, but that's a useless result
     // since this would only happen in malformed demos, we just return 0
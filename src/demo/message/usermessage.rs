@@ -1,4 +1,5 @@
 use bitbuffer::{BitError, BitRead, BitWrite, BitWriteStream, Endianness, LittleEndian};
+use parse_display::Display;
 use serde::{Deserialize, Serialize};
 
 use crate::demo::data::MaybeUtf8String;
@@ -191,17 +192,22 @@ fn test_user_message_roundtrip() {
 }
 
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Display)]
 pub enum ChatMessageKind {
     #[serde(rename = "TF_Chat_All")]
+    #[display("TF_Chat_All")]
     ChatAll,
     #[serde(rename = "TF_Chat_Team")]
+    #[display("TF_Chat_Team")]
     ChatTeam,
     #[serde(rename = "TF_Chat_AllDead")]
+    #[display("TF_Chat_AllDead")]
     ChatAllDead,
     #[serde(rename = "TF_Chat_Team_Dead")]
+    #[display("TF_Chat_Team_Dead")]
     ChatTeamDead,
     #[serde(rename = "TF_Chat_AllSpec")]
+    #[display("TF_Chat_AllSpec")]
     ChatAllSpec,
     NameChange,
     Empty,
@@ -1,4 +1,6 @@
-use bitbuffer::{BitReadBuffer, BitReadStream, LittleEndian};
+use crate::demo::header::Header;
+use crate::Result;
+use bitbuffer::{BitRead, BitReadBuffer, BitReadStream, LittleEndian};
 
 pub mod data;
 pub mod gameevent_gen;
@@ -30,6 +32,11 @@ impl<'a> Demo<'a> {
     pub fn get_stream(&self) -> Stream<'a> {
         self.stream.clone()
     }
+
+    /// Read only the header of the demo, for getting the map, server and duration without parsing the full demo
+    pub fn parse_header(&self) -> Result<Header> {
+        Ok(Header::read(&mut self.get_stream())?)
+    }
 }
 
 impl Demo<'static> {
@@ -7,13 +7,16 @@ use crate::{Parse, ParseError, ParserState, Result, Stream};
 use bitbuffer::{
     BitRead, BitReadStream, BitWrite, BitWriteSized, BitWriteStream, Endianness, LittleEndian,
 };
+use fnv::{FnvHashMap, FnvHasher};
 use parse_display::{Display, FromStr};
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::cmp::min;
 use std::convert::TryFrom;
+use std::hash::Hasher;
 use std::iter::once;
 use std::ops::Deref;
+use std::sync::{Arc, Mutex};
 
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(
@@ -427,13 +430,109 @@ impl ParseSendTable {
     }
 }
 
+/// Flattened props for every send table, keyed by table name
+pub type FlatProps = FnvHashMap<SendTableName, Arc<[SendPropDefinition]>>;
+
+/// Flatten the props of all send tables
+pub fn flatten_tables(tables: &[ParseSendTable]) -> Result<FlatProps> {
+    tables
+        .iter()
+        .map(|table| Ok((table.name.clone(), table.flatten_props(tables)?.into())))
+        .collect()
+}
+
+/// Hash identifying a set of send tables
+///
+/// The hash is calculated over the encoded send tables, demos recorded with the same server
+/// build will have the same schema hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+pub struct SchemaHash(u64);
+
+impl SchemaHash {
+    pub fn new(tables: &[ParseSendTable]) -> Result<Self> {
+        let mut data = Vec::with_capacity(64 * 1024);
+        {
+            let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+            for table in tables {
+                table.write(&mut stream)?;
+            }
+        }
+        let mut hasher = FnvHasher::default();
+        hasher.write(&data);
+        Ok(SchemaHash(hasher.finish()))
+    }
+}
+
+impl From<SchemaHash> for u64 {
+    fn from(hash: SchemaHash) -> Self {
+        hash.0
+    }
+}
+
+/// Cache for flattened send table props that can be shared between parsers
+///
+/// Flattening the send tables is done once per demo, when parsing a large number of demos
+/// from the same server build the flattened props can be re-used between demos with a matching schema.
+///
+/// The cache is cheap to clone, all clones share the same cached data.
+#[derive(Debug, Clone, Default)]
+pub struct FlatPropsCache {
+    entries: Arc<Mutex<FnvHashMap<SchemaHash, Arc<FlatProps>>>>,
+}
+
+impl FlatPropsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the flattened props for the send tables, flattening them if the schema isn't cached yet
+    pub fn get_or_flatten(&self, tables: &[ParseSendTable]) -> Result<Arc<FlatProps>> {
+        let hash = SchemaHash::new(tables)?;
+        if let Some(cached) = self.get(hash) {
+            return Ok(cached);
+        }
+
+        let flat = Arc::new(flatten_tables(tables)?);
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(hash, flat.clone());
+        Ok(flat)
+    }
+
+    pub fn get(&self, hash: SchemaHash) -> Option<Arc<FlatProps>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(&hash)
+            .cloned()
+    }
+
+    pub fn contains(&self, hash: SchemaHash) -> bool {
+        self.get(hash).is_some()
+    }
+
+    /// Number of distinct schemas in the cache
+    pub fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SendTable {
     pub name: SendTableName,
     pub needs_decoder: bool,
     pub raw_props: Vec<RawSendPropDefinition>,
-    pub flattened_props: Vec<SendPropDefinition>,
+    /// The flattened props are shared with the [`FlatPropsCache`] the tables were flattened with, if any
+    pub flattened_props: Arc<[SendPropDefinition]>,
 }
 
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
@@ -1,31 +1,45 @@
-use crate::demo::data::{DemoTick, ServerTick};
+pub use crate::demo::data::UserId;
+use crate::demo::data::{DemoTick, MaybeUtf8String, ServerTick};
 use crate::demo::gameevent_gen::{
-    GameEvent, PlayerDeathEvent, PlayerSpawnEvent, TeamPlayRoundWinEvent,
+    GameEvent, PlayerChangeClassEvent, PlayerDeathEvent, PlayerSpawnEvent, PlayerTeamEvent,
+    TeamPlayCaptureBlockedEvent, TeamPlayPointStartCaptureEvent, TeamPlayRoundWinEvent,
+    TeamPlayWinPanelEvent,
 };
 use crate::demo::message::packetentities::EntityId;
 use crate::demo::message::usermessage::{ChatMessageKind, SayText2Message, UserMessage};
 use crate::demo::message::{Message, MessageType};
 use crate::demo::packet::stringtable::StringTableEntry;
 use crate::demo::parser::handler::{BorrowMessageHandler, MessageHandler};
+use crate::demo::parser::weapons::normalize_weapon;
 use crate::demo::vector::Vector;
 use crate::{ParserState, ReadResult, Stream};
-use bitbuffer::{BitWrite, BitWriteStream, Endianness};
 use num_enum::TryFromPrimitive;
 use parse_display::{Display, FromStr};
 use serde::de::Error;
 use serde::{ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::convert::TryFrom;
+use std::io::{self, Write};
 use std::ops::{Index, IndexMut};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ChatMessage {
     pub kind: ChatMessageKind,
+    /// The name of the sender at the time the message was sent
     pub from: String,
     pub text: String,
     pub tick: DemoTick,
+    /// The user id of the sender, `None` for messages from the server, server plugins or unknown players
+    ///
+    /// Unlike the name in [`ChatMessage::from`], this isn't affected by the player changing their name.
+    #[serde(default)]
+    pub user: Option<UserId>,
 }
 
+/// Misspelled name of [`ChatMessage`] kept for compatibility
+#[deprecated(note = "use `ChatMessage` instead, the fields and serialized format are unchanged")]
+pub type ChatMassage = ChatMessage;
+
 impl ChatMessage {
     pub fn from_message(message: &SayText2Message, tick: DemoTick) -> Self {
         ChatMessage {
@@ -37,13 +51,25 @@ impl ChatMessage {
                 .unwrap_or_default(),
             text: message.plain_text(),
             tick,
+            user: None,
         }
     }
 }
 
 #[derive(
-    Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq, Hash, TryFromPrimitive, Default,
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    TryFromPrimitive,
+    Display,
+    Default,
 )]
+#[display(style = "lowercase")]
 #[serde(rename_all = "lowercase")]
 #[repr(u8)]
 pub enum Team {
@@ -68,10 +94,20 @@ impl Team {
 }
 
 #[derive(
-    Debug, Clone, Serialize, Copy, PartialEq, Eq, Hash, TryFromPrimitive, Display, FromStr, Default,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    TryFromPrimitive,
+    Display,
+    FromStr,
+    Default,
 )]
 #[display(style = "lowercase")]
-#[serde(rename_all = "lowercase")]
 #[repr(u8)]
 pub enum Class {
     #[default]
@@ -87,11 +123,30 @@ pub enum Class {
     Engineer = 9,
 }
 
+/// Classes are serialized by name in human readable formats and by number in binary formats
+impl Serialize for Class {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.to_string())
+        } else {
+            serializer.serialize_u8(*self as u8)
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for Class {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
+        if !deserializer.is_human_readable() {
+            return Class::try_from_primitive(u8::deserialize(deserializer)?)
+                .map_err(D::Error::custom);
+        }
+
         #[derive(Deserialize, Debug)]
         #[serde(untagged)]
         enum IntOrStr<'a> {
@@ -184,7 +239,7 @@ impl Serialize for ClassList {
         let mut classes = serializer.serialize_map(Some(count))?;
         for (class, count) in self.0.iter().copied().enumerate() {
             if count > 0 {
-                classes.serialize_entry(&class, &count)?;
+                classes.serialize_entry(&(class as u8), &count)?;
             }
         }
 
@@ -204,48 +259,6 @@ impl From<HashMap<Class, u8>> for ClassList {
     }
 }
 
-#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
-#[derive(
-    Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq, Hash, Ord, PartialOrd, Default,
-)]
-pub struct UserId(u16);
-
-impl<E: Endianness> BitWrite<E> for UserId {
-    fn write(&self, stream: &mut BitWriteStream<E>) -> ReadResult<()> {
-        (self.0 as u32).write(stream)
-    }
-}
-
-impl From<u32> for UserId {
-    fn from(int: u32) -> Self {
-        UserId(int as u16)
-    }
-}
-
-impl From<u16> for UserId {
-    fn from(int: u16) -> Self {
-        UserId(int)
-    }
-}
-
-impl From<UserId> for u16 {
-    fn from(id: UserId) -> Self {
-        id.0
-    }
-}
-
-impl From<UserId> for u32 {
-    fn from(id: UserId) -> Self {
-        id.0 as u32
-    }
-}
-
-impl PartialEq<u16> for UserId {
-    fn eq(&self, other: &u16) -> bool {
-        self.0 == *other
-    }
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Spawn {
     pub user: UserId,
@@ -265,7 +278,51 @@ impl Spawn {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A class picked by a player from the class selection menu
+///
+/// Picking a class doesn't change the class of the player until they respawn, a player can pick
+/// multiple classes before spawning or change their class while alive and keep playing their
+/// current class, so the intended class can differ from the class of the next spawn.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClassIntent {
+    pub user: UserId,
+    pub class: Class,
+    pub tick: DemoTick,
+}
+
+impl ClassIntent {
+    pub fn from_event(event: &PlayerChangeClassEvent, tick: DemoTick) -> Self {
+        ClassIntent {
+            user: UserId::from(event.user_id),
+            class: Class::new(event.class),
+            tick,
+        }
+    }
+}
+
+/// A player spawning as a different class than their previous spawn
+///
+/// `from` is [`Class::Other`] for the first spawn of a player.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClassChange {
+    pub user: UserId,
+    pub from: Class,
+    pub to: Class,
+    pub tick: DemoTick,
+}
+
+/// A player spawning on a different team than their previous spawn
+///
+/// `from` is [`Team::Other`] for the first spawn of a player.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TeamChange {
+    pub user: UserId,
+    pub from: Team,
+    pub to: Team,
+    pub tick: DemoTick,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct UserInfo {
     pub classes: ClassList,
@@ -275,6 +332,10 @@ pub struct UserInfo {
     #[serde(skip)]
     pub entity_id: EntityId,
     pub team: Team,
+    /// Total damage done to other players, not including self damage
+    pub damage_dealt: u32,
+    /// The number of times the player started dominating another player, either as killer or assister
+    pub dominations: u32,
 }
 
 impl From<crate::demo::data::UserInfo> for UserInfo {
@@ -286,6 +347,8 @@ impl From<crate::demo::data::UserInfo> for UserInfo {
             steam_id: info.player_info.steam_id,
             entity_id: info.entity_id,
             team: Team::default(),
+            damage_dealt: 0,
+            dominations: 0,
         }
     }
 }
@@ -297,24 +360,107 @@ impl PartialEq for UserInfo {
             && self.user_id == other.user_id
             && self.steam_id == other.steam_id
             && self.team == other.team
+            && self.damage_dealt == other.damage_dealt
+            && self.dominations == other.dominations
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, Display)]
+#[display(style = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum CritType {
+    #[default]
+    None,
+    Mini,
+    Full,
+}
+
+impl CritType {
+    pub fn new(number: u16) -> Self {
+        match number {
+            1 => CritType::Mini,
+            2 => CritType::Full,
+            _ => CritType::None,
+        }
+    }
+}
+
+// `customkill` values of death events, from `ETFDmgCustom` in the game
+pub const TF_DMG_CUSTOM_HEADSHOT: u16 = 1;
+pub const TF_DMG_CUSTOM_BACKSTAB: u16 = 2;
+pub const TF_DMG_CUSTOM_TAUNTATK_HADOUKEN: u16 = 7;
+pub const TF_DMG_CUSTOM_TAUNTATK_HIGH_NOON: u16 = 9;
+pub const TF_DMG_CUSTOM_TAUNTATK_GRAND_SLAM: u16 = 10;
+pub const TF_DMG_CUSTOM_TAUNTATK_FENCING: u16 = 13;
+pub const TF_DMG_CUSTOM_TAUNTATK_ARROW_STAB: u16 = 15;
+pub const TF_DMG_CUSTOM_TAUNTATK_GRENADE: u16 = 21;
+pub const TF_DMG_CUSTOM_TAUNTATK_BARBARIAN_SWING: u16 = 24;
+pub const TF_DMG_CUSTOM_TAUNTATK_UBERSLICE: u16 = 29;
+pub const TF_DMG_CUSTOM_TAUNTATK_ENGINEER_GUITAR_SMASH: u16 = 33;
+pub const TF_DMG_CUSTOM_TAUNTATK_ENGINEER_ARM_KILL: u16 = 38;
+pub const TF_DMG_CUSTOM_HEADSHOT_DECAPITATION: u16 = 51;
+pub const TF_DMG_CUSTOM_TAUNTATK_ARMAGEDDON: u16 = 52;
+pub const TF_DMG_CUSTOM_TAUNTATK_ALLCLASS_GUITAR_RIFF: u16 = 62;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
 pub struct Death {
     pub weapon: String,
     pub victim: UserId,
+    /// The user id of the player credited with the assist, `None` if nobody assisted
+    ///
+    /// The game only credits a single assister per kill, either the player that did the most damage to the victim
+    /// or the medic healing the killer, other players that contributed aren't included in the event.
     pub assister: Option<UserId>,
     pub killer: UserId,
     pub tick: DemoTick,
+    /// The team of the killer at the time of the kill,
+    /// [`Team::Other`] if the player hasn't been seen spawning yet
+    #[serde(default)]
+    pub killer_team: Team,
+    /// The team of the victim at the time of the kill,
+    /// [`Team::Other`] if the player hasn't been seen spawning yet
+    #[serde(default)]
+    pub victim_team: Team,
+    #[serde(default)]
+    pub crit_type: CritType,
+    /// The `customkill` value from the event, such as headshots or backstabs
+    #[serde(default)]
+    pub custom_kill: u16,
+    /// The tick the victim spawned again, `None` if the victim didn't respawn before the end of the demo
+    pub respawn_tick: Option<DemoTick>,
+    /// Whether the kill was a full crit
+    #[serde(default)]
+    pub crit: bool,
+    #[serde(default)]
+    pub headshot: bool,
+    #[serde(default)]
+    pub backstab: bool,
+    /// Whether the killer started dominating the victim with this kill
+    #[serde(default)]
+    pub domination: bool,
+    /// Whether the assister started dominating the victim with this kill
+    #[serde(default)]
+    pub assister_domination: bool,
+    /// Whether the killer got revenge on the victim that was dominating them
+    #[serde(default)]
+    pub revenge: bool,
+    /// Whether the assister got revenge on the victim that was dominating them
+    #[serde(default)]
+    pub assister_revenge: bool,
 }
 
 impl Death {
     pub fn from_event(event: &PlayerDeathEvent, tick: DemoTick) -> Self {
-        let assister = if event.assister < (16 * 1024) {
-            Some(UserId::from(event.assister))
-        } else {
-            None
+        const TF_DEATH_DOMINATION: u16 = 0x0001;
+        const TF_DEATH_ASSISTER_DOMINATION: u16 = 0x0002;
+        const TF_DEATH_REVENGE: u16 = 0x0004;
+        const TF_DEATH_ASSISTER_REVENGE: u16 = 0x0008;
+
+        let assister = match event.assister {
+            // the event sends -1 as the user id when there is no assister
+            u16::MAX => None,
+            assister => Some(UserId::from(assister)),
         };
         Death {
             assister,
@@ -322,8 +468,47 @@ impl Death {
             killer: UserId::from(event.attacker),
             weapon: event.weapon.to_string(),
             victim: UserId::from(event.user_id),
+            killer_team: Team::default(),
+            victim_team: Team::default(),
+            crit_type: CritType::new(event.crit_type),
+            custom_kill: event.custom_kill,
+            respawn_tick: None,
+            crit: CritType::new(event.crit_type) == CritType::Full,
+            headshot: matches!(
+                event.custom_kill,
+                TF_DMG_CUSTOM_HEADSHOT | TF_DMG_CUSTOM_HEADSHOT_DECAPITATION
+            ),
+            backstab: event.custom_kill == TF_DMG_CUSTOM_BACKSTAB,
+            domination: event.death_flags & TF_DEATH_DOMINATION != 0,
+            assister_domination: event.death_flags & TF_DEATH_ASSISTER_DOMINATION != 0,
+            revenge: event.death_flags & TF_DEATH_REVENGE != 0,
+            assister_revenge: event.death_flags & TF_DEATH_ASSISTER_REVENGE != 0,
         }
     }
+
+    /// Get the display name of the weapon used for the kill, or the raw weapon identifier if it isn't known
+    pub fn weapon_name(&self) -> &str {
+        normalize_weapon(&self.weapon).unwrap_or(&self.weapon)
+    }
+
+    /// Whether the kill was made with a taunt attack, such as the hadouken or the armageddon
+    pub fn is_taunt_kill(&self) -> bool {
+        matches!(
+            self.custom_kill,
+            TF_DMG_CUSTOM_TAUNTATK_HADOUKEN
+                | TF_DMG_CUSTOM_TAUNTATK_HIGH_NOON
+                | TF_DMG_CUSTOM_TAUNTATK_GRAND_SLAM
+                | TF_DMG_CUSTOM_TAUNTATK_FENCING
+                | TF_DMG_CUSTOM_TAUNTATK_ARROW_STAB
+                | TF_DMG_CUSTOM_TAUNTATK_GRENADE
+                | TF_DMG_CUSTOM_TAUNTATK_BARBARIAN_SWING
+                | TF_DMG_CUSTOM_TAUNTATK_UBERSLICE
+                | TF_DMG_CUSTOM_TAUNTATK_ENGINEER_GUITAR_SMASH
+                | TF_DMG_CUSTOM_TAUNTATK_ENGINEER_ARM_KILL
+                | TF_DMG_CUSTOM_TAUNTATK_ARMAGEDDON
+                | TF_DMG_CUSTOM_TAUNTATK_ALLCLASS_GUITAR_RIFF
+        )
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -331,6 +516,8 @@ pub struct Round {
     pub winner: Team,
     pub length: f32,
     pub end_tick: DemoTick,
+    /// Whether the full round is finished, this is `false` when only a stage of a multi-stage map is finished
+    pub full_round: bool,
 }
 
 impl Round {
@@ -339,20 +526,253 @@ impl Round {
             winner: Team::new(event.team),
             length: event.round_time,
             end_tick: tick,
+            full_round: event.full_round != 0,
+        }
+    }
+}
+
+/// Time a player spent on a control point, in ticks
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectiveTime {
+    /// Ticks spent capturing a point
+    pub capturing_ticks: u32,
+    /// Ticks spent blocking an enemy capture
+    pub blocking_ticks: u32,
+}
+
+/// Damage and healing done by a player while playing a single class
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct ClassStats {
+    pub damage: u32,
+    pub healing: u32,
+}
+
+/// What dealt damage to a player
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DamageSource {
+    /// Environmental damage such as fall damage, drowning or map hazards
+    World,
+    /// Damage from a weapon, by the weapon id from the `player_hurt` event
+    Weapon(u16),
+}
+
+/// A point where the server tick jumped backwards, for example in concatenated demos
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TickDiscontinuity {
+    pub previous: ServerTick,
+    pub next: ServerTick,
+}
+
+/// A period during which the game was paused
+///
+/// The server tick doesn't advance while paused, the demo tick does.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Pause {
+    pub start: DemoTick,
+    pub end: DemoTick,
+}
+
+impl Pause {
+    pub fn ticks(&self) -> u32 {
+        u32::from(self.end).saturating_sub(u32::from(self.start))
+    }
+}
+
+/// A control point being captured by a team
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PointCapture {
+    pub point: u8,
+    pub team: Team,
+    pub tick: DemoTick,
+    /// The players standing on the point when it was captured
+    #[serde(default)]
+    pub cappers: Vec<UserId>,
+}
+
+/// A team defending their last control point after the enemy captured the point in front of it
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Hold {
+    pub team: Team,
+    pub start_tick: DemoTick,
+    pub end_tick: DemoTick,
+    /// Whether the team kept their last point, either by pushing out or by the round ending otherwise
+    pub held: bool,
+}
+
+/// The number of events in a fixed time window of the match
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub struct TimeBin {
+    /// The start of the window in seconds since the start of the demo
+    pub start: f32,
+    /// The end of the window in seconds, the last window ends at the end of the demo
+    pub end: f32,
+    /// Kills made by players, excluding suicides and environmental deaths
+    pub kills: u32,
+    pub deaths: u32,
+    pub captures: u32,
+}
+
+/// The server switching to a different map while the demo was recording
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MapChange {
+    pub tick: DemoTick,
+    /// The name of the new map
+    pub map: String,
+}
+
+/// Why a player was moved to another team by the server
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BalanceReason {
+    /// The player was moved to even out the team sizes
+    Autobalance,
+    /// The teams were scrambled after the server announced a scramble
+    Scramble,
+    /// The teams were switched without a scramble being announced, such as between the halves of a stopwatch match
+    Switch,
+}
+
+/// A player being moved to another team by the server instead of picking a team themselves
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TeamBalance {
+    pub tick: DemoTick,
+    pub user: UserId,
+    /// The team the player was moved to
+    pub team: Team,
+    pub reason: BalanceReason,
+}
+
+/// The top players of a round as shown on the panel at the end of the round
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WinPanel {
+    /// The index of the finished round in the list of rounds, the panel is shown before the round win is recorded
+    pub round: usize,
+    pub tick: DemoTick,
+    /// The top players of the round with the points they scored during the round
+    pub players: Vec<(UserId, u32)>,
+}
+
+/// The kind of interaction with the intelligence in capture the flag maps
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FlagEventType {
+    Pickup,
+    Capture,
+    Defend,
+    Dropped,
+    Returned,
+}
+
+impl FlagEventType {
+    /// Get the type from the `eventtype` of a `teamplay_flag_event`, `None` for unknown types
+    pub fn new(number: u16) -> Option<Self> {
+        match number {
+            1 => Some(FlagEventType::Pickup),
+            2 => Some(FlagEventType::Capture),
+            3 => Some(FlagEventType::Defend),
+            4 => Some(FlagEventType::Dropped),
+            5 => Some(FlagEventType::Returned),
+            _ => None,
+        }
+    }
+}
+
+/// A player interacting with the intelligence in capture the flag maps
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FlagEvent {
+    pub player: UserId,
+    pub event_type: FlagEventType,
+    pub tick: DemoTick,
+}
+
+/// The kind of object from the `objecttype` of building events
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ObjectType {
+    Dispenser,
+    Teleporter,
+    Sentry,
+    Sapper,
+}
+
+impl ObjectType {
+    /// Get the type from the `objecttype` of a building event, `None` for unknown types
+    pub fn new(number: u16) -> Option<Self> {
+        match number {
+            0 => Some(ObjectType::Dispenser),
+            1 => Some(ObjectType::Teleporter),
+            2 => Some(ObjectType::Sentry),
+            3 => Some(ObjectType::Sapper),
+            _ => None,
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildingEventType {
+    /// The object was placed, from the `player_builtobject` event
+    Built,
+    /// The object was destroyed by another player, from the `object_destroyed` event
+    Destroyed,
+}
+
+/// An engineer building or spy sapper being placed or destroyed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildingEvent {
+    /// The player that placed the object
+    pub builder: UserId,
+    pub object_type: ObjectType,
+    pub event: BuildingEventType,
+    /// The player that destroyed the object, `None` for placed objects or objects destroyed by the world
+    pub attacker: Option<UserId>,
+    pub tick: DemoTick,
+}
+
+/// A capture in progress, the list of cappers comes from the `teamplay_point_startcapture` event
+#[derive(Default, Debug, Serialize, Deserialize, PartialEq)]
+struct ActiveCapture {
+    start: DemoTick,
+    cappers: Vec<UserId>,
+    blockers: Vec<(UserId, DemoTick)>,
+}
+
+/// Whether the SourceTV camera target was picked by the automatic director or forced by a person
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FocusSource {
+    Auto,
+    Manual,
+}
+
+/// A change in the player the SourceTV director is focused on
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DirectorFocus {
+    pub tick: DemoTick,
+    pub target: Option<UserId>,
+    pub source: FocusSource,
+}
+
 #[derive(Default, Debug, Serialize, Deserialize, PartialEq)]
 pub struct World {
     pub boundary_min: Vector,
     pub boundary_max: Vector,
 }
 
-#[derive(Default, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct Analyser {
     state: MatchState,
     user_id_map: HashMap<EntityId, UserId>,
+    active_captures: BTreeMap<u8, ActiveCapture>,
+    cameraman_active: bool,
+    current_class: HashMap<UserId, Class>,
+    last_tick: ServerTick,
+    pause_start: Option<DemoTick>,
+    scramble_pending: bool,
 }
 
 impl MessageHandler for Analyser {
@@ -365,21 +785,78 @@ impl MessageHandler for Analyser {
                 | MessageType::UserMessage
                 | MessageType::ServerInfo
                 | MessageType::NetTick
+                | MessageType::GameEventList
+                | MessageType::SetPause
         )
     }
 
-    fn handle_message(&mut self, message: &Message, tick: DemoTick, _parser_state: &ParserState) {
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
         match message {
             Message::NetTick(msg) => {
-                if self.state.start_tick == 0 {
+                if msg.tick < self.last_tick {
+                    self.state.tick_discontinuities.push(TickDiscontinuity {
+                        previous: self.last_tick,
+                        next: msg.tick,
+                    });
+                }
+                self.last_tick = msg.tick;
+
+                if self.state.start_tick == 0 || msg.tick < self.state.start_tick {
                     self.state.start_tick = msg.tick;
                 }
+                self.state.end_tick = tick;
             }
             Message::ServerInfo(message) => {
-                self.state.interval_per_tick = message.interval_per_tick
+                self.state.interval_per_tick = message.interval_per_tick;
+                if !self.state.map.is_empty() && self.state.map != message.map {
+                    self.state.map_changes.push(MapChange {
+                        tick,
+                        map: message.map.clone(),
+                    });
+                }
+                self.state.map = message.map.clone();
             }
             Message::GameEvent(message) => self.handle_event(&message.event, tick),
-            Message::UserMessage(message) => self.handle_user_message(message, tick),
+            Message::SetPause(message) => {
+                if message.pause {
+                    self.pause_start.get_or_insert(tick);
+                } else if let Some(start) = self.pause_start.take() {
+                    self.state.pauses.push(Pause { start, end: tick });
+                }
+            }
+            Message::GameEventList(_) => {
+                self.state.event_list_tick.get_or_insert(tick);
+            }
+            Message::UserMessage(message) => self.handle_user_message(message, tick, parser_state),
+            _ => {}
+        }
+    }
+
+    fn handle_message_before_range(
+        &mut self,
+        message: &Message,
+        tick: DemoTick,
+        parser_state: &ParserState,
+    ) {
+        // only keep track of the player state that events in the range depend on
+        match message {
+            Message::GameEvent(message) => {
+                if let GameEvent::PlayerSpawn(event) = &message.event {
+                    let spawn = Spawn::from_event(event, tick);
+                    if let Some(user_state) = self.state.users.get_mut(&spawn.user) {
+                        user_state.team = spawn.team;
+                    }
+                    self.current_class.insert(spawn.user, spawn.class);
+                }
+            }
+            Message::UserMessage(UserMessage::SayText2(text_message))
+                if text_message.kind == ChatMessageKind::NameChange =>
+            {
+                if let Some(from) = text_message.from.clone() {
+                    let user = parser_state.user_id_for_entity(text_message.client);
+                    self.change_name(user, from.into(), text_message.plain_text());
+                }
+            }
             _ => {}
         }
     }
@@ -411,52 +888,402 @@ impl BorrowMessageHandler for Analyser {
     }
 }
 
+impl Default for Analyser {
+    fn default() -> Self {
+        Analyser {
+            state: MatchState {
+                parser_version: env!("CARGO_PKG_VERSION").to_string(),
+                ..MatchState::default()
+            },
+            user_id_map: HashMap::new(),
+            active_captures: BTreeMap::new(),
+            cameraman_active: false,
+            current_class: HashMap::new(),
+            last_tick: ServerTick::default(),
+            pause_start: None,
+            scramble_pending: false,
+        }
+    }
+}
+
 impl Analyser {
     pub fn new() -> Self {
         Self::default()
     }
 
-    fn handle_user_message(&mut self, message: &UserMessage, tick: DemoTick) {
+    fn handle_user_message(
+        &mut self,
+        message: &UserMessage,
+        tick: DemoTick,
+        parser_state: &ParserState,
+    ) {
         if let UserMessage::SayText2(text_message) = message {
+            // messages printed by server plugins have no sender name but are still sent with a player entity
+            let user = text_message
+                .from
+                .as_ref()
+                .filter(|from| !from.as_ref().is_empty())
+                .and_then(|_| parser_state.user_id_for_entity(text_message.client));
             if text_message.kind == ChatMessageKind::NameChange {
                 if let Some(from) = text_message.from.clone() {
-                    self.change_name(from.into(), text_message.plain_text());
+                    self.change_name(user, from.into(), text_message.plain_text());
                 }
             } else {
-                self.state
-                    .chat
-                    .push(ChatMessage::from_message(text_message, tick));
+                self.state.chat.push(ChatMessage {
+                    user,
+                    ..ChatMessage::from_message(text_message, tick)
+                });
             }
         }
     }
 
-    fn change_name(&mut self, from: String, to: String) {
-        if let Some(user) = self.state.users.values_mut().find(|user| user.name == from) {
+    /// Rename the sender of a name change message, players are matched by their old name
+    /// only if the sending entity isn't known
+    fn change_name(&mut self, user: Option<UserId>, from: String, to: String) {
+        let user = match user {
+            Some(user) => self.state.users.get_mut(&user),
+            None => self.state.users.values_mut().find(|user| user.name == from),
+        };
+        if let Some(user) = user {
             user.name = to;
         }
     }
 
     fn handle_event(&mut self, event: &GameEvent, tick: DemoTick) {
         const WIN_REASON_TIME_LIMIT: u8 = 6;
+        const HUD_ALERT_SCRAMBLE_TEAMS: u16 = 0;
 
         match event {
-            GameEvent::PlayerDeath(event) => self.state.deaths.push(Death::from_event(event, tick)),
+            GameEvent::PlayerDeath(event) => {
+                let mut death = Death::from_event(event, tick);
+                death.killer_team = self.team_of(death.killer);
+                death.victim_team = self.team_of(death.victim);
+                let dominators = [
+                    Some(death.killer).filter(|_| death.domination),
+                    death.assister.filter(|_| death.assister_domination),
+                ];
+                for dominator in dominators.into_iter().flatten() {
+                    if let Some(user) = self.state.users.get_mut(&dominator) {
+                        user.dominations += 1;
+                    }
+                }
+                self.state.deaths.push(death);
+            }
             GameEvent::PlayerSpawn(event) => {
                 let spawn = Spawn::from_event(event, tick);
                 if let Some(user_state) = self.state.users.get_mut(&spawn.user) {
                     user_state.classes[spawn.class] += 1;
+                    if user_state.team != spawn.team {
+                        self.state.team_changes.push(TeamChange {
+                            user: spawn.user,
+                            from: user_state.team,
+                            to: spawn.team,
+                            tick,
+                        });
+                    }
                     user_state.team = spawn.team;
                 }
+                if let Some(death) = self
+                    .state
+                    .deaths
+                    .iter_mut()
+                    .rev()
+                    .find(|death| death.victim == spawn.user)
+                {
+                    // only the first spawn after a death is the respawn
+                    death.respawn_tick.get_or_insert(tick);
+                }
+                let previous_class = self.current_class.insert(spawn.user, spawn.class);
+                if previous_class != Some(spawn.class) {
+                    self.state.class_changes.push(ClassChange {
+                        user: spawn.user,
+                        from: previous_class.unwrap_or_default(),
+                        to: spawn.class,
+                        tick,
+                    });
+                }
+                self.state.spawns.push(spawn);
+            }
+            GameEvent::PlayerChangeClass(event) => {
+                self.state
+                    .class_intents
+                    .push(ClassIntent::from_event(event, tick));
+            }
+            GameEvent::PlayerHurt(event) => {
+                if event.attacker == 0 {
+                    self.state.world_damage += event.damage_amount as u32;
+                } else if event.attacker != event.user_id {
+                    if let Some(attacker) = self.state.users.get_mut(&event.attacker.into()) {
+                        attacker.damage_dealt += event.damage_amount as u32;
+                    }
+                    self.class_stats_for(UserId::from(event.attacker)).damage +=
+                        event.damage_amount as u32;
+                    *self.state.weapon_damage.entry(event.weapon_id).or_default() +=
+                        event.damage_amount as u32;
+                }
+            }
+            GameEvent::PlayerHealed(event) => {
+                if event.healer != 0 && event.healer != event.patient {
+                    self.class_stats_for(UserId::from(event.healer)).healing += event.amount as u32;
+                }
             }
             GameEvent::TeamPlayRoundWin(event) => {
                 if event.win_reason != WIN_REASON_TIME_LIMIT {
                     self.state.rounds.push(Round::from_event(event, tick))
                 }
+                self.end_all_captures(tick);
+            }
+            GameEvent::TeamPlayRoundStart(_) => self.end_all_captures(tick),
+            GameEvent::TeamPlayRestartRound(_) => self.state.match_start_tick = Some(tick),
+            GameEvent::TeamPlayRoundActive(_) => self.scramble_pending = false,
+            GameEvent::TeamPlayAlert(event) if event.alert_type == HUD_ALERT_SCRAMBLE_TEAMS => {
+                self.scramble_pending = true
+            }
+            GameEvent::PlayerTeam(event) => self.handle_team_change(event, tick),
+            GameEvent::TeamPlayTeamBalancedPlayer(event) => {
+                if let Some(user) = self.user_for_entity(EntityId::from(event.player as u32)) {
+                    self.mark_autobalance(user, Team::new(event.team), tick);
+                }
+            }
+            GameEvent::TeamPlayWinPanel(event) => self.handle_win_panel(event, tick),
+            GameEvent::TeamPlayFlagEvent(event) => {
+                // the player is send as entity index
+                let player = self.user_for_entity(EntityId::from(event.player as u32));
+                if let (Some(player), Some(event_type)) =
+                    (player, FlagEventType::new(event.event_type))
+                {
+                    self.state.flag_events.push(FlagEvent {
+                        player,
+                        event_type,
+                        tick,
+                    });
+                }
+            }
+            GameEvent::PlayerBuiltObject(event) => {
+                if let Some(object_type) = ObjectType::new(event.object) {
+                    self.state.building_events.push(BuildingEvent {
+                        builder: UserId::from(event.user_id),
+                        object_type,
+                        event: BuildingEventType::Built,
+                        attacker: None,
+                        tick,
+                    });
+                }
+            }
+            GameEvent::ObjectDestroyed(event) => {
+                if let Some(object_type) = ObjectType::new(event.object_type) {
+                    self.state.building_events.push(BuildingEvent {
+                        builder: UserId::from(event.user_id),
+                        object_type,
+                        event: BuildingEventType::Destroyed,
+                        attacker: (event.attacker != 0).then_some(UserId::from(event.attacker)),
+                        tick,
+                    });
+                }
+            }
+            GameEvent::TeamPlayPointStartCapture(event) => self.start_capture(event, tick),
+            GameEvent::TeamPlayPointCaptured(event) => {
+                let cappers = self.users_for_cappers(&event.cappers);
+                self.state.captures.push(PointCapture {
+                    point: event.cp,
+                    team: Team::new(event.team),
+                    tick,
+                    cappers,
+                });
+                self.end_capture(event.cp, tick)
+            }
+            GameEvent::TeamPlayCaptureBroken(event) => self.end_capture(event.cp, tick),
+            GameEvent::TeamPlayCaptureBlocked(event) => self.block_capture(event, tick),
+            GameEvent::HLTVCameraman(event) => self.cameraman_active = event.index != 0,
+            GameEvent::HLTVChase(event) => {
+                self.change_focus(event.target_1, self.director_source(), tick)
+            }
+            GameEvent::HLTVFixed(event) => {
+                self.change_focus(event.target, self.director_source(), tick)
+            }
+            GameEvent::HLTVChangedTarget(event) => {
+                self.change_focus(event.obs_target, FocusSource::Manual, tick)
             }
             _ => {}
         }
     }
 
+    fn handle_team_change(&mut self, event: &PlayerTeamEvent, tick: DemoTick) {
+        let (old_team, team) = (Team::new(event.old_team), Team::new(event.team));
+        // team changes forced by the server are silent, players picking a team themselves aren't
+        if !event.silent || event.disconnect || !old_team.is_player() || !team.is_player() {
+            return;
+        }
+        let reason = if self.scramble_pending {
+            BalanceReason::Scramble
+        } else {
+            BalanceReason::Switch
+        };
+        self.state.balances.push(TeamBalance {
+            tick,
+            user: UserId::from(event.user_id),
+            team,
+            reason,
+        });
+    }
+
+    fn mark_autobalance(&mut self, user: UserId, team: Team, tick: DemoTick) {
+        // the team change itself is send right before the balance event
+        let existing = self
+            .state
+            .balances
+            .iter_mut()
+            .rev()
+            .take_while(|balance| balance.tick == tick)
+            .find(|balance| balance.user == user);
+        match existing {
+            Some(balance) => balance.reason = BalanceReason::Autobalance,
+            None => self.state.balances.push(TeamBalance {
+                tick,
+                user,
+                team,
+                reason: BalanceReason::Autobalance,
+            }),
+        }
+    }
+
+    fn team_of(&self, user: UserId) -> Team {
+        self.state
+            .users
+            .get(&user)
+            .map(|user| user.team)
+            .unwrap_or_default()
+    }
+
+    fn class_stats_for(&mut self, user: UserId) -> &mut ClassStats {
+        let class = self.current_class.get(&user).copied().unwrap_or_default();
+        self.state
+            .class_stats
+            .entry(user)
+            .or_default()
+            .entry(class)
+            .or_default()
+    }
+
+    fn user_for_entity(&self, entity_id: EntityId) -> Option<UserId> {
+        self.state
+            .users
+            .values()
+            .find(|user| user.entity_id == entity_id)
+            .map(|user| user.user_id)
+    }
+
+    fn director_source(&self) -> FocusSource {
+        // while a cameraman is active the director just relays their camera
+        if self.cameraman_active {
+            FocusSource::Manual
+        } else {
+            FocusSource::Auto
+        }
+    }
+
+    fn handle_win_panel(&mut self, event: &TeamPlayWinPanelEvent, tick: DemoTick) {
+        // the top players are send as player entity indexes, with 0 for empty slots
+        let players = [
+            (event.player_1, event.player_1_points),
+            (event.player_2, event.player_2_points),
+            (event.player_3, event.player_3_points),
+        ]
+        .into_iter()
+        .filter(|(entity, _)| *entity != 0)
+        .filter_map(|(entity, points)| {
+            self.user_for_entity(EntityId::from(entity as u32))
+                .map(|user| (user, points as u32))
+        })
+        .collect();
+
+        self.state.win_panels.push(WinPanel {
+            round: self.state.rounds.len(),
+            tick,
+            players,
+        });
+    }
+
+    fn change_focus(&mut self, target_entity: u16, source: FocusSource, tick: DemoTick) {
+        let target = match target_entity {
+            0 => None,
+            entity => self.user_for_entity(EntityId::from(entity as u32)),
+        };
+        let unchanged = self
+            .state
+            .director_focus
+            .last()
+            .map(|focus| focus.target == target && focus.source == source)
+            .unwrap_or_default();
+        if !unchanged {
+            self.state.director_focus.push(DirectorFocus {
+                tick,
+                target,
+                source,
+            });
+        }
+    }
+
+    fn start_capture(&mut self, event: &TeamPlayPointStartCaptureEvent, tick: DemoTick) {
+        self.end_capture(event.cp, tick);
+
+        let cappers = self.users_for_cappers(&event.cappers);
+        self.active_captures.insert(
+            event.cp,
+            ActiveCapture {
+                start: tick,
+                cappers,
+                blockers: Vec::new(),
+            },
+        );
+    }
+
+    /// The cappers are send as a string with one byte per player entity index
+    fn users_for_cappers(&self, cappers: &MaybeUtf8String) -> Vec<UserId> {
+        cappers
+            .as_bytes()
+            .iter()
+            .filter_map(|entity| self.user_for_entity(EntityId::from(*entity as u32)))
+            .collect()
+    }
+
+    fn block_capture(&mut self, event: &TeamPlayCaptureBlockedEvent, tick: DemoTick) {
+        if let Some(blocker) = self.user_for_entity(EntityId::from(event.blocker as u32)) {
+            if let Some(capture) = self.active_captures.get_mut(&event.cp) {
+                if !capture.blockers.iter().any(|(user, _)| *user == blocker) {
+                    capture.blockers.push((blocker, tick));
+                }
+            }
+        }
+    }
+
+    fn end_capture(&mut self, cp: u8, tick: DemoTick) {
+        if let Some(capture) = self.active_captures.remove(&cp) {
+            let capture_ticks = u32::from(tick).saturating_sub(capture.start.into());
+            for capper in capture.cappers {
+                self.state
+                    .objective_times
+                    .entry(capper)
+                    .or_default()
+                    .capturing_ticks += capture_ticks;
+            }
+            for (blocker, start) in capture.blockers {
+                self.state
+                    .objective_times
+                    .entry(blocker)
+                    .or_default()
+                    .blocking_ticks += u32::from(tick).saturating_sub(start.into());
+            }
+        }
+    }
+
+    fn end_all_captures(&mut self, tick: DemoTick) {
+        let points: Vec<u8> = self.active_captures.keys().copied().collect();
+        for cp in points {
+            self.end_capture(cp, tick);
+        }
+    }
+
     fn parse_user_info(
         &mut self,
         index: usize,
@@ -479,13 +1306,1535 @@ impl Analyser {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Default)]
+/// A series of kills by a player without dying in between
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
-pub struct MatchState {
-    pub chat: Vec<ChatMessage>,
+pub struct KillStreak {
+    pub player: UserId,
+    pub count: u32,
+    /// The tick of the first kill of the streak
+    pub start_tick: DemoTick,
+    /// The tick of the last kill of the streak
+    pub end_tick: DemoTick,
+}
+
+/// A player and their number of kills
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Fragger {
+    pub user: UserId,
+    pub name: String,
+    pub kills: u32,
+}
+
+/// A player as shown in the kill feed
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KillFeedPlayer {
+    pub user: UserId,
+    pub name: String,
+    pub team: Team,
+}
+
+/// A kill as it is shown in the in-game kill feed
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct KillFeedEntry {
+    pub tick: DemoTick,
+    /// The killer, `None` for suicides and deaths not caused by a player
+    pub killer: Option<KillFeedPlayer>,
+    pub assister: Option<KillFeedPlayer>,
+    pub victim: KillFeedPlayer,
+    pub weapon: String,
+    pub crit_type: CritType,
+    pub custom_kill: u16,
+}
+
+/// The kind of event a row of [`MatchState::to_event_rows`] was created from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EventType {
+    Death,
+    Chat,
+    Spawn,
+    ClassChange,
+    Capture,
+    RoundWin,
+    Balance,
+}
+
+/// A single event of the match with the columns shared between all event types,
+/// columns that don't apply to the event are `None`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EventRow {
+    pub tick: DemoTick,
+    /// Seconds since the start of the demo
+    pub seconds: f32,
+    pub event_type: EventType,
+    /// The player causing the event, such as the killer or the player sending a chat message
+    pub actor: Option<UserId>,
+    /// The player affected by the event, such as the victim of a kill
+    pub target: Option<UserId>,
+    pub team: Option<Team>,
+    pub class: Option<Class>,
+    /// The weapon of a kill, the text of a chat message or the index of a captured point
+    pub value: Option<String>,
+}
+
+/// Small pre-aggregated overview of a match
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchSummary {
+    pub map: String,
+    /// Duration of the match in seconds, excluding any time the game was paused
+    pub duration: f32,
+    pub player_count: usize,
+    pub red_round_wins: u32,
+    pub blue_round_wins: u32,
+    pub total_kills: u32,
+    /// The three players with the most kills, sorted by kills descending
+    pub top_fraggers: Vec<Fragger>,
+}
+
+/// The competitive format of a match
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchFormat {
+    Sixes,
+    Highlander,
+    Fours,
+    Other,
+}
+
+/// The formats a [`MatchState`] can be written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Compact json on a single line
+    Json,
+    /// Indented json
+    PrettyJson,
+    /// Newline delimited json with one record per line
+    ///
+    /// The first line contains the match state with all lists, such as the deaths, chat and rounds, left empty.
+    /// Every following line contains a single entry of one of those lists, as an object with the name of the list
+    /// as the only key.
+    NdJson,
+    /// The deaths as csv, see [`MatchState::deaths_to_csv`]
+    DeathsCsv,
+    /// The chat messages as csv, see [`MatchState::chat_to_csv`]
+    ChatCsv,
+    /// Bincode, requires the `bincode` feature
+    #[cfg(feature = "bincode")]
+    Bincode,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchState {
+    pub map: String,
+    pub chat: Vec<ChatMessage>,
     pub users: BTreeMap<UserId, UserInfo>,
     pub deaths: Vec<Death>,
     pub rounds: Vec<Round>,
     pub start_tick: ServerTick,
+    /// The demo tick of the last network tick
+    pub end_tick: DemoTick,
     pub interval_per_tick: f32,
+    pub objective_times: BTreeMap<UserId, ObjectiveTime>,
+    pub director_focus: Vec<DirectorFocus>,
+    pub class_stats: BTreeMap<UserId, BTreeMap<Class, ClassStats>>,
+    pub tick_discontinuities: Vec<TickDiscontinuity>,
+    pub weapon_damage: BTreeMap<u16, u32>,
+    pub world_damage: u32,
+    /// The raw demo tick of the packet containing the game event definitions,
+    /// game events from before this packet can't be decoded.
+    ///
+    /// Note that packets sent during signon carry the tick from before the demo started syncing,
+    /// so this can be larger than the ticks of later packets.
+    pub event_list_tick: Option<DemoTick>,
+    pub pauses: Vec<Pause>,
+    /// Classes picked by players, separate from the classes they actually spawned as
+    pub class_intents: Vec<ClassIntent>,
+    pub captures: Vec<PointCapture>,
+    pub win_panels: Vec<WinPanel>,
+    pub spawns: Vec<Spawn>,
+    /// Changes to a different map after the demo started, [`MatchState::map`] is the last map of the demo.
+    ///
+    /// Stats are not reset on a map change, use the ticks of the changes to split the stats per map.
+    pub map_changes: Vec<MapChange>,
+    /// Players moved to another team by the server, either by autobalance or by a scramble or switch of the teams
+    pub balances: Vec<TeamBalance>,
+    /// The version of the parser that produced this state, for detecting outdated cached results.
+    ///
+    /// Empty for states produced before the version was recorded.
+    #[serde(default)]
+    pub parser_version: String,
+    /// The tick the match was restarted after both teams readied up in tournament mode,
+    /// any kills or damage before this tick happened during the warmup.
+    ///
+    /// If the match was restarted multiple times, the last restart is used.
+    #[serde(default)]
+    pub match_start_tick: Option<DemoTick>,
+    /// Pickups, captures, defenses and drops of the intelligence in capture the flag maps
+    #[serde(default)]
+    pub flag_events: Vec<FlagEvent>,
+    /// Buildings and sappers being placed and destroyed
+    #[serde(default)]
+    pub building_events: Vec<BuildingEvent>,
+    /// Players spawning as a different class than their previous spawn, sorted by tick
+    #[serde(default)]
+    pub class_changes: Vec<ClassChange>,
+    /// Players spawning on a different team than their previous spawn, sorted by tick
+    #[serde(default)]
+    pub team_changes: Vec<TeamChange>,
+}
+
+/// Write a row of csv fields, quoting fields that contain separators, quotes or newlines
+fn write_csv_row<W: Write>(writer: &mut W, fields: &[&str]) -> io::Result<()> {
+    for (index, field) in fields.iter().enumerate() {
+        if index > 0 {
+            writer.write_all(b",")?;
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            write!(writer, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            writer.write_all(field.as_bytes())?;
+        }
+    }
+    writer.write_all(b"\n")
+}
+
+impl MatchState {
+    /// Write the match state to `writer` in the requested format
+    pub fn write<W: Write>(&self, format: OutputFormat, mut writer: W) -> io::Result<()> {
+        match format {
+            OutputFormat::Json => serde_json::to_writer(&mut writer, self)?,
+            OutputFormat::PrettyJson => serde_json::to_writer_pretty(&mut writer, self)?,
+            OutputFormat::NdJson => self.write_ndjson(&mut writer)?,
+            OutputFormat::DeathsCsv => self.write_deaths_csv(&mut writer)?,
+            OutputFormat::ChatCsv => self.write_chat_csv(&mut writer)?,
+            #[cfg(feature = "bincode")]
+            OutputFormat::Bincode => {
+                bincode::serialize_into(&mut writer, self).map_err(io::Error::other)?
+            }
+        }
+        writer.flush()
+    }
+
+    fn write_ndjson<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut state = serde_json::to_value(self)?;
+        let mut records = Vec::new();
+        if let serde_json::Value::Object(fields) = &mut state {
+            for (name, value) in fields.iter_mut() {
+                if let serde_json::Value::Array(list) = value {
+                    records.extend(list.drain(..).map(|record| (name.clone(), record)));
+                }
+            }
+        }
+
+        serde_json::to_writer(&mut writer, &state)?;
+        writer.write_all(b"\n")?;
+        for (name, record) in records {
+            serde_json::to_writer(&mut writer, &BTreeMap::from([(name, record)]))?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Write the deaths as csv with a header row, with the user ids resolved to player names
+    ///
+    /// The killer is empty for deaths not caused by a player.
+    pub fn deaths_to_csv<W: Write>(&self, writer: W) -> io::Result<()> {
+        self.write(OutputFormat::DeathsCsv, writer)
+    }
+
+    fn write_deaths_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let names = self.name_table();
+        let id = |user: Option<UserId>| user.map(|user| u16::from(user).to_string());
+        let name = |user: Option<UserId>| user.and_then(|user| names.get(&user).copied());
+
+        write_csv_row(
+            &mut writer,
+            &[
+                "tick",
+                "killer",
+                "killer_name",
+                "killer_team",
+                "assister",
+                "assister_name",
+                "victim",
+                "victim_name",
+                "victim_team",
+                "weapon",
+                "crit_type",
+            ],
+        )?;
+        for death in &self.deaths {
+            let killer = (death.killer != 0).then_some(death.killer);
+            write_csv_row(
+                &mut writer,
+                &[
+                    &u32::from(death.tick).to_string(),
+                    &id(killer).unwrap_or_default(),
+                    name(killer).unwrap_or_default(),
+                    &death.killer_team.to_string(),
+                    &id(death.assister).unwrap_or_default(),
+                    name(death.assister).unwrap_or_default(),
+                    &id(Some(death.victim)).unwrap_or_default(),
+                    name(Some(death.victim)).unwrap_or_default(),
+                    &death.victim_team.to_string(),
+                    &death.weapon,
+                    &death.crit_type.to_string(),
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Write the chat messages as csv with a header row
+    pub fn chat_to_csv<W: Write>(&self, writer: W) -> io::Result<()> {
+        self.write(OutputFormat::ChatCsv, writer)
+    }
+
+    fn write_chat_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write_csv_row(&mut writer, &["tick", "kind", "from", "text"])?;
+        for message in &self.chat {
+            write_csv_row(
+                &mut writer,
+                &[
+                    &u32::from(message.tick).to_string(),
+                    &message.kind.to_string(),
+                    &message.from,
+                    &message.text,
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Get the number of ticks a player spent capturing or blocking control points
+    ///
+    /// This is derived from the capture events, a player is considered capturing from the start of the
+    /// capture until the point is captured or the capture is broken. A player is considered blocking from
+    /// the moment they block the capture until the capture ends.
+    pub fn objective_time(&self, user: UserId) -> ObjectiveTime {
+        self.objective_times.get(&user).copied().unwrap_or_default()
+    }
+
+    /// Get the longest killstreak of every player that got at least one kill
+    ///
+    /// A streak is the number of kills a player gets without dying in between, suicides end the streak
+    /// without counting as a kill.
+    pub fn best_killstreaks(&self) -> HashMap<UserId, u16> {
+        let mut current: HashMap<UserId, u16> = HashMap::new();
+        let mut best: HashMap<UserId, u16> = HashMap::new();
+
+        for death in &self.deaths {
+            if death.killer != death.victim && death.killer != 0 {
+                let streak = current.entry(death.killer).or_default();
+                *streak += 1;
+                let best_streak = best.entry(death.killer).or_default();
+                *best_streak = (*best_streak).max(*streak);
+            }
+            current.remove(&death.victim);
+        }
+
+        best
+    }
+
+    /// Get every kill streak of the match, ordered by the first kill of the streak
+    ///
+    /// A streak lasts until the player dies, suicides end the streak without counting as a kill.
+    /// Deaths are processed in the order they happened in the demo, so when a player gets a kill
+    /// and dies in the same tick the order of the events decides if the kill is part of the streak.
+    pub fn kill_streaks(&self) -> Vec<KillStreak> {
+        let mut streaks: Vec<KillStreak> = Vec::new();
+        let mut current: HashMap<UserId, usize> = HashMap::new();
+
+        for death in &self.deaths {
+            if death.killer != death.victim && death.killer != 0 {
+                match current.get(&death.killer) {
+                    Some(index) => {
+                        let streak = &mut streaks[*index];
+                        streak.count += 1;
+                        streak.end_tick = death.tick;
+                    }
+                    None => {
+                        current.insert(death.killer, streaks.len());
+                        streaks.push(KillStreak {
+                            player: death.killer,
+                            count: 1,
+                            start_tick: death.tick,
+                            end_tick: death.tick,
+                        });
+                    }
+                }
+            }
+            current.remove(&death.victim);
+        }
+
+        streaks
+    }
+
+    /// Get the total damage done over the match, split by weapon
+    ///
+    /// Damage not done by a player, such as fall damage, is grouped under [`DamageSource::World`],
+    /// self damage is not counted.
+    pub fn weapon_damage_totals(&self) -> HashMap<DamageSource, u32> {
+        let mut totals: HashMap<DamageSource, u32> = self
+            .weapon_damage
+            .iter()
+            .map(|(weapon, damage)| (DamageSource::Weapon(*weapon), *damage))
+            .collect();
+        if self.world_damage > 0 {
+            totals.insert(DamageSource::World, self.world_damage);
+        }
+        totals
+    }
+
+    /// Get the total time in seconds that the game was paused
+    pub fn paused_time(&self) -> f32 {
+        let ticks: u32 = self.pauses.iter().map(Pause::ticks).sum();
+        ticks as f32 * self.interval_per_tick
+    }
+
+    /// Get the name of every user, for resolving many user ids at once
+    ///
+    /// If a player changed their name during the match, their final name is used.
+    pub fn name_table(&self) -> HashMap<UserId, &str> {
+        self.users
+            .iter()
+            .map(|(user_id, user)| (*user_id, user.name.as_str()))
+            .collect()
+    }
+
+    /// Check if a player was alive at a tick, a player is alive from their spawn until their next death
+    ///
+    /// Players that haven't been seen spawning before the tick are considered dead.
+    /// Note that a spy faking their death with the dead ringer is also considered dead until they respawn.
+    pub fn is_alive(&self, user: UserId, tick: DemoTick) -> bool {
+        let tick = u32::from(tick);
+        let last_spawn = self
+            .spawns
+            .iter()
+            .filter(|spawn| spawn.user == user && u32::from(spawn.tick) <= tick)
+            .map(|spawn| u32::from(spawn.tick))
+            .max();
+        let last_death = self
+            .deaths
+            .iter()
+            .filter(|death| death.victim == user && u32::from(death.tick) <= tick)
+            .map(|death| u32::from(death.tick))
+            .max();
+
+        match (last_spawn, last_death) {
+            (Some(spawn), Some(death)) => spawn > death,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+
+    /// Get the team and tick of the earliest capture of every control point that was captured
+    pub fn first_captures(&self) -> HashMap<u8, (Team, DemoTick)> {
+        let mut first = HashMap::new();
+        for capture in &self.captures {
+            first
+                .entry(capture.point)
+                .or_insert((capture.team, capture.tick));
+        }
+        first
+    }
+
+    /// Get the periods in which a team was pushed back to their last control point
+    ///
+    /// This assumes a symmetric control point map where blue owns the first point and red the last,
+    /// point ownership is reset at the end of every round.
+    ///
+    /// The number of control points isn't part of the game events, it can be read from the objective resource
+    /// with [`GameState::control_point_count`](crate::demo::parser::gamestateanalyser::GameState::control_point_count).
+    /// No holds are returned when the number of control points is unknown.
+    pub fn last_point_holds(&self, point_count: Option<u8>) -> Vec<Hold> {
+        let point_count = match point_count {
+            Some(count) if count >= 3 => count,
+            _ => return Vec::new(),
+        };
+        let last_point = |team: Team| match team {
+            Team::Blue => 0,
+            _ => point_count - 1,
+        };
+        let second_point = |team: Team| match team {
+            Team::Blue => 1,
+            _ => point_count - 2,
+        };
+
+        let mut holds = Vec::new();
+        let mut active: Vec<(Team, DemoTick)> = Vec::new();
+        let mut end_hold = |active: &mut Vec<(Team, DemoTick)>, team: Team, tick, held| {
+            if let Some(index) = active.iter().position(|(hold_team, _)| *hold_team == team) {
+                let (team, start_tick) = active.remove(index);
+                holds.push(Hold {
+                    team,
+                    start_tick,
+                    end_tick: tick,
+                    held,
+                });
+            }
+        };
+
+        let mut round_ends = self.rounds.iter().map(|round| round.end_tick).peekable();
+        for capture in &self.captures {
+            while let Some(round_end) = round_ends.next_if(|end| *end < capture.tick) {
+                for team in [Team::Red, Team::Blue] {
+                    end_hold(&mut active, team, round_end, true);
+                }
+            }
+
+            for team in [Team::Red, Team::Blue] {
+                if capture.team == team {
+                    if capture.point == second_point(team) {
+                        end_hold(&mut active, team, capture.tick, true);
+                    }
+                } else if capture.point == last_point(team) {
+                    end_hold(&mut active, team, capture.tick, false);
+                } else if capture.point == second_point(team)
+                    && !active.iter().any(|(hold_team, _)| *hold_team == team)
+                {
+                    active.push((team, capture.tick));
+                }
+            }
+        }
+
+        let end_tick = round_ends.next().unwrap_or(self.end_tick);
+        for team in [Team::Red, Team::Blue] {
+            end_hold(&mut active, team, end_tick, true);
+        }
+        holds
+    }
+
+    /// Count the kills, deaths and captures in windows of `bin_seconds` from the start of the demo
+    ///
+    /// The final window is cut short at the end of the demo.
+    pub fn histogram(&self, bin_seconds: f32) -> Vec<TimeBin> {
+        if bin_seconds.is_nan() || bin_seconds <= 0.0 || self.interval_per_tick <= 0.0 {
+            return Vec::new();
+        }
+        let duration = u32::from(self.end_tick) as f32 * self.interval_per_tick;
+        let bin_count = ((duration / bin_seconds).ceil() as usize).max(1);
+        let mut bins: Vec<TimeBin> = (0..bin_count)
+            .map(|index| TimeBin {
+                start: index as f32 * bin_seconds,
+                end: ((index + 1) as f32 * bin_seconds).min(duration),
+                ..TimeBin::default()
+            })
+            .collect();
+
+        let bin_for = |tick: DemoTick| {
+            let time = u32::from(tick) as f32 * self.interval_per_tick;
+            ((time / bin_seconds) as usize).min(bin_count - 1)
+        };
+        for death in &self.deaths {
+            let bin = &mut bins[bin_for(death.tick)];
+            bin.deaths += 1;
+            if death.killer != death.victim && death.killer != 0 {
+                bin.kills += 1;
+            }
+        }
+        for capture in &self.captures {
+            bins[bin_for(capture.tick)].captures += 1;
+        }
+        bins
+    }
+
+    /// Get the last death of the losing team in every round, the death that preceded the round loss
+    ///
+    /// Returns the index of the death in [`MatchState::deaths`] together with the losing team,
+    /// rounds that ended in a stalemate or without a death of the losing team are skipped.
+    pub fn round_ending_deaths(&self) -> Vec<(usize, Team)> {
+        let mut round_start = DemoTick::default();
+        let mut result = Vec::new();
+        for round in &self.rounds {
+            let losing_team = match round.winner {
+                Team::Red => Team::Blue,
+                Team::Blue => Team::Red,
+                _ => {
+                    round_start = round.end_tick;
+                    continue;
+                }
+            };
+            let last_death = self.deaths.iter().enumerate().rev().find(|(_, death)| {
+                death.victim_team == losing_team
+                    && death.tick > round_start
+                    && death.tick <= round.end_tick
+            });
+            if let Some((index, _)) = last_death {
+                result.push((index, losing_team));
+            }
+            round_start = round.end_tick;
+        }
+        result
+    }
+
+    /// Guess the competitive format of the match from the number of players and classes per team
+    ///
+    /// The players and classes spawned on each team are counted separately for every round and each team
+    /// is classified by both counts, the most common format is used so that a substitute joining mid-round
+    /// or a round with a lot of offclassing doesn't affect the result.
+    pub fn detect_format(&self) -> MatchFormat {
+        let mut teams: HashMap<(usize, Team), (Vec<UserId>, Vec<Class>)> = HashMap::new();
+        for spawn in &self.spawns {
+            if !matches!(spawn.team, Team::Red | Team::Blue) {
+                continue;
+            }
+            let round = self
+                .rounds
+                .iter()
+                .filter(|round| round.end_tick < spawn.tick)
+                .count();
+            let (users, classes) = teams.entry((round, spawn.team)).or_default();
+            if !users.contains(&spawn.user) {
+                users.push(spawn.user);
+            }
+            if !classes.contains(&spawn.class) {
+                classes.push(spawn.class);
+            }
+        }
+
+        let mut occurrences: BTreeMap<MatchFormat, usize> = BTreeMap::new();
+        for (users, classes) in teams.values() {
+            let format = match (users.len(), classes.len()) {
+                (4, _) => MatchFormat::Fours,
+                (5..=6, _) => MatchFormat::Sixes,
+                // a sixes team with a substitute or a highlander team that is missing players
+                (7, 0..=7) => MatchFormat::Sixes,
+                // one player of every class
+                (7..=10, 8..) => MatchFormat::Highlander,
+                _ => MatchFormat::Other,
+            };
+            *occurrences.entry(format).or_default() += 1;
+        }
+
+        occurrences
+            .into_iter()
+            // prefer a known format when tied
+            .max_by_key(|(format, occurrences)| (*occurrences, *format != MatchFormat::Other))
+            .map(|(format, _)| format)
+            .unwrap_or(MatchFormat::Other)
+    }
+
+    /// Get the average length of a life of every player in seconds
+    ///
+    /// A life lasts from the last spawn of a player until their death. When `include_final_life` is set,
+    /// the lives that were still going at the end of the demo are counted up to the end of the demo,
+    /// otherwise they are ignored.
+    pub fn avg_life_seconds(&self, include_final_life: bool) -> HashMap<UserId, f32> {
+        let mut events: Vec<(DemoTick, bool, UserId)> = self
+            .spawns
+            .iter()
+            .map(|spawn| (spawn.tick, true, spawn.user))
+            .chain(
+                self.deaths
+                    .iter()
+                    .map(|death| (death.tick, false, death.victim)),
+            )
+            .collect();
+        // handle deaths before spawns in the same tick
+        events.sort_by_key(|(tick, is_spawn, _)| (*tick, *is_spawn));
+
+        let mut spawned: HashMap<UserId, DemoTick> = HashMap::new();
+        let mut lives: HashMap<UserId, (u32, u32)> = HashMap::new();
+        let mut end_life = |user: UserId, start: DemoTick, end: DemoTick| {
+            let (count, ticks) = lives.entry(user).or_default();
+            *count += 1;
+            *ticks += u32::from(end) - u32::from(start);
+        };
+        for (tick, is_spawn, user) in events {
+            if is_spawn {
+                spawned.insert(user, tick);
+            } else if let Some(start) = spawned.remove(&user) {
+                end_life(user, start, tick);
+            }
+        }
+        if include_final_life {
+            for (user, start) in spawned {
+                end_life(user, start, self.end_tick.max(start));
+            }
+        }
+
+        lives
+            .into_iter()
+            .map(|(user, (count, ticks))| {
+                (user, ticks as f32 * self.interval_per_tick / count as f32)
+            })
+            .collect()
+    }
+
+    /// Get the tick of the last kill, capture or round end, for trimming the idle time at the end of a demo
+    ///
+    /// Returns `None` if the demo doesn't contain any of these events.
+    pub fn last_action_tick(&self) -> Option<DemoTick> {
+        let deaths = self.deaths.iter().map(|death| death.tick);
+        let rounds = self.rounds.iter().map(|round| round.end_tick);
+        let captures = self.captures.iter().map(|capture| capture.tick);
+        deaths.chain(rounds).chain(captures).max()
+    }
+
+    /// Get every distinct weapon name used in a kill
+    ///
+    /// Damage events only carry a numeric weapon id, so weapons that were used without getting a kill are not included.
+    pub fn weapon_names(&self) -> BTreeSet<String> {
+        self.deaths
+            .iter()
+            .map(|death| death.weapon.clone())
+            .filter(|weapon| !weapon.is_empty())
+            .collect()
+    }
+
+    /// Get the chat messages sent by a player, including messages sent before the player changed their name
+    ///
+    /// Messages without a known sender, such as those from states stored before the sender was recorded,
+    /// are matched by the final name of the player.
+    pub fn chat_from(&self, user: UserId) -> Vec<&ChatMessage> {
+        let name = self.users.get(&user).map(|info| info.name.as_str());
+        self.chat
+            .iter()
+            .filter(|message| match message.user {
+                Some(sender) => sender == user,
+                None => Some(message.from.as_str()) == name,
+            })
+            .collect()
+    }
+
+    /// Get the chat messages sent under a specific name
+    pub fn chat_from_name(&self, name: &str) -> Vec<&ChatMessage> {
+        self.chat
+            .iter()
+            .filter(|message| message.from == name)
+            .collect()
+    }
+
+    /// Get the players that never killed or damaged anyone and spawned at most `max_spawns` times,
+    /// these are likely idle or disconnected right after joining
+    pub fn idle_players(&self, max_spawns: usize) -> Vec<UserId> {
+        self.users
+            .values()
+            .filter(|user| user.damage_dealt == 0)
+            .filter(|user| {
+                !self
+                    .deaths
+                    .iter()
+                    .any(|death| death.killer == user.user_id && death.victim != user.user_id)
+            })
+            .filter(|user| {
+                self.spawns
+                    .iter()
+                    .filter(|spawn| spawn.user == user.user_id)
+                    .count()
+                    <= max_spawns
+            })
+            .map(|user| user.user_id)
+            .collect()
+    }
+
+    /// Get the kills as they were shown in the in-game kill feed
+    pub fn kill_feed(&self) -> Vec<KillFeedEntry> {
+        let player = |user: UserId, team: Team| KillFeedPlayer {
+            user,
+            name: self
+                .users
+                .get(&user)
+                .map(|info| info.name.clone())
+                .unwrap_or_default(),
+            team,
+        };
+
+        self.deaths
+            .iter()
+            .map(|death| KillFeedEntry {
+                tick: death.tick,
+                killer: (death.killer != 0 && death.killer != death.victim)
+                    .then(|| player(death.killer, death.killer_team)),
+                // assisters are always on the same team as the killer
+                assister: death
+                    .assister
+                    .map(|assister| player(assister, death.killer_team)),
+                victim: player(death.victim, death.victim_team),
+                weapon: death.weapon.clone(),
+                crit_type: death.crit_type,
+                custom_kill: death.custom_kill,
+            })
+            .collect()
+    }
+
+    /// Get all events of the match as a flat list of rows sorted by tick, for loading into a data frame
+    ///
+    /// Chat messages without a known sender are matched to the sender by name, messages from the server
+    /// have no actor.
+    pub fn to_event_rows(&self) -> Vec<EventRow> {
+        let row = |tick: DemoTick, event_type: EventType| EventRow {
+            tick,
+            seconds: u32::from(tick) as f32 * self.interval_per_tick,
+            event_type,
+            actor: None,
+            target: None,
+            team: None,
+            class: None,
+            value: None,
+        };
+
+        let mut rows = Vec::new();
+        rows.extend(self.deaths.iter().map(|death| EventRow {
+            actor: (death.killer != 0).then_some(death.killer),
+            target: Some(death.victim),
+            team: Some(death.killer_team),
+            value: Some(death.weapon.clone()),
+            ..row(death.tick, EventType::Death)
+        }));
+        rows.extend(self.chat.iter().map(|message| EventRow {
+            actor: message.user.or_else(|| {
+                self.users
+                    .iter()
+                    .find(|(_, user)| user.name == message.from)
+                    .map(|(user_id, _)| *user_id)
+            }),
+            value: Some(message.text.clone()),
+            ..row(message.tick, EventType::Chat)
+        }));
+        rows.extend(self.spawns.iter().map(|spawn| EventRow {
+            actor: Some(spawn.user),
+            team: Some(spawn.team),
+            class: Some(spawn.class),
+            ..row(spawn.tick, EventType::Spawn)
+        }));
+        rows.extend(self.class_intents.iter().map(|intent| EventRow {
+            actor: Some(intent.user),
+            class: Some(intent.class),
+            ..row(intent.tick, EventType::ClassChange)
+        }));
+        rows.extend(self.captures.iter().map(|capture| EventRow {
+            team: Some(capture.team),
+            value: Some(capture.point.to_string()),
+            ..row(capture.tick, EventType::Capture)
+        }));
+        rows.extend(self.rounds.iter().map(|round| EventRow {
+            team: Some(round.winner),
+            ..row(round.end_tick, EventType::RoundWin)
+        }));
+        rows.extend(self.balances.iter().map(|balance| EventRow {
+            actor: Some(balance.user),
+            team: Some(balance.team),
+            ..row(balance.tick, EventType::Balance)
+        }));
+        rows.sort_by_key(|row| row.tick);
+        rows
+    }
+
+    /// Get a small overview of the match
+    ///
+    /// Suicides and deaths not caused by a player are not counted as kills.
+    pub fn summarize(&self) -> MatchSummary {
+        let mut kills: HashMap<UserId, u32> = HashMap::new();
+        for death in &self.deaths {
+            if death.killer != death.victim && death.killer != 0 {
+                *kills.entry(death.killer).or_default() += 1;
+            }
+        }
+
+        let mut top_fraggers: Vec<Fragger> = kills
+            .iter()
+            .map(|(user, kills)| Fragger {
+                user: *user,
+                name: self
+                    .users
+                    .get(user)
+                    .map(|info| info.name.clone())
+                    .unwrap_or_default(),
+                kills: *kills,
+            })
+            .collect();
+        top_fraggers.sort_by(|a, b| b.kills.cmp(&a.kills).then(a.user.cmp(&b.user)));
+        top_fraggers.truncate(3);
+
+        let round_wins = |team: Team| {
+            self.rounds
+                .iter()
+                .filter(|round| round.winner == team)
+                .count() as u32
+        };
+
+        let paused_ticks: u32 = self.pauses.iter().map(Pause::ticks).sum();
+        let ticks = u32::from(self.end_tick).saturating_sub(paused_ticks);
+
+        MatchSummary {
+            map: self.map.clone(),
+            duration: ticks as f32 * self.interval_per_tick,
+            player_count: self.users.len(),
+            red_round_wins: round_wins(Team::Red),
+            blue_round_wins: round_wins(Team::Blue),
+            total_kills: kills.values().sum(),
+            top_fraggers,
+        }
+    }
+
+    /// Get the damage and healing done by every player, split by the class they were playing at the time
+    ///
+    /// Self damage and self healing are not counted.
+    pub fn class_breakdown(&self) -> HashMap<(UserId, Class), ClassStats> {
+        self.class_stats
+            .iter()
+            .flat_map(|(user, classes)| {
+                classes
+                    .iter()
+                    .map(move |(class, stats)| ((*user, *class), *stats))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+fn test_user(user_id: u16) -> UserInfo {
+    UserInfo {
+        name: format!("player{}", user_id),
+        user_id: UserId::from(user_id),
+        entity_id: EntityId::from(user_id as u32),
+        ..UserInfo::default()
+    }
+}
+
+#[cfg(test)]
+fn test_death(killer: u16, victim: u16) -> Death {
+    Death {
+        victim: UserId::from(victim),
+        killer: UserId::from(killer),
+        killer_team: Team::Red,
+        victim_team: Team::Blue,
+        ..Death::default()
+    }
+}
+
+#[test]
+fn test_default_parser_version() {
+    let parser_state = ParserState::new(24, |_| false, false);
+    assert_eq!(
+        env!("CARGO_PKG_VERSION"),
+        Analyser::default()
+            .borrow_output(&parser_state)
+            .parser_version
+    );
+}
+
+#[test]
+fn test_death_deserialize_without_new_fields() {
+    // a death as serialized before the teams and kill flags were added
+    let death: Death = serde_json::from_str(
+        r#"{"weapon": "tf_projectile_rocket", "victim": 8, "assister": 13, "killer": 14, "tick": 8}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        Death {
+            weapon: "tf_projectile_rocket".into(),
+            victim: UserId::from(8u16),
+            assister: Some(UserId::from(13u16)),
+            killer: UserId::from(14u16),
+            tick: DemoTick::from(8u32),
+            ..Death::default()
+        },
+        death
+    );
+    assert_eq!(Team::Other, death.killer_team);
+    assert_eq!(Team::Other, death.victim_team);
+}
+
+#[test]
+fn test_director_focus() {
+    use crate::demo::gameevent_gen::{HLTVCameramanEvent, HLTVChaseEvent, HLTVFixedEvent};
+
+    let mut analyser = Analyser::new();
+    for (user_id, entity_id) in [(10u16, 2u32), (11, 3)] {
+        analyser.state.users.insert(
+            UserId::from(user_id),
+            UserInfo {
+                entity_id: EntityId::from(entity_id),
+                team: Team::Red,
+                ..test_user(user_id)
+            },
+        );
+    }
+
+    let chase = |target_1| {
+        GameEvent::HLTVChase(HLTVChaseEvent {
+            target_1,
+            target_2: 0,
+            distance: 96,
+            theta: 0,
+            phi: 0,
+            inertia: 0,
+            in_eye: 0,
+        })
+    };
+
+    analyser.handle_event(&chase(2), DemoTick::from(10u32));
+    analyser.handle_event(&chase(2), DemoTick::from(20u32));
+    analyser.handle_event(
+        &GameEvent::HLTVFixed(HLTVFixedEvent {
+            pos_x: 0,
+            pos_y: 0,
+            pos_z: 0,
+            theta: 0,
+            phi: 0,
+            offset: 0,
+            fov: 90.0,
+            target: 0,
+        }),
+        DemoTick::from(30u32),
+    );
+    analyser.handle_event(
+        &GameEvent::HLTVCameraman(HLTVCameramanEvent { index: 5 }),
+        DemoTick::from(40u32),
+    );
+    analyser.handle_event(&chase(3), DemoTick::from(50u32));
+
+    assert_eq!(
+        vec![
+            DirectorFocus {
+                tick: DemoTick::from(10u32),
+                target: Some(UserId::from(10u16)),
+                source: FocusSource::Auto,
+            },
+            DirectorFocus {
+                tick: DemoTick::from(30u32),
+                target: None,
+                source: FocusSource::Auto,
+            },
+            DirectorFocus {
+                tick: DemoTick::from(50u32),
+                target: Some(UserId::from(11u16)),
+                source: FocusSource::Manual,
+            },
+        ],
+        analyser.state.director_focus
+    );
+}
+
+#[test]
+fn test_flag_events() {
+    use crate::demo::gameevent_gen::TeamPlayFlagEventEvent;
+
+    let mut analyser = Analyser::new();
+    analyser.state.users.insert(
+        UserId::from(10u16),
+        UserInfo {
+            entity_id: EntityId::from(2u32),
+            team: Team::Red,
+            ..test_user(10)
+        },
+    );
+
+    let flag_event = |player, event_type| {
+        GameEvent::TeamPlayFlagEvent(TeamPlayFlagEventEvent {
+            player,
+            carrier: 0,
+            event_type,
+            home: 0,
+            team: 2,
+        })
+    };
+
+    analyser.handle_event(&flag_event(2, 1), DemoTick::from(10u32));
+    analyser.handle_event(&flag_event(2, 4), DemoTick::from(20u32));
+    // unknown player
+    analyser.handle_event(&flag_event(5, 1), DemoTick::from(30u32));
+    // unknown event type
+    analyser.handle_event(&flag_event(2, 9), DemoTick::from(40u32));
+    analyser.handle_event(&flag_event(2, 2), DemoTick::from(50u32));
+
+    assert_eq!(
+        vec![
+            FlagEvent {
+                player: UserId::from(10u16),
+                event_type: FlagEventType::Pickup,
+                tick: DemoTick::from(10u32),
+            },
+            FlagEvent {
+                player: UserId::from(10u16),
+                event_type: FlagEventType::Dropped,
+                tick: DemoTick::from(20u32),
+            },
+            FlagEvent {
+                player: UserId::from(10u16),
+                event_type: FlagEventType::Capture,
+                tick: DemoTick::from(50u32),
+            },
+        ],
+        analyser.state.flag_events
+    );
+}
+
+#[test]
+fn test_building_events() {
+    use crate::demo::gameevent_gen::{ObjectDestroyedEvent, PlayerBuiltObjectEvent};
+
+    let mut analyser = Analyser::new();
+    let destroyed = |object_type, attacker| {
+        GameEvent::ObjectDestroyed(ObjectDestroyedEvent {
+            user_id: 10,
+            attacker,
+            assister: 0,
+            weapon: "tf_projectile_rocket".into(),
+            weapon_id: 0,
+            object_type,
+            index: 50,
+            was_building: false,
+        })
+    };
+
+    analyser.handle_event(
+        &GameEvent::PlayerBuiltObject(PlayerBuiltObjectEvent {
+            user_id: 10,
+            object: 2,
+            index: 50,
+        }),
+        DemoTick::from(10u32),
+    );
+    analyser.handle_event(&destroyed(2, 12), DemoTick::from(20u32));
+    // unknown object type
+    analyser.handle_event(&destroyed(7, 12), DemoTick::from(30u32));
+    analyser.handle_event(&destroyed(3, 0), DemoTick::from(40u32));
+
+    assert_eq!(
+        vec![
+            BuildingEvent {
+                builder: UserId::from(10u16),
+                object_type: ObjectType::Sentry,
+                event: BuildingEventType::Built,
+                attacker: None,
+                tick: DemoTick::from(10u32),
+            },
+            BuildingEvent {
+                builder: UserId::from(10u16),
+                object_type: ObjectType::Sentry,
+                event: BuildingEventType::Destroyed,
+                attacker: Some(UserId::from(12u16)),
+                tick: DemoTick::from(20u32),
+            },
+            BuildingEvent {
+                builder: UserId::from(10u16),
+                object_type: ObjectType::Sapper,
+                event: BuildingEventType::Destroyed,
+                attacker: None,
+                tick: DemoTick::from(40u32),
+            },
+        ],
+        analyser.state.building_events
+    );
+}
+
+#[test]
+fn test_class_and_team_changes() {
+    let mut analyser = Analyser::new();
+    analyser.state.users.insert(
+        UserId::from(10u16),
+        UserInfo {
+            entity_id: EntityId::from(2u32),
+            team: Team::Other,
+            ..test_user(10)
+        },
+    );
+
+    let spawn = |team, class| {
+        GameEvent::PlayerSpawn(PlayerSpawnEvent {
+            user_id: 10,
+            team,
+            class,
+        })
+    };
+
+    analyser.handle_event(&spawn(2, 1), DemoTick::from(10u32));
+    analyser.handle_event(&spawn(2, 1), DemoTick::from(20u32));
+    analyser.handle_event(&spawn(2, 3), DemoTick::from(30u32));
+    analyser.handle_event(&spawn(3, 3), DemoTick::from(40u32));
+
+    assert_eq!(
+        vec![
+            ClassChange {
+                user: UserId::from(10u16),
+                from: Class::Other,
+                to: Class::Scout,
+                tick: DemoTick::from(10u32),
+            },
+            ClassChange {
+                user: UserId::from(10u16),
+                from: Class::Scout,
+                to: Class::Soldier,
+                tick: DemoTick::from(30u32),
+            },
+        ],
+        analyser.state.class_changes
+    );
+    assert_eq!(
+        vec![
+            TeamChange {
+                user: UserId::from(10u16),
+                from: Team::Other,
+                to: Team::Red,
+                tick: DemoTick::from(10u32),
+            },
+            TeamChange {
+                user: UserId::from(10u16),
+                from: Team::Red,
+                to: Team::Blue,
+                tick: DemoTick::from(40u32),
+            },
+        ],
+        analyser.state.team_changes
+    );
+}
+
+#[test]
+fn test_tick_discontinuity() {
+    use crate::demo::message::NetTickMessage;
+
+    let parser_state = ParserState::new(24, |_| false, false);
+    let mut analyser = Analyser::new();
+    for (tick, server_tick) in [(1u32, 500u32), (2, 501), (3, 100), (4, 101)] {
+        analyser.handle_message(
+            &Message::NetTick(NetTickMessage {
+                tick: ServerTick::from(server_tick),
+                frame_time: 0,
+                std_dev: 0,
+            }),
+            DemoTick::from(tick),
+            &parser_state,
+        );
+    }
+
+    let state = analyser.into_output(&parser_state);
+    assert_eq!(
+        vec![TickDiscontinuity {
+            previous: ServerTick::from(501u32),
+            next: ServerTick::from(100u32),
+        }],
+        state.tick_discontinuities
+    );
+    assert_eq!(ServerTick::from(100u32), state.start_tick);
+}
+
+#[test]
+fn test_best_killstreaks() {
+    let death = test_death;
+    let state = MatchState {
+        deaths: vec![
+            death(1, 2),
+            death(1, 3),
+            death(2, 4),
+            death(3, 1),
+            death(1, 2),
+            death(4, 4),
+            death(1, 3),
+            death(1, 4),
+            death(1, 2),
+        ],
+        ..MatchState::default()
+    };
+
+    let streaks = state.best_killstreaks();
+    assert_eq!(Some(&4), streaks.get(&UserId::from(1u16)));
+    assert_eq!(Some(&1), streaks.get(&UserId::from(2u16)));
+    assert_eq!(Some(&1), streaks.get(&UserId::from(3u16)));
+    assert_eq!(None, streaks.get(&UserId::from(4u16)));
+}
+
+#[test]
+fn test_idle_players() {
+    let user = |user_id: u16, damage_dealt: u32| {
+        (
+            UserId::from(user_id),
+            UserInfo {
+                team: Team::Red,
+                damage_dealt,
+                ..test_user(user_id)
+            },
+        )
+    };
+    let spawn = |user_id: u16| Spawn {
+        user: UserId::from(user_id),
+        class: Class::Scout,
+        team: Team::Red,
+        tick: DemoTick::default(),
+    };
+    let death = test_death;
+    let state = MatchState {
+        // 1 killed someone, 2 dealt damage, 3 did nothing and 4 kept respawning from suicides
+        users: [user(1, 0), user(2, 100), user(3, 0), user(4, 0)]
+            .into_iter()
+            .collect(),
+        deaths: vec![death(1, 2), death(4, 4), death(4, 4), death(4, 4)],
+        spawns: vec![
+            spawn(1),
+            spawn(2),
+            spawn(3),
+            spawn(4),
+            spawn(4),
+            spawn(4),
+            spawn(4),
+        ],
+        ..MatchState::default()
+    };
+
+    assert_eq!(vec![UserId::from(3u16)], state.idle_players(2));
+    assert_eq!(
+        vec![UserId::from(3u16), UserId::from(4u16)],
+        state.idle_players(4)
+    );
+}
+
+#[test]
+fn test_kill_streaks() {
+    let death = |killer: u16, victim: u16, tick: u32| Death {
+        tick: DemoTick::from(tick),
+        ..test_death(killer, victim)
+    };
+    let state = MatchState {
+        deaths: vec![
+            death(1, 2, 10),
+            death(1, 3, 20),
+            // 1 and 4 trade in the same tick, the kill happened before 1 died
+            death(1, 4, 30),
+            death(4, 1, 30),
+            death(2, 2, 40),
+            death(3, 2, 50),
+            death(3, 4, 60),
+        ],
+        ..MatchState::default()
+    };
+
+    let streak = |player: u16, count, start_tick: u32, end_tick: u32| KillStreak {
+        player: UserId::from(player),
+        count,
+        start_tick: DemoTick::from(start_tick),
+        end_tick: DemoTick::from(end_tick),
+    };
+    assert_eq!(
+        vec![
+            streak(1, 3, 10, 30),
+            streak(4, 1, 30, 30),
+            streak(3, 2, 50, 60)
+        ],
+        state.kill_streaks()
+    );
+}
+
+#[test]
+fn test_last_point_holds_uncaptured_last() {
+    let capture = |point, team, tick: u32| PointCapture {
+        point,
+        team,
+        tick: DemoTick::from(tick),
+        cappers: Vec::new(),
+    };
+    // blue pushes red back to their last point but never captures it
+    let state = MatchState {
+        captures: vec![
+            capture(2, Team::Blue, 100),
+            capture(3, Team::Blue, 200),
+            capture(3, Team::Red, 300),
+        ],
+        end_tick: DemoTick::from(1000u32),
+        ..MatchState::default()
+    };
+
+    assert_eq!(
+        vec![Hold {
+            team: Team::Red,
+            start_tick: DemoTick::from(200u32),
+            end_tick: DemoTick::from(300u32),
+            held: true,
+        }],
+        state.last_point_holds(Some(5))
+    );
+    assert!(state.last_point_holds(None).is_empty());
+}
+
+#[test]
+fn test_detect_format_offclassing() {
+    let spawns = |team: Team, first_user: u16, classes: &[Class]| {
+        classes
+            .iter()
+            .enumerate()
+            .map(|(index, class)| Spawn {
+                user: UserId::from(first_user + (index % 6) as u16),
+                class: *class,
+                team,
+                tick: DemoTick::from(10u32),
+            })
+            .collect::<Vec<_>>()
+    };
+    // red offclasses through most of the classes during the round
+    let mut state = MatchState {
+        spawns: [
+            spawns(
+                Team::Red,
+                1,
+                &[
+                    Class::Scout,
+                    Class::Scout,
+                    Class::Soldier,
+                    Class::Soldier,
+                    Class::Demoman,
+                    Class::Medic,
+                    Class::Sniper,
+                    Class::Spy,
+                    Class::Engineer,
+                    Class::Heavy,
+                ],
+            ),
+            spawns(
+                Team::Blue,
+                11,
+                &[
+                    Class::Scout,
+                    Class::Scout,
+                    Class::Soldier,
+                    Class::Soldier,
+                    Class::Demoman,
+                    Class::Medic,
+                ],
+            ),
+        ]
+        .concat(),
+        ..MatchState::default()
+    };
+    assert_eq!(MatchFormat::Sixes, state.detect_format());
+
+    let highlander = [
+        Class::Scout,
+        Class::Soldier,
+        Class::Pyro,
+        Class::Demoman,
+        Class::Heavy,
+        Class::Engineer,
+        Class::Medic,
+        Class::Sniper,
+        Class::Spy,
+    ];
+    state.spawns = highlander
+        .iter()
+        .enumerate()
+        .flat_map(|(index, class)| {
+            [(Team::Red, 1), (Team::Blue, 11)].map(|(team, first_user)| Spawn {
+                user: UserId::from(first_user + index as u16),
+                class: *class,
+                team,
+                tick: DemoTick::from(10u32),
+            })
+        })
+        .collect();
+    assert_eq!(MatchFormat::Highlander, state.detect_format());
+}
+
+#[test]
+fn test_summarize() {
+    let death = test_death;
+    let mut state = MatchState {
+        map: "cp_process_final".into(),
+        end_tick: DemoTick::from(66u32),
+        interval_per_tick: 0.015,
+        deaths: vec![
+            death(1, 2),
+            death(2, 3),
+            death(3, 3),
+            death(4, 2),
+            death(2, 1),
+            death(4, 3),
+            death(0, 4),
+            death(4, 1),
+        ],
+        rounds: vec![
+            Round {
+                winner: Team::Red,
+                length: 0.0,
+                end_tick: DemoTick::default(),
+                full_round: true,
+            },
+            Round {
+                winner: Team::Blue,
+                length: 0.0,
+                end_tick: DemoTick::default(),
+                full_round: true,
+            },
+            Round {
+                winner: Team::Red,
+                length: 0.0,
+                end_tick: DemoTick::default(),
+                full_round: true,
+            },
+        ],
+        ..MatchState::default()
+    };
+    for user_id in 1..=5u16 {
+        state
+            .users
+            .insert(UserId::from(user_id), test_user(user_id));
+    }
+
+    let summary = state.summarize();
+    assert_eq!("cp_process_final", summary.map);
+    assert!((summary.duration - 0.99).abs() < 0.001);
+    assert_eq!(state.users.len(), summary.player_count);
+    assert_eq!(2, summary.red_round_wins);
+    assert_eq!(1, summary.blue_round_wins);
+    assert_eq!(6, summary.total_kills);
+    assert_eq!(
+        vec![(4u16, "player4", 3), (2, "player2", 2), (1, "player1", 1)],
+        summary
+            .top_fraggers
+            .iter()
+            .map(|fragger| (
+                u16::from(fragger.user),
+                fragger.name.as_str(),
+                fragger.kills
+            ))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_csv_export() {
+    let mut state = MatchState {
+        deaths: vec![Death {
+            weapon: "scattergun".into(),
+            tick: DemoTick::from(100u32),
+            crit_type: CritType::Mini,
+            ..test_death(1, 2)
+        }],
+        chat: vec![ChatMessage {
+            kind: ChatMessageKind::ChatAll,
+            from: "\"quoted\", name".into(),
+            text: "gg\nwp".into(),
+            tick: DemoTick::from(200u32),
+            user: None,
+        }],
+        ..MatchState::default()
+    };
+    for (user_id, name) in [(1u16, "comma, name"), (2, "plain")] {
+        state.users.insert(
+            UserId::from(user_id),
+            UserInfo {
+                name: name.into(),
+                ..test_user(user_id)
+            },
+        );
+    }
+
+    let mut deaths = Vec::new();
+    state.deaths_to_csv(&mut deaths).unwrap();
+    assert_eq!(
+        "tick,killer,killer_name,killer_team,assister,assister_name,victim,victim_name,victim_team,weapon,crit_type\n\
+        100,1,\"comma, name\",red,,,2,plain,blue,scattergun,mini\n",
+        String::from_utf8(deaths).unwrap()
+    );
+
+    let mut chat = Vec::new();
+    state.chat_to_csv(&mut chat).unwrap();
+    assert_eq!(
+        "tick,kind,from,text\n200,TF_Chat_All,\"\"\"quoted\"\", name\",\"gg\nwp\"\n",
+        String::from_utf8(chat).unwrap()
+    );
 }
@@ -0,0 +1,132 @@
+use crate::demo::data::DemoTick;
+use crate::demo::message::packetentities::EntityId;
+use crate::demo::message::{Message, MessageType};
+use crate::demo::packet::datatable::{ParseSendTable, ServerClass};
+use crate::demo::packet::message::MessagePacketMeta;
+use crate::demo::packet::stringtable::StringTableEntry;
+use crate::demo::parser::analyser::UserId;
+use crate::demo::parser::gamestateanalyser::{Building, BuildingClass, GameStateAnalyser};
+use crate::demo::parser::handler::MessageHandler;
+use crate::demo::vector::Vector;
+use crate::ParserState;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct BuildingState {
+    pub entity: EntityId,
+    pub builder: UserId,
+    pub kind: BuildingClass,
+    pub level: u8,
+    pub position: Vector,
+    pub health: u16,
+    pub max_health: u16,
+    pub tick: DemoTick,
+}
+
+impl BuildingState {
+    fn new(building: &Building, tick: DemoTick) -> Self {
+        BuildingState {
+            entity: building.entity_id(),
+            builder: building.builder(),
+            kind: building.class(),
+            level: building.level(),
+            position: building.position(),
+            health: building.health(),
+            max_health: building.max_health(),
+            tick,
+        }
+    }
+}
+
+/// Get the health of every building over time from the recorded building states
+///
+/// Note that the entity id of a destroyed building can be re-used for a newly built building.
+pub fn health_timelines(states: &[BuildingState]) -> BTreeMap<EntityId, Vec<(DemoTick, u16)>> {
+    let mut timelines: BTreeMap<EntityId, Vec<(DemoTick, u16)>> = BTreeMap::new();
+    for state in states {
+        let timeline = timelines.entry(state.entity).or_default();
+        if timeline.last().map(|(_, health)| *health) != Some(state.health) {
+            timeline.push((state.tick, state.health));
+        }
+    }
+    timelines
+}
+
+/// An analyser that records the placement, upgrades and health of engineer buildings.
+///
+/// A state is recorded when a building is first seen and every time the upgrade level or health of a building changes,
+/// health goes up both from repairs and from the building being constructed or upgraded.
+#[derive(Default, Debug)]
+pub struct BuildingAnalyser {
+    game_state: GameStateAnalyser,
+    last: HashMap<EntityId, (u8, u16, u16)>,
+    states: Vec<BuildingState>,
+}
+
+impl BuildingAnalyser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn record_buildings(&mut self, tick: DemoTick) {
+        let buildings = &self.game_state.state.buildings;
+        self.last.retain(|entity, _| buildings.contains_key(entity));
+
+        for building in buildings.values() {
+            let current = (building.level(), building.health(), building.max_health());
+            if self.last.insert(building.entity_id(), current) != Some(current) {
+                self.states.push(BuildingState::new(building, tick));
+            }
+        }
+    }
+}
+
+impl MessageHandler for BuildingAnalyser {
+    type Output = Vec<BuildingState>;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        GameStateAnalyser::does_handle(message_type)
+    }
+
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
+        self.game_state.handle_message(message, tick, parser_state);
+        if let Message::PacketEntities(_) = message {
+            self.record_buildings(tick);
+        }
+    }
+
+    fn handle_string_entry(
+        &mut self,
+        table: &str,
+        index: usize,
+        entry: &StringTableEntry,
+        parser_state: &ParserState,
+    ) {
+        self.game_state
+            .handle_string_entry(table, index, entry, parser_state)
+    }
+
+    fn handle_data_tables(
+        &mut self,
+        tables: &[ParseSendTable],
+        server_classes: &[ServerClass],
+        parser_state: &ParserState,
+    ) {
+        self.game_state
+            .handle_data_tables(tables, server_classes, parser_state)
+    }
+
+    fn handle_packet_meta(
+        &mut self,
+        tick: DemoTick,
+        meta: &MessagePacketMeta,
+        parser_state: &ParserState,
+    ) {
+        self.game_state.handle_packet_meta(tick, meta, parser_state)
+    }
+
+    fn into_output(self, _state: &ParserState) -> Self::Output {
+        self.states
+    }
+}
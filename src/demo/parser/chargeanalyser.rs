@@ -0,0 +1,116 @@
+use crate::demo::data::DemoTick;
+use crate::demo::message::packetentities::{EntityId, PacketEntity, UpdateType};
+use crate::demo::message::{Message, MessageType};
+use crate::demo::parser::analyser::UserId;
+use crate::demo::parser::gamestateanalyser::ENTITY_INDEX_MASK;
+use crate::demo::parser::handler::{BorrowMessageHandler, MessageHandler};
+use crate::demo::sendprop::SendPropIdentifier;
+use crate::ParserState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+/// The charge level of a medigun, from `0.0` to `1.0`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ChargeSample {
+    pub tick: DemoTick,
+    pub medic: UserId,
+    pub level: f32,
+}
+
+#[derive(Debug, Default)]
+struct Medigun {
+    owner: Option<EntityId>,
+    level: f32,
+}
+
+/// An analyser that records the charge level of every medigun every time it changes, sorted by tick.
+///
+/// While building the charge rises steadily, once deployed it drains back to zero over the duration of the uber.
+/// Combine it with the [`Analyser`](crate::demo::parser::analyser::Analyser) in a tuple to get the charge levels
+/// alongside the [`MatchState`](crate::MatchState).
+#[derive(Default, Debug)]
+pub struct ChargeAnalyser {
+    samples: Vec<ChargeSample>,
+    mediguns: HashMap<EntityId, Medigun>,
+}
+
+impl ChargeAnalyser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn handle_entity(&mut self, entity: &PacketEntity, tick: DemoTick, parser_state: &ParserState) {
+        const OWNER: SendPropIdentifier =
+            SendPropIdentifier::new("DT_BaseCombatWeapon", "m_hOwner");
+        const CHARGE: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFWeaponMedigunDataNonLocal", "m_flChargeLevel");
+        const LOCAL_CHARGE: SendPropIdentifier =
+            SendPropIdentifier::new("DT_LocalTFWeaponMedigunData", "m_flChargeLevel");
+
+        if entity.update_type == UpdateType::Delete {
+            self.mediguns.remove(&entity.entity_index);
+            return;
+        }
+
+        let mut level = None;
+        let mut owner = None;
+        for prop in entity.props(parser_state) {
+            match prop.identifier {
+                OWNER => {
+                    let handle = i64::try_from(&prop.value).unwrap_or_default();
+                    owner = Some(EntityId::from(handle as u32 & ENTITY_INDEX_MASK));
+                }
+                CHARGE | LOCAL_CHARGE => level = f32::try_from(&prop.value).ok(),
+                _ => {}
+            }
+        }
+
+        // only mediguns have a charge level
+        if level.is_none() && !self.mediguns.contains_key(&entity.entity_index) {
+            return;
+        }
+
+        let medigun = self.mediguns.entry(entity.entity_index).or_default();
+        if owner.is_some() {
+            medigun.owner = owner;
+        }
+        if let Some(level) = level {
+            if level != medigun.level {
+                medigun.level = level;
+                if let Some(medic) = medigun
+                    .owner
+                    .and_then(|owner| parser_state.user_id_for_entity(owner))
+                {
+                    self.samples.push(ChargeSample { tick, medic, level });
+                }
+            }
+        }
+    }
+}
+
+impl MessageHandler for ChargeAnalyser {
+    type Output = Vec<ChargeSample>;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        matches!(message_type, MessageType::PacketEntities)
+    }
+
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
+        if let Message::PacketEntities(message) = message {
+            for entity in &message.entities {
+                self.handle_entity(entity, tick, parser_state);
+            }
+        }
+    }
+
+    fn into_output(self, _state: &ParserState) -> Self::Output {
+        self.samples
+    }
+}
+
+impl BorrowMessageHandler for ChargeAnalyser {
+    fn borrow_output(&self, _state: &ParserState) -> &Self::Output {
+        &self.samples
+    }
+}
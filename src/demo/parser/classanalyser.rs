@@ -0,0 +1,209 @@
+use crate::demo::data::DemoTick;
+use crate::demo::gamevent::GameEvent;
+use crate::demo::message::packetentities::PacketEntity;
+use crate::demo::message::{Message, MessageType};
+use crate::demo::packet::message::MessagePacketMeta;
+use crate::demo::parser::analyser::{Class, Spawn, UserId};
+use crate::demo::parser::handler::MessageHandler;
+use crate::demo::sendprop::SendPropIdentifier;
+use crate::ParserState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+/// The class of a player as read from the `m_iClass` prop of the player entity
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClassSample {
+    pub tick: DemoTick,
+    pub user: UserId,
+    pub class: Class,
+}
+
+/// A change of the `m_iClass` prop to a different class than reported by the last spawn event of the player
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassDiscrepancy {
+    pub tick: DemoTick,
+    pub user: UserId,
+    pub spawn_class: Class,
+    pub prop_class: Class,
+    /// The class the player picked according to the `m_iDesiredPlayerClass` prop
+    pub desired_class: Class,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClassTimeline {
+    /// Every change of the `m_iClass` prop
+    pub classes: Vec<ClassSample>,
+    /// Every change of the `m_iDesiredPlayerClass` prop, the class a player picked for their next spawn
+    ///
+    /// This can be used to detect class changes in demos that are missing the `player_changeclass` events.
+    pub desired_classes: Vec<ClassSample>,
+    /// Changes of the `m_iClass` prop to the class the player picked, without a spawn event for the new class
+    #[serde(default)]
+    pub unreported_changes: Vec<ClassSample>,
+    /// Changes of the `m_iClass` prop that match neither the last spawn event nor the class the player picked
+    pub discrepancies: Vec<ClassDiscrepancy>,
+}
+
+/// An analyser that tracks the class of players from the `m_iClass` prop of the player entities
+/// and checks it against the class reported by the spawn events.
+///
+/// Note that player entities are only updated while they are in view of the recording player,
+/// for POV demos the prop can lag behind the spawn events significantly.
+#[derive(Default, Debug)]
+pub struct ClassAnalyser {
+    timeline: ClassTimeline,
+    classes: HashMap<UserId, Class>,
+    desired_classes: HashMap<UserId, Class>,
+    spawn_classes: HashMap<UserId, Class>,
+    pending_changes: Vec<ClassSample>,
+}
+
+impl ClassAnalyser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn handle_entity(&mut self, entity: &PacketEntity, tick: DemoTick, parser_state: &ParserState) {
+        const CLASS: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFPlayerClassShared", "m_iClass");
+        const DESIRED_CLASS: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFPlayerShared", "m_iDesiredPlayerClass");
+
+        let user = match parser_state.user_id_for_entity(entity.entity_index) {
+            Some(user) => user,
+            None => return,
+        };
+
+        if let Some(prop) = entity.get_prop_by_identifier(&CLASS, parser_state) {
+            let class = Class::new(i64::try_from(&prop.value).unwrap_or_default());
+            if self.classes.insert(user, class) != Some(class) {
+                let sample = ClassSample { tick, user, class };
+                self.timeline.classes.push(sample);
+                self.pending_changes.push(sample);
+            }
+        }
+
+        if let Some(prop) = entity.get_prop_by_identifier(&DESIRED_CLASS, parser_state) {
+            let class = Class::new(i64::try_from(&prop.value).unwrap_or_default());
+            if self.desired_classes.insert(user, class) != Some(class) {
+                self.timeline
+                    .desired_classes
+                    .push(ClassSample { tick, user, class });
+            }
+        }
+    }
+
+    /// The spawn event for a class change can be in the same packet as the entity update,
+    /// so changes are only checked once the entire packet has been handled
+    ///
+    /// When the spawn event for a class change is missing, the class change is still accepted if the player
+    /// picked the new class according to the `m_iDesiredPlayerClass` prop.
+    fn check_changes(&mut self) {
+        for change in self.pending_changes.drain(..) {
+            let spawn_class = match self.spawn_classes.get_mut(&change.user) {
+                Some(spawn_class) if *spawn_class != change.class => spawn_class,
+                _ => continue,
+            };
+            let desired_class = self
+                .desired_classes
+                .get(&change.user)
+                .copied()
+                .unwrap_or_default();
+            if desired_class == change.class {
+                *spawn_class = change.class;
+                self.timeline.unreported_changes.push(change);
+            } else {
+                self.timeline.discrepancies.push(ClassDiscrepancy {
+                    tick: change.tick,
+                    user: change.user,
+                    spawn_class: *spawn_class,
+                    prop_class: change.class,
+                    desired_class,
+                });
+            }
+        }
+    }
+}
+
+#[test]
+fn test_desired_class_fallback() {
+    let user = UserId::from(3u16);
+    let sample = |tick: u32, class| ClassSample {
+        tick: tick.into(),
+        user,
+        class,
+    };
+
+    let mut analyser = ClassAnalyser::new();
+    analyser.spawn_classes.insert(user, Class::Soldier);
+    analyser.desired_classes.insert(user, Class::Medic);
+
+    // the spawn event for the change to medic is missing
+    analyser.pending_changes.push(sample(10, Class::Medic));
+    analyser.check_changes();
+    assert_eq!(
+        vec![sample(10, Class::Medic)],
+        analyser.timeline.unreported_changes
+    );
+    assert!(analyser.timeline.discrepancies.is_empty());
+    assert_eq!(Some(&Class::Medic), analyser.spawn_classes.get(&user));
+
+    // a change to a class the player didn't pick is still a discrepancy
+    analyser.pending_changes.push(sample(20, Class::Scout));
+    analyser.check_changes();
+    assert_eq!(1, analyser.timeline.unreported_changes.len());
+    assert_eq!(
+        vec![ClassDiscrepancy {
+            tick: 20u32.into(),
+            user,
+            spawn_class: Class::Medic,
+            prop_class: Class::Scout,
+            desired_class: Class::Medic,
+        }],
+        analyser.timeline.discrepancies
+    );
+}
+
+impl MessageHandler for ClassAnalyser {
+    type Output = ClassTimeline;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        matches!(
+            message_type,
+            MessageType::PacketEntities | MessageType::GameEvent
+        )
+    }
+
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
+        match message {
+            Message::PacketEntities(message) => {
+                for entity in &message.entities {
+                    self.handle_entity(entity, tick, parser_state);
+                }
+            }
+            Message::GameEvent(message) => {
+                if let GameEvent::PlayerSpawn(event) = &message.event {
+                    let spawn = Spawn::from_event(event, tick);
+                    self.spawn_classes.insert(spawn.user, spawn.class);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_packet_meta(
+        &mut self,
+        _tick: DemoTick,
+        _meta: &MessagePacketMeta,
+        _parser_state: &ParserState,
+    ) {
+        self.check_changes();
+    }
+
+    fn into_output(mut self, _state: &ParserState) -> Self::Output {
+        self.check_changes();
+        self.timeline
+    }
+}
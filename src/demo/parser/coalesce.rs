@@ -0,0 +1,124 @@
+use crate::demo::data::DemoTick;
+use crate::demo::message::packetentities::{EntityId, PacketEntity, UpdateType};
+use crate::demo::sendprop::{SendPropIdentifier, SendPropValue};
+use std::collections::{HashMap, HashSet};
+
+/// An iterator adapter over entity updates that only emits the props that changed since the
+/// previous emission of an entity.
+///
+/// Updates where none of the props changed are skipped entirely, unless the entity enters, leaves or is
+/// deleted, turning a stream of entity states into a sparse stream of changes.
+pub struct CoalesceEntities<I> {
+    inner: I,
+    tracked_props: Option<HashSet<SendPropIdentifier>>,
+    values: HashMap<EntityId, HashMap<SendPropIdentifier, SendPropValue>>,
+}
+
+impl<I: Iterator<Item = (DemoTick, PacketEntity)>> CoalesceEntities<I> {
+    pub fn new(inner: I) -> Self {
+        CoalesceEntities {
+            inner,
+            tracked_props: None,
+            values: HashMap::new(),
+        }
+    }
+
+    /// Only track changes to the provided props, any other props are dropped from the emitted entities
+    pub fn with_props(mut self, props: &[SendPropIdentifier]) -> Self {
+        self.tracked_props = Some(props.iter().copied().collect());
+        self
+    }
+
+    fn coalesce(&mut self, mut entity: PacketEntity) -> Option<PacketEntity> {
+        if entity.update_type == UpdateType::Delete {
+            self.values.remove(&entity.entity_index);
+            return Some(entity);
+        }
+
+        let tracked_props = &self.tracked_props;
+        let values = self.values.entry(entity.entity_index).or_default();
+        entity.props.retain(|prop| {
+            tracked_props
+                .as_ref()
+                .map(|tracked| tracked.contains(&prop.identifier))
+                .unwrap_or(true)
+                && values.get(&prop.identifier) != Some(&prop.value)
+        });
+        for prop in &entity.props {
+            values.insert(prop.identifier, prop.value.clone());
+        }
+
+        if entity.props.is_empty() && entity.update_type == UpdateType::Preserve {
+            None
+        } else {
+            Some(entity)
+        }
+    }
+}
+
+impl<I: Iterator<Item = (DemoTick, PacketEntity)>> Iterator for CoalesceEntities<I> {
+    type Item = (DemoTick, PacketEntity);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (tick, entity) = self.inner.next()?;
+            if let Some(entity) = self.coalesce(entity) {
+                return Some((tick, entity));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_coalesce_entities() {
+    use crate::demo::packet::datatable::ClassId;
+    use crate::demo::sendprop::SendProp;
+
+    let health = SendPropIdentifier::new("DT_BasePlayer", "m_iHealth");
+    let armor = SendPropIdentifier::new("DT_BasePlayer", "m_ArmorValue");
+    let entity = |tick: u32, props: &[(SendPropIdentifier, i64)]| {
+        (
+            DemoTick::from(tick),
+            PacketEntity {
+                server_class: ClassId::from(0u16),
+                entity_index: EntityId::from(3u32),
+                props: props
+                    .iter()
+                    .map(|(identifier, value)| SendProp {
+                        index: 0,
+                        identifier: *identifier,
+                        value: SendPropValue::Integer(*value),
+                    })
+                    .collect(),
+                in_pvs: true,
+                update_type: UpdateType::Preserve,
+                serial_number: 0,
+                delay: None,
+                delta: None,
+                baseline_index: 0,
+            },
+        )
+    };
+
+    let updates = vec![
+        entity(1, &[(health, 100), (armor, 0)]),
+        entity(2, &[(health, 100), (armor, 0)]),
+        entity(3, &[(health, 100)]),
+        entity(4, &[(health, 80), (armor, 0)]),
+        entity(5, &[(health, 80)]),
+    ];
+
+    let coalesced: Vec<_> = CoalesceEntities::new(updates.clone().into_iter()).collect();
+    assert_eq!(
+        vec![
+            entity(1, &[(health, 100), (armor, 0)]),
+            entity(4, &[(health, 80)])
+        ],
+        coalesced
+    );
+
+    let armor_only: Vec<_> = CoalesceEntities::new(updates.into_iter())
+        .with_props(&[armor])
+        .collect();
+    assert_eq!(vec![entity(1, &[(armor, 0)])], armor_only);
+}
@@ -0,0 +1,60 @@
+use crate::demo::data::DemoTick;
+use crate::demo::message::{Message, MessageType};
+use crate::demo::parser::handler::MessageHandler;
+use crate::ParserState;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConsoleOutputKind {
+    /// A command the server made the client execute, from a `net_StringCmd` message
+    Command,
+    /// Text the server printed to the client console, from a `net_Print` message
+    Print,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConsoleOutput {
+    pub tick: DemoTick,
+    pub kind: ConsoleOutputKind,
+    pub text: String,
+}
+
+/// An analyser that collects the commands and text the server sent to the client console.
+///
+/// This is kept separate from the default analyser to avoid decoding these messages when they aren't needed.
+#[derive(Default, Debug)]
+pub struct ConsoleAnalyser {
+    output: Vec<ConsoleOutput>,
+}
+
+impl ConsoleAnalyser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MessageHandler for ConsoleAnalyser {
+    type Output = Vec<ConsoleOutput>;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        matches!(message_type, MessageType::StringCmd | MessageType::Print)
+    }
+
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, _parser_state: &ParserState) {
+        let (kind, text) = match message {
+            Message::StringCmd(message) => (ConsoleOutputKind::Command, message.command.clone()),
+            Message::Print(message) => (ConsoleOutputKind::Print, message.value.to_string()),
+            _ => return,
+        };
+        self.output.push(ConsoleOutput {
+            tick,
+            kind,
+            text: text.trim_end().to_string(),
+        });
+    }
+
+    fn into_output(self, _state: &ParserState) -> Self::Output {
+        self.output
+    }
+}
@@ -0,0 +1,142 @@
+use crate::demo::data::DemoTick;
+use crate::demo::gamevent::{GameEvent, RawGameEvent};
+use crate::demo::message::{Message, MessageType};
+use crate::demo::parser::handler::MessageHandler;
+use crate::ParserState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Decode a raw game event into a consumer provided type
+pub type CustomEventDecoder<T> = fn(&RawGameEvent) -> Option<T>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomEvent<T> {
+    pub tick: DemoTick,
+    pub event: T,
+}
+
+/// An analyser that decodes game events unknown to the parser, such as events added by server mods.
+///
+/// Decoders are registered by the name of the game event and are called with the raw event values
+/// for every occurrence of the event.
+pub struct CustomEventAnalyser<T> {
+    decoders: HashMap<String, CustomEventDecoder<T>>,
+    events: Vec<CustomEvent<T>>,
+}
+
+impl<T> Default for CustomEventAnalyser<T> {
+    fn default() -> Self {
+        CustomEventAnalyser {
+            decoders: HashMap::new(),
+            events: Vec::new(),
+        }
+    }
+}
+
+impl<T> CustomEventAnalyser<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a decoder for the game event with the provided name
+    pub fn register(mut self, event_name: &str, decoder: CustomEventDecoder<T>) -> Self {
+        self.decoders.insert(event_name.into(), decoder);
+        self
+    }
+}
+
+impl<T> MessageHandler for CustomEventAnalyser<T> {
+    type Output = Vec<CustomEvent<T>>;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        matches!(message_type, MessageType::GameEvent)
+    }
+
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, _parser_state: &ParserState) {
+        if let Message::GameEvent(message) = message {
+            if let GameEvent::Unknown(raw) = &message.event {
+                if let Some(decoder) = self.decoders.get(raw.event_type.as_str()) {
+                    if let Some(event) = decoder(raw) {
+                        self.events.push(CustomEvent { tick, event });
+                    }
+                }
+            }
+        }
+    }
+
+    fn into_output(self, _state: &ParserState) -> Self::Output {
+        self.events
+    }
+}
+
+#[test]
+fn test_custom_event_decoder() {
+    use crate::demo::gamevent::{
+        GameEventDefinition, GameEventEntry, GameEventType, GameEventValue, GameEventValueType,
+    };
+    use crate::demo::message::gameevent::{GameEventMessage, GameEventTypeId};
+    use crate::demo::parser::{Encode, Parse};
+    use bitbuffer::{BitReadBuffer, BitReadStream, BitWriteStream, LittleEndian};
+
+    #[derive(Debug, PartialEq)]
+    struct BonusPoints {
+        user_id: u16,
+        points: u8,
+    }
+
+    fn decode_bonus_points(event: &RawGameEvent) -> Option<BonusPoints> {
+        match event.values.as_slice() {
+            [GameEventValue::Short(user_id), GameEventValue::Byte(points)] => Some(BonusPoints {
+                user_id: *user_id,
+                points: *points,
+            }),
+            _ => None,
+        }
+    }
+
+    let mut state = ParserState::new(24, |_| false, false);
+    state.event_definitions = vec![GameEventDefinition {
+        id: GameEventTypeId::from(0u16),
+        event_type: GameEventType::from_type_name("mod_bonus_points"),
+        entries: vec![
+            GameEventEntry {
+                name: "userid".into(),
+                kind: GameEventValueType::Short,
+            },
+            GameEventEntry {
+                name: "points".into(),
+                kind: GameEventValueType::Byte,
+            },
+        ],
+    }];
+
+    let mut data = Vec::new();
+    {
+        let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+        GameEventMessage {
+            event_type_id: GameEventTypeId::from(0u16),
+            event: GameEvent::Unknown(RawGameEvent {
+                event_type: GameEventType::from_type_name("mod_bonus_points"),
+                values: vec![GameEventValue::Short(12), GameEventValue::Byte(3)],
+            }),
+        }
+        .encode(&mut stream, &state)
+        .unwrap();
+    }
+    let mut stream = BitReadStream::new(BitReadBuffer::new(&data, LittleEndian));
+    let message = GameEventMessage::parse(&mut stream, &state).unwrap();
+
+    let mut analyser = CustomEventAnalyser::new().register("mod_bonus_points", decode_bonus_points);
+    analyser.handle_message(&Message::GameEvent(message), DemoTick::from(5u32), &state);
+
+    assert_eq!(
+        vec![CustomEvent {
+            tick: DemoTick::from(5u32),
+            event: BonusPoints {
+                user_id: 12,
+                points: 3
+            }
+        }],
+        analyser.into_output(&state)
+    );
+}
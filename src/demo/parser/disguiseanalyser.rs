@@ -0,0 +1,111 @@
+use crate::demo::data::DemoTick;
+use crate::demo::message::packetentities::{EntityId, PacketEntity};
+use crate::demo::message::{Message, MessageType};
+use crate::demo::parser::analyser::{Class, Team, UserId};
+use crate::demo::parser::handler::MessageHandler;
+use crate::demo::sendprop::SendPropIdentifier;
+use crate::ParserState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Disguise {
+    pub team: Team,
+    pub class: Class,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DisguiseChange {
+    pub tick: DemoTick,
+    pub user: UserId,
+    /// The disguise of the spy, `None` when undisguised
+    pub disguise: Option<Disguise>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct DisguiseProps {
+    team: Team,
+    class: Class,
+}
+
+impl DisguiseProps {
+    fn disguise(&self) -> Option<Disguise> {
+        (self.class != Class::Other).then_some(Disguise {
+            team: self.team,
+            class: self.class,
+        })
+    }
+}
+
+/// An analyser that tracks the disguises of spies.
+///
+/// The disguise props are only set once the spy finished disguising,
+/// so a change is recorded when the disguise is complete and when the spy loses their disguise.
+#[derive(Default, Debug)]
+pub struct DisguiseAnalyser {
+    changes: Vec<DisguiseChange>,
+    props: HashMap<EntityId, DisguiseProps>,
+}
+
+impl DisguiseAnalyser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn handle_entity(&mut self, entity: &PacketEntity, tick: DemoTick, parser_state: &ParserState) {
+        const DISGUISE_TEAM: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFPlayerShared", "m_nDisguiseTeam");
+        const DISGUISE_CLASS: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFPlayerShared", "m_nDisguiseClass");
+
+        let user = match parser_state.user_id_for_entity(entity.entity_index) {
+            Some(user) => user,
+            None => return,
+        };
+
+        let props = self.props.entry(entity.entity_index).or_default();
+        let previous = props.disguise();
+
+        for prop in entity.props(parser_state) {
+            match prop.identifier {
+                DISGUISE_TEAM => {
+                    props.team = Team::new(i64::try_from(&prop.value).unwrap_or_default())
+                }
+                DISGUISE_CLASS => {
+                    props.class = Class::new(i64::try_from(&prop.value).unwrap_or_default())
+                }
+                _ => {}
+            }
+        }
+
+        let disguise = props.disguise();
+        if disguise != previous {
+            self.changes.push(DisguiseChange {
+                tick,
+                user,
+                disguise,
+            });
+        }
+    }
+}
+
+impl MessageHandler for DisguiseAnalyser {
+    type Output = Vec<DisguiseChange>;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        matches!(message_type, MessageType::PacketEntities)
+    }
+
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
+        if let Message::PacketEntities(message) = message {
+            for entity in &message.entities {
+                self.handle_entity(entity, tick, parser_state);
+            }
+        }
+    }
+
+    fn into_output(self, _state: &ParserState) -> Self::Output {
+        self.changes
+    }
+}
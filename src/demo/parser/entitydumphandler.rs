@@ -0,0 +1,117 @@
+use crate::demo::data::DemoTick;
+use crate::demo::message::packetentities::{EntityId, PacketEntity, UpdateType};
+use crate::demo::message::{Message, MessageType};
+use crate::demo::parser::handler::MessageHandler;
+use crate::demo::sendprop::{SendPropIdentifier, SendPropValue};
+use crate::ParserState;
+use serde::{Deserialize, Serialize};
+
+/// All entity updates in a single tick
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EntityTick {
+    pub tick: DemoTick,
+    /// The updated entities, entering entities contain their full props merged with the baseline
+    pub entities: Vec<PacketEntity>,
+    /// Entities deleted in the tick
+    pub removed: Vec<EntityId>,
+}
+
+/// The entity updates of a demo, sorted by tick
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct EntityDump {
+    pub ticks: Vec<EntityTick>,
+}
+
+impl EntityDump {
+    /// Get the entity updates of a tick, `None` if no entities were updated in the tick
+    pub fn at_tick(&self, tick: DemoTick) -> Option<&EntityTick> {
+        self.ticks
+            .binary_search_by_key(&tick, |entity_tick| entity_tick.tick)
+            .ok()
+            .map(|index| &self.ticks[index])
+    }
+
+    /// Get all updates for an entity, with the tick of the update
+    pub fn entity_updates(
+        &self,
+        entity: EntityId,
+    ) -> impl Iterator<Item = (DemoTick, &PacketEntity)> + '_ {
+        self.ticks.iter().flat_map(move |entity_tick| {
+            entity_tick
+                .entities
+                .iter()
+                .filter(move |update| update.entity_index == entity)
+                .map(move |update| (entity_tick.tick, update))
+        })
+    }
+
+    /// Get every value sent for a prop of an entity, with the tick the value was sent
+    pub fn prop_values(
+        &self,
+        entity: EntityId,
+        identifier: SendPropIdentifier,
+    ) -> impl Iterator<Item = (DemoTick, &SendPropValue)> + '_ {
+        self.entity_updates(entity).flat_map(move |(tick, update)| {
+            update
+                .props
+                .iter()
+                .filter(move |prop| prop.identifier == identifier)
+                .map(move |prop| (tick, &prop.value))
+        })
+    }
+}
+
+/// A handler that collects all entity updates with their raw prop values.
+///
+/// Only the props sent in an update are recorded, the props of an entity entering the pvs include the values
+/// from the baseline. Since every update is kept, the output for a full demo can be large.
+#[derive(Debug, Default)]
+pub struct EntityDumpHandler {
+    dump: EntityDump,
+}
+
+impl EntityDumpHandler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn tick_mut(&mut self, tick: DemoTick) -> &mut EntityTick {
+        let ticks = &mut self.dump.ticks;
+        if ticks.last().map(|entity_tick| entity_tick.tick) != Some(tick) {
+            ticks.push(EntityTick {
+                tick,
+                entities: Vec::new(),
+                removed: Vec::new(),
+            });
+        }
+        ticks.last_mut().unwrap()
+    }
+}
+
+impl MessageHandler for EntityDumpHandler {
+    type Output = EntityDump;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        matches!(message_type, MessageType::PacketEntities)
+    }
+
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
+        if let Message::PacketEntities(message) = message {
+            let entity_tick = self.tick_mut(tick);
+            for entity in &message.entities {
+                let mut update = entity.clone();
+                if entity.update_type == UpdateType::Enter {
+                    update.props = entity.props(parser_state).collect();
+                }
+                entity_tick.entities.push(update);
+            }
+            entity_tick
+                .removed
+                .extend_from_slice(&message.removed_entities);
+        }
+    }
+
+    fn into_output(self, _state: &ParserState) -> Self::Output {
+        self.dump
+    }
+}
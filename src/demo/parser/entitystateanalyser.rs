@@ -0,0 +1,73 @@
+use crate::demo::data::DemoTick;
+use crate::demo::message::packetentities::{EntityId, PacketEntity, UpdateType};
+use crate::demo::message::{Message, MessageType};
+use crate::demo::parser::handler::MessageHandler;
+use crate::ParserState;
+
+/// An analyser that reconstructs the full prop state of a single entity at a tick
+/// by applying all updates for the entity up to and including the tick.
+///
+/// The output is `None` if the entity doesn't exist at the tick.
+#[derive(Debug)]
+pub struct EntityStateAnalyser {
+    entity: EntityId,
+    tick: DemoTick,
+    state: Option<PacketEntity>,
+}
+
+impl EntityStateAnalyser {
+    pub fn new(entity: EntityId, tick: DemoTick) -> Self {
+        EntityStateAnalyser {
+            entity,
+            tick,
+            state: None,
+        }
+    }
+
+    fn handle_entity(&mut self, entity: &PacketEntity, parser_state: &ParserState) {
+        match (entity.update_type, &mut self.state) {
+            (UpdateType::Delete, _) => self.state = None,
+            (UpdateType::Leave, _) => {}
+            (UpdateType::Preserve, Some(state)) => state.apply_update(&entity.props),
+            (UpdateType::Enter, Some(state)) if !parser_state.is_new_entity(entity) => {
+                let props: Vec<_> = entity.props(parser_state).collect();
+                state.apply_update(&props);
+            }
+            // entering entities contain the full state, merged with the baseline
+            _ => {
+                self.state = Some(PacketEntity {
+                    props: entity.props(parser_state).collect(),
+                    ..entity.clone()
+                })
+            }
+        }
+    }
+}
+
+impl MessageHandler for EntityStateAnalyser {
+    type Output = Option<PacketEntity>;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        matches!(message_type, MessageType::PacketEntities)
+    }
+
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
+        if tick > self.tick {
+            return;
+        }
+        if let Message::PacketEntities(message) = message {
+            for entity in &message.entities {
+                if entity.entity_index == self.entity {
+                    self.handle_entity(entity, parser_state);
+                }
+            }
+            if message.removed_entities.contains(&self.entity) {
+                self.state = None;
+            }
+        }
+    }
+
+    fn into_output(self, _state: &ParserState) -> Self::Output {
+        self.state
+    }
+}
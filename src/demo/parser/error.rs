@@ -1,3 +1,4 @@
+use crate::demo::data::ServerTick;
 use crate::demo::gamevent::GameEventValueType;
 use crate::demo::message::gameevent::GameEventTypeId;
 use crate::demo::message::packetentities::EntityId;
@@ -81,6 +82,46 @@ pub enum ParseError {
     UnknownEntity(EntityId),
     #[error(display = "No sendprop definition found for property")]
     UnknownDefinition(SendPropIdentifier),
+    #[error(
+        display = "Entity update is a delta from tick {} for which no entities were received",
+        _0
+    )]
+    UnknownDeltaTick(ServerTick),
+}
+
+/// Non-fatal problems encountered while parsing, most of these are only recorded in lenient mode
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseWarning {
+    /// A property index outside of the send table was read,
+    /// the update of the entity and the remaining entity updates from the packet were skipped
+    ///
+    /// Delta entity updates are skipped until the next full entity update
+    PropIndexOutOfBounds {
+        entity: EntityId,
+        index: i32,
+        prop_count: usize,
+        table: String,
+        /// The number of skipped entity updates, starting with the update of `entity`.
+        /// The indexes of the entities after `entity` can't be read.
+        skipped_updates: u16,
+        /// Whether the list of removed entities from the packet was skipped
+        removed_skipped: bool,
+    },
+    /// An entity update was a delta from a tick for which no entities were received
+    UnknownDeltaTick {
+        delta: ServerTick,
+        /// Whether the entity updates from the packet were skipped,
+        /// depending on the [`UnknownDeltaPolicy`](crate::demo::parser::UnknownDeltaPolicy)
+        skipped: bool,
+    },
+    /// A game event was read with a type that isn't in the game event definitions,
+    /// the event was skipped
+    UnknownGameEventType(GameEventTypeId),
+    /// An entity update referenced an entity that was never created or a server class that doesn't exist,
+    /// the remaining entity updates from the packet were skipped
+    ///
+    /// Delta entity updates are skipped until the next full entity update
+    GhostEntity(EntityId),
 }
 
 #[derive(Debug, Error)]
@@ -0,0 +1,197 @@
+use crate::demo::data::DemoTick;
+use crate::demo::message::packetentities::{EntityId, PacketEntity, UpdateType};
+use crate::demo::message::{Message, MessageType};
+use crate::demo::packet::datatable::{ParseSendTable, ServerClass};
+use crate::demo::packet::message::MessagePacketMeta;
+use crate::demo::packet::stringtable::StringTableEntry;
+use crate::demo::parser::analyser::{Team, UserId};
+use crate::demo::parser::gamestateanalyser::{GameStateAnalyser, ENTITY_INDEX_MASK};
+use crate::demo::parser::handler::MessageHandler;
+use crate::demo::sendprop::SendPropIdentifier;
+use crate::demo::vector::Vector;
+use crate::ParserState;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FlagStatus {
+    #[default]
+    Home,
+    Carried,
+    Dropped,
+}
+
+impl FlagStatus {
+    pub fn new(number: i64) -> Self {
+        match number {
+            1 => FlagStatus::Carried,
+            2 => FlagStatus::Dropped,
+            _ => FlagStatus::Home,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct FlagState {
+    pub tick: DemoTick,
+    /// The team owning the flag
+    pub team: Team,
+    pub status: FlagStatus,
+    /// The position of the flag, or the position of the carrier while the flag is carried
+    pub position: Vector,
+    pub carrier: Option<UserId>,
+}
+
+#[derive(Debug, Default)]
+struct Flag {
+    team: Team,
+    status: FlagStatus,
+    origin: Vector,
+    owner: Option<EntityId>,
+    last_state: Option<FlagState>,
+}
+
+/// An analyser that tracks the status and position of the intelligence in capture the flag maps.
+///
+/// A new state is recorded every time the status or position of a flag changes.
+#[derive(Debug, Default)]
+pub struct FlagAnalyser {
+    game_state: GameStateAnalyser,
+    flags: BTreeMap<EntityId, Flag>,
+    states: Vec<FlagState>,
+}
+
+impl FlagAnalyser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn handle_entity(&mut self, entity: &PacketEntity, parser_state: &ParserState) {
+        const STATUS: SendPropIdentifier =
+            SendPropIdentifier::new("DT_CaptureFlag", "m_nFlagStatus");
+        const TEAM: SendPropIdentifier = SendPropIdentifier::new("DT_BaseEntity", "m_iTeamNum");
+        const ORIGIN: SendPropIdentifier = SendPropIdentifier::new("DT_BaseEntity", "m_vecOrigin");
+        const OWNER: SendPropIdentifier =
+            SendPropIdentifier::new("DT_BaseEntity", "m_hOwnerEntity");
+
+        if entity.update_type == UpdateType::Delete {
+            self.flags.remove(&entity.entity_index);
+            return;
+        }
+
+        let class_name = parser_state
+            .server_classes
+            .get(usize::from(entity.server_class))
+            .map(|class| class.name.as_str());
+        if class_name != Some("CCaptureFlag") {
+            return;
+        }
+
+        let flag = self.flags.entry(entity.entity_index).or_default();
+        for prop in entity.props(parser_state) {
+            match prop.identifier {
+                STATUS => {
+                    flag.status = FlagStatus::new(i64::try_from(&prop.value).unwrap_or_default())
+                }
+                TEAM => flag.team = Team::new(i64::try_from(&prop.value).unwrap_or_default()),
+                ORIGIN => flag.origin = Vector::try_from(&prop.value).unwrap_or_default(),
+                OWNER => {
+                    let handle = i64::try_from(&prop.value).unwrap_or_default() as u32;
+                    flag.owner = (handle & ENTITY_INDEX_MASK != ENTITY_INDEX_MASK)
+                        .then(|| EntityId::from(handle & ENTITY_INDEX_MASK));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn record_states(&mut self, tick: DemoTick) {
+        for flag in self.flags.values_mut() {
+            let carrier = match (flag.status, flag.owner) {
+                (FlagStatus::Carried, Some(owner)) => self
+                    .game_state
+                    .state
+                    .players
+                    .iter()
+                    .find(|player| player.entity_id() == owner),
+                _ => None,
+            };
+            let state = FlagState {
+                tick,
+                team: flag.team,
+                status: flag.status,
+                position: carrier.map(|player| player.position).unwrap_or(flag.origin),
+                carrier: carrier
+                    .and_then(|player| player.info.as_ref())
+                    .map(|info| info.user_id),
+            };
+
+            let changed = match &flag.last_state {
+                Some(last) => {
+                    last.status != state.status
+                        || last.position != state.position
+                        || last.carrier != state.carrier
+                }
+                None => true,
+            };
+            if changed {
+                flag.last_state = Some(state);
+                self.states.push(state);
+            }
+        }
+    }
+}
+
+impl MessageHandler for FlagAnalyser {
+    type Output = Vec<FlagState>;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        matches!(message_type, MessageType::PacketEntities)
+    }
+
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
+        self.game_state.handle_message(message, tick, parser_state);
+        if let Message::PacketEntities(message) = message {
+            for entity in &message.entities {
+                self.handle_entity(entity, parser_state);
+            }
+            self.record_states(tick);
+        }
+    }
+
+    fn handle_string_entry(
+        &mut self,
+        table: &str,
+        index: usize,
+        entry: &StringTableEntry,
+        parser_state: &ParserState,
+    ) {
+        self.game_state
+            .handle_string_entry(table, index, entry, parser_state)
+    }
+
+    fn handle_data_tables(
+        &mut self,
+        tables: &[ParseSendTable],
+        server_classes: &[ServerClass],
+        parser_state: &ParserState,
+    ) {
+        self.game_state
+            .handle_data_tables(tables, server_classes, parser_state)
+    }
+
+    fn handle_packet_meta(
+        &mut self,
+        tick: DemoTick,
+        meta: &MessagePacketMeta,
+        parser_state: &ParserState,
+    ) {
+        self.game_state.handle_packet_meta(tick, meta, parser_state)
+    }
+
+    fn into_output(self, _state: &ParserState) -> Self::Output {
+        self.states
+    }
+}
@@ -1,8 +1,9 @@
 use crate::demo::data::DemoTick;
-use crate::demo::gameevent_gen::{ObjectDestroyedEvent, PlayerDeathEvent};
+use crate::demo::gameevent_gen::{ObjectDestroyedEvent, PlayerDeathEvent, PlayerHurtEvent};
 use crate::demo::gamevent::GameEvent;
 use crate::demo::message::gameevent::GameEventMessage;
 use crate::demo::message::packetentities::{EntityId, PacketEntity, UpdateType};
+use crate::demo::message::tempentities::EventInfo;
 use crate::demo::message::Message;
 use crate::demo::packet::datatable::{ParseSendTable, ServerClass, ServerClassName};
 use crate::demo::packet::message::MessagePacketMeta;
@@ -15,12 +16,15 @@ use crate::demo::sendprop::{SendProp, SendPropIdentifier, SendPropValue};
 use crate::demo::vector::{Vector, VectorXY};
 use crate::{MessageType, ParserState, ReadResult, Stream};
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::TryFrom;
 use std::str::FromStr;
 
 pub struct CachedEntities {}
 
+/// Entity handles contain the entity index in the lower 11 bits, the rest is the serial number
+pub(crate) const ENTITY_INDEX_MASK: u32 = (1 << 11) - 1;
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
 pub enum PlayerState {
     #[default]
@@ -54,6 +58,35 @@ pub struct Player {
     pub state: PlayerState,
     pub info: Option<UserInfo>,
     pub charge: u8,
+    pub stats: PlayerStats,
+}
+
+impl Player {
+    pub fn entity_id(&self) -> EntityId {
+        self.entity
+    }
+}
+
+/// Shot statistics for a player
+///
+/// Only hitscan weapons are counted, since only those network an event for every shot fired.
+/// Projectile weapons can't be measured this way.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct PlayerStats {
+    pub shots_fired: u32,
+    /// Number of shots that damaged at least one player
+    pub shots_hit: u32,
+}
+
+impl PlayerStats {
+    /// Fraction of the shots that hit, or `None` if no hitscan shots have been fired
+    pub fn accuracy(&self) -> Option<f32> {
+        if self.shots_fired == 0 {
+            None
+        } else {
+            Some(self.shots_hit.min(self.shots_fired) as f32 / self.shots_fired as f32)
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
@@ -211,12 +244,13 @@ impl Building {
     pub fn class(&self) -> BuildingClass {
         match self {
             Building::Sentry(_) => BuildingClass::Sentry,
-            Building::Dispenser(_) => BuildingClass::Sentry,
+            Building::Dispenser(_) => BuildingClass::Dispenser,
             Building::Teleporter(_) => BuildingClass::Teleporter,
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub enum BuildingClass {
     Sentry,
     Dispenser,
@@ -257,6 +291,9 @@ pub struct GameState {
     pub world: Option<World>,
     pub kills: Vec<Kill>,
     pub tick: DemoTick,
+    /// The number of control points on the map, as sent by the objective resource
+    #[serde(default)]
+    pub control_point_count: Option<u8>,
 }
 
 impl GameState {
@@ -301,6 +338,10 @@ pub struct GameStateAnalyser {
     pub state: GameState,
     tick: DemoTick,
     class_names: Vec<ServerClassName>, // indexed by ClassId
+    fire_bullets_player: u32,
+    fire_bullets_weapon: u16,
+    fired_weapons: HashSet<(EntityId, u16)>,
+    last_hit: HashMap<UserId, DemoTick>,
 }
 
 impl MessageHandler for GameStateAnalyser {
@@ -309,7 +350,7 @@ impl MessageHandler for GameStateAnalyser {
     fn does_handle(message_type: MessageType) -> bool {
         matches!(
             message_type,
-            MessageType::PacketEntities | MessageType::GameEvent
+            MessageType::PacketEntities | MessageType::GameEvent | MessageType::TempEntities
         )
     }
 
@@ -320,10 +361,16 @@ impl MessageHandler for GameStateAnalyser {
                     self.handle_entity(entity, parser_state);
                 }
             }
+            Message::TempEntities(message) => {
+                for event in &message.events {
+                    self.handle_temp_entity(event);
+                }
+            }
             Message::GameEvent(GameEventMessage { event, .. }) => match event {
                 GameEvent::PlayerDeath(death) => {
                     self.state.kills.push(Kill::new(self.tick, death.as_ref()))
                 }
+                GameEvent::PlayerHurt(hurt) => self.handle_player_hurt(hurt),
                 GameEvent::RoundStart(_) => {
                     self.state.buildings.clear();
                 }
@@ -400,10 +447,15 @@ impl GameStateAnalyser {
             .get(usize::from(entity.server_class))
             .map(|class_name| class_name.as_str())
             .unwrap_or("");
+        // a different entity re-using the index of a building that wasn't deleted
+        if parser_state.is_new_entity(entity) {
+            self.state.remove_building(entity.entity_index);
+        }
         match class_name {
             "CTFPlayer" => self.handle_player_entity(entity, parser_state),
             "CTFPlayerResource" => self.handle_player_resource(entity, parser_state),
             "CWorld" => self.handle_world_entity(entity, parser_state),
+            "CTFObjectiveResource" => self.handle_objective_resource(entity, parser_state),
             "CObjectSentrygun" => self.handle_sentry_entity(entity, parser_state),
             "CObjectDispenser" => self.handle_dispenser_entity(entity, parser_state),
             "CObjectTeleporter" => self.handle_teleporter_entity(entity, parser_state),
@@ -411,6 +463,76 @@ impl GameStateAnalyser {
         }
     }
 
+    fn handle_temp_entity(&mut self, event: &EventInfo) {
+        const PLAYER_PROP: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TEFireBullets", "m_iPlayer");
+        const WEAPON_PROP: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TEFireBullets", "m_iWeaponID");
+
+        let class_name = self
+            .class_names
+            .get(usize::from(event.class_id))
+            .map(|class_name| class_name.as_str());
+        if class_name != Some("CTEFireBullets") {
+            return;
+        }
+
+        // temp entity props that didn't change since the previous bullets aren't sent
+        for prop in &event.props {
+            match prop.identifier {
+                PLAYER_PROP => {
+                    self.fire_bullets_player = i64::try_from(&prop.value).unwrap_or_default() as u32
+                }
+                WEAPON_PROP => {
+                    self.fire_bullets_weapon = i64::try_from(&prop.value).unwrap_or_default() as u16
+                }
+                _ => {}
+            }
+        }
+
+        // the player is send as entity index - 1
+        let entity_id = EntityId::from(self.fire_bullets_player + 1);
+        self.fired_weapons
+            .insert((entity_id, self.fire_bullets_weapon));
+        if let Some(player) = self
+            .state
+            .players
+            .iter_mut()
+            .find(|player| player.entity == entity_id)
+        {
+            player.stats.shots_fired += 1;
+        }
+    }
+
+    fn handle_player_hurt(&mut self, hurt: &PlayerHurtEvent) {
+        if hurt.attacker == hurt.user_id {
+            return;
+        }
+        let attacker = UserId::from(hurt.attacker);
+
+        if let Some(player) = self.state.players.iter_mut().find(|player| {
+            player
+                .info
+                .as_ref()
+                .map(|info| info.user_id == attacker)
+                .unwrap_or_default()
+        }) {
+            // only count damage from weapons we've seen the player fire bullets with,
+            // the recording player's own bullets are predicted and not included in the demo
+            if !self
+                .fired_weapons
+                .contains(&(player.entity, hurt.weapon_id))
+            {
+                return;
+            }
+
+            // a single shot can damage multiple players
+            if self.last_hit.insert(attacker, self.tick) != Some(self.tick) {
+                player.stats.shots_hit += 1;
+            }
+        }
+    }
+
     pub fn handle_player_resource(&mut self, entity: &PacketEntity, parser_state: &ParserState) {
         for prop in entity.props(parser_state) {
             if let Some((table_name, prop_name)) = prop.identifier.names() {
@@ -524,6 +646,17 @@ impl GameStateAnalyser {
         }
     }
 
+    pub fn handle_objective_resource(&mut self, entity: &PacketEntity, parser_state: &ParserState) {
+        const NUM_CONTROL_POINTS: SendPropIdentifier =
+            SendPropIdentifier::new("DT_BaseTeamObjectiveResource", "m_iNumControlPoints");
+
+        if let Some(prop) = entity.get_prop_by_identifier(&NUM_CONTROL_POINTS, parser_state) {
+            self.state.control_point_count = i64::try_from(&prop.value)
+                .ok()
+                .and_then(|count| u8::try_from(count).ok());
+        }
+    }
+
     pub fn handle_sentry_entity(&mut self, entity: &PacketEntity, parser_state: &ParserState) {
         const ANGLE: SendPropIdentifier =
             SendPropIdentifier::new("DT_TFNonLocalPlayerExclusive", "m_angEyeAngles[1]");
@@ -671,10 +804,6 @@ impl GameStateAnalyser {
         parser_state: &ParserState,
         class: BuildingClass,
     ) {
-        let building = self
-            .state
-            .get_or_create_building(entity.entity_index, class);
-
         const LOCAL_ORIGIN: SendPropIdentifier =
             SendPropIdentifier::new("DT_BaseEntity", "m_vecOrigin");
         const TEAM: SendPropIdentifier = SendPropIdentifier::new("DT_BaseEntity", "m_iTeamNum");
@@ -689,6 +818,14 @@ impl GameStateAnalyser {
             SendPropIdentifier::new("DT_BaseObject", "m_iMaxHealth");
         const HEALTH: SendPropIdentifier = SendPropIdentifier::new("DT_BaseObject", "m_iHealth");
 
+        let builder_user = entity
+            .get_prop_by_identifier(&BUILDER, parser_state)
+            .and_then(|prop| self.user_for_handle(i64::try_from(&prop.value).unwrap_or_default()));
+
+        let building = self
+            .state
+            .get_or_create_building(entity.entity_index, class);
+
         match building {
             Building::Sentry(Sentry {
                 position,
@@ -737,8 +874,9 @@ impl GameStateAnalyser {
                         BUILDING => *building = i64::try_from(&prop.value).unwrap_or_default() > 0,
                         LEVEL => *level = i64::try_from(&prop.value).unwrap_or_default() as u8,
                         BUILDER => {
-                            *builder =
-                                UserId::from(i64::try_from(&prop.value).unwrap_or_default() as u16)
+                            if let Some(user) = builder_user {
+                                *builder = user
+                            }
                         }
                         MAX_HEALTH => {
                             *max_health = i64::try_from(&prop.value).unwrap_or_default() as u16
@@ -751,6 +889,16 @@ impl GameStateAnalyser {
         }
     }
 
+    fn user_for_handle(&self, handle: i64) -> Option<UserId> {
+        let entity = EntityId::from(handle as u32 & ENTITY_INDEX_MASK);
+        self.state
+            .players
+            .iter()
+            .find(|player| player.entity == entity)
+            .and_then(|player| player.info.as_ref())
+            .map(|info| info.user_id)
+    }
+
     fn parse_user_info(
         &mut self,
         index: usize,
@@ -0,0 +1,102 @@
+use crate::demo::data::DemoTick;
+use crate::demo::message::packetentities::{EntityId, PacketEntity};
+use crate::demo::message::{Message, MessageType};
+use crate::demo::parser::analyser::UserId;
+use crate::demo::parser::gamestateanalyser::ENTITY_INDEX_MASK;
+use crate::demo::parser::handler::MessageHandler;
+use crate::demo::sendprop::SendPropIdentifier;
+use crate::ParserState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+/// What a player is standing on
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum GroundKind {
+    /// The player is airborne
+    None,
+    World,
+    Player(UserId),
+    /// Any other entity, such as a building or a moving platform
+    Other(EntityId),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GroundEntityChange {
+    pub tick: DemoTick,
+    pub user: UserId,
+    pub ground: GroundKind,
+}
+
+/// An analyser that tracks the ground entity (`m_hGroundEntity`) of players.
+///
+/// A change is recorded every time a player lands on, jumps off or moves to a different ground entity.
+///
+/// The ground entity is part of `DT_LocalPlayerExclusive`, which the server only sends for the player
+/// the demo is recorded from. For POV demos this only tracks the recording player, STV demos contain
+/// no ground entity changes at all.
+#[derive(Default, Debug)]
+pub struct GroundEntityAnalyser {
+    changes: Vec<GroundEntityChange>,
+    ground: HashMap<EntityId, GroundKind>,
+}
+
+impl GroundEntityAnalyser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn handle_entity(&mut self, entity: &PacketEntity, tick: DemoTick, parser_state: &ParserState) {
+        const GROUND_ENTITY: SendPropIdentifier =
+            SendPropIdentifier::new("DT_LocalPlayerExclusive", "m_hGroundEntity");
+
+        let user = match parser_state.user_id_for_entity(entity.entity_index) {
+            Some(user) => user,
+            None => return,
+        };
+
+        if let Some(prop) = entity.get_prop_by_identifier(&GROUND_ENTITY, parser_state) {
+            let handle = i64::try_from(&prop.value)
+                .map(|handle| handle as u32)
+                .unwrap_or(ENTITY_INDEX_MASK);
+            let ground = resolve_handle(handle, parser_state);
+            if self.ground.insert(entity.entity_index, ground) != Some(ground) {
+                self.changes.push(GroundEntityChange { tick, user, ground });
+            }
+        }
+    }
+}
+
+fn resolve_handle(handle: u32, parser_state: &ParserState) -> GroundKind {
+    if handle & ENTITY_INDEX_MASK == ENTITY_INDEX_MASK {
+        return GroundKind::None;
+    }
+    let entity = EntityId::from(handle & ENTITY_INDEX_MASK);
+    if entity == 0u32 {
+        GroundKind::World
+    } else if let Some(user) = parser_state.user_id_for_entity(entity) {
+        GroundKind::Player(user)
+    } else {
+        GroundKind::Other(entity)
+    }
+}
+
+impl MessageHandler for GroundEntityAnalyser {
+    type Output = Vec<GroundEntityChange>;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        matches!(message_type, MessageType::PacketEntities)
+    }
+
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
+        if let Message::PacketEntities(message) = message {
+            for entity in &message.entities {
+                self.handle_entity(entity, tick, parser_state);
+            }
+        }
+    }
+
+    fn into_output(self, _state: &ParserState) -> Self::Output {
+        self.changes
+    }
+}
@@ -20,6 +20,19 @@ pub trait MessageHandler {
     fn handle_message(&mut self, _message: &Message, _tick: DemoTick, _parser_state: &ParserState) {
     }
 
+    /// Handle a message from before the start of the range when parsing with [`DemoParser::parse_range`](crate::DemoParser::parse_range)
+    ///
+    /// Handlers can use this to track the state they need to correctly handle the messages in the range,
+    /// such as the teams of the players, without adding anything to their output.
+    /// By default messages from before the range are ignored.
+    fn handle_message_before_range(
+        &mut self,
+        _message: &Message,
+        _tick: DemoTick,
+        _parser_state: &ParserState,
+    ) {
+    }
+
     fn handle_string_entry(
         &mut self,
         _table: &str,
@@ -64,6 +77,78 @@ impl MessageHandler for NullHandler {
     fn into_output(self, _state: &ParserState) -> Self::Output {}
 }
 
+/// Run multiple handlers over a single pass of the demo by combining them in a tuple,
+/// the output is a tuple of the outputs of the handlers.
+///
+/// All handlers receive the messages that any of the handlers handles.
+macro_rules! impl_tuple_handler {
+    ($($handler:ident),+) => {
+        #[allow(non_snake_case)]
+        impl<$($handler: MessageHandler),+> MessageHandler for ($($handler,)+) {
+            type Output = ($($handler::Output,)+);
+
+            fn does_handle(message_type: MessageType) -> bool {
+                $($handler::does_handle(message_type))||+
+            }
+
+            fn handle_header(&mut self, header: &Header) {
+                let ($($handler,)+) = self;
+                $($handler.handle_header(header);)+
+            }
+
+            fn handle_message(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
+                let ($($handler,)+) = self;
+                $($handler.handle_message(message, tick, parser_state);)+
+            }
+
+            fn handle_message_before_range(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
+                let ($($handler,)+) = self;
+                $($handler.handle_message_before_range(message, tick, parser_state);)+
+            }
+
+            fn handle_string_entry(
+                &mut self,
+                table: &str,
+                index: usize,
+                entries: &StringTableEntry,
+                parser_state: &ParserState,
+            ) {
+                let ($($handler,)+) = self;
+                $($handler.handle_string_entry(table, index, entries, parser_state);)+
+            }
+
+            fn handle_data_tables(
+                &mut self,
+                tables: &[ParseSendTable],
+                server_classes: &[ServerClass],
+                parser_state: &ParserState,
+            ) {
+                let ($($handler,)+) = self;
+                $($handler.handle_data_tables(tables, server_classes, parser_state);)+
+            }
+
+            fn handle_packet_meta(
+                &mut self,
+                tick: DemoTick,
+                meta: &MessagePacketMeta,
+                parser_state: &ParserState,
+            ) {
+                let ($($handler,)+) = self;
+                $($handler.handle_packet_meta(tick, meta, parser_state);)+
+            }
+
+            fn into_output(self, state: &ParserState) -> Self::Output {
+                let ($($handler,)+) = self;
+                ($($handler.into_output(state),)+)
+            }
+        }
+    };
+}
+
+impl_tuple_handler!(A, B);
+impl_tuple_handler!(A, B, C);
+impl_tuple_handler!(A, B, C, D);
+
 #[derive(Clone)]
 pub struct DemoHandler<'a, T: MessageHandler> {
     pub server_tick: ServerTick,
@@ -115,6 +200,18 @@ impl<'a, T: MessageHandler> DemoHandler<'a, T> {
     }
 
     pub fn handle_packet(&mut self, packet: Packet<'a>) -> Result<()> {
+        self.handle_packet_with(packet, true)
+    }
+
+    /// Handle a packet from before the start of a parsed range, the messages in it only update the parser state
+    /// and are passed to [`MessageHandler::handle_message_before_range`] instead of `handle_message`.
+    ///
+    /// String table and data table updates are still passed to the analyser.
+    pub fn handle_packet_before_range(&mut self, packet: Packet<'a>) -> Result<()> {
+        self.handle_packet_with(packet, false)
+    }
+
+    fn handle_packet_with(&mut self, packet: Packet<'a>, handle_messages: bool) -> Result<()> {
         match packet {
             Packet::DataTables(packet) => {
                 self.handle_data_table(packet.tables, packet.server_classes)?;
@@ -125,13 +222,22 @@ impl<'a, T: MessageHandler> DemoHandler<'a, T> {
                 }
             }
             Packet::Message(packet) | Packet::Signon(packet) => {
-                self.analyser
-                    .handle_packet_meta(packet.tick, &packet.meta, &self.state_handler);
+                if handle_messages {
+                    self.analyser.handle_packet_meta(
+                        packet.tick,
+                        &packet.meta,
+                        &self.state_handler,
+                    );
+                }
                 for message in packet.messages {
                     match message {
                         Message::NetTick(message) => {
                             self.server_tick = message.tick;
-                            self.handle_message(Message::NetTick(message), packet.tick)
+                            self.handle_message_with(
+                                Message::NetTick(message),
+                                packet.tick,
+                                handle_messages,
+                            )
                         }
                         Message::CreateStringTable(message) => {
                             self.handle_string_table(message.table)
@@ -139,10 +245,12 @@ impl<'a, T: MessageHandler> DemoHandler<'a, T> {
                         Message::UpdateStringTable(message) => {
                             self.handle_table_update(message.table_id, message.entries)
                         }
-                        Message::PacketEntities(msg) => {
-                            self.handle_message(Message::PacketEntities(msg), packet.tick)
-                        }
-                        message => self.handle_message(message, packet.tick),
+                        Message::PacketEntities(msg) => self.handle_message_with(
+                            Message::PacketEntities(msg),
+                            packet.tick,
+                            handle_messages,
+                        ),
+                        message => self.handle_message_with(message, packet.tick, handle_messages),
                     }
                 }
             }
@@ -193,10 +301,19 @@ impl<'a, T: MessageHandler> DemoHandler<'a, T> {
     }
 
     pub fn handle_message(&mut self, message: Message<'a>, tick: DemoTick) {
+        self.handle_message_with(message, tick, true)
+    }
+
+    fn handle_message_with(&mut self, message: Message<'a>, tick: DemoTick, handle: bool) {
         let message_type = message.get_message_type();
         if T::does_handle(message_type) {
-            self.analyser
-                .handle_message(&message, tick, &self.state_handler);
+            if handle {
+                self.analyser
+                    .handle_message(&message, tick, &self.state_handler);
+            } else {
+                self.analyser
+                    .handle_message_before_range(&message, tick, &self.state_handler);
+            }
         }
         self.state_handler.handle_message(message, tick);
     }
@@ -0,0 +1,113 @@
+use crate::demo::data::DemoTick;
+use crate::demo::message::packetentities::{EntityId, PacketEntity, UpdateType};
+use crate::demo::message::{Message, MessageType};
+use crate::demo::parser::analyser::UserId;
+use crate::demo::parser::gamestateanalyser::ENTITY_INDEX_MASK;
+use crate::demo::parser::handler::MessageHandler;
+use crate::demo::sendprop::SendPropIdentifier;
+use crate::ParserState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+/// The player a medic started healing, `None` when the medic stopped healing
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HealTarget {
+    pub tick: DemoTick,
+    pub medic: UserId,
+    pub target: Option<UserId>,
+}
+
+#[derive(Debug, Default)]
+struct Medigun {
+    owner: Option<EntityId>,
+    target: Option<EntityId>,
+}
+
+/// An analyser that records the heal target of every medigun every time it changes.
+///
+/// Unlike the deploy target from the uber events this follows who the medic is healing from moment to moment.
+#[derive(Default, Debug)]
+pub struct HealTargetAnalyser {
+    targets: Vec<HealTarget>,
+    mediguns: HashMap<EntityId, Medigun>,
+}
+
+impl HealTargetAnalyser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn handle_entity(&mut self, entity: &PacketEntity, tick: DemoTick, parser_state: &ParserState) {
+        const OWNER: SendPropIdentifier =
+            SendPropIdentifier::new("DT_BaseCombatWeapon", "m_hOwner");
+        const HEALING_TARGET: SendPropIdentifier =
+            SendPropIdentifier::new("DT_WeaponMedigun", "m_hHealingTarget");
+
+        if entity.update_type == UpdateType::Delete {
+            self.mediguns.remove(&entity.entity_index);
+            return;
+        }
+
+        let mut target = None;
+        let mut owner = None;
+        for prop in entity.props(parser_state) {
+            match prop.identifier {
+                OWNER => {
+                    let handle = i64::try_from(&prop.value).unwrap_or_default();
+                    owner = Some(EntityId::from(handle as u32 & ENTITY_INDEX_MASK));
+                }
+                HEALING_TARGET => {
+                    let handle = i64::try_from(&prop.value).unwrap_or_default();
+                    target = Some(EntityId::from(handle as u32 & ENTITY_INDEX_MASK));
+                }
+                _ => {}
+            }
+        }
+
+        // only mediguns have a healing target
+        if target.is_none() && !self.mediguns.contains_key(&entity.entity_index) {
+            return;
+        }
+
+        let medigun = self.mediguns.entry(entity.entity_index).or_default();
+        if owner.is_some() {
+            medigun.owner = owner;
+        }
+        if target.is_some() && target != medigun.target {
+            medigun.target = target;
+            if let Some(medic) = medigun
+                .owner
+                .and_then(|owner| parser_state.user_id_for_entity(owner))
+            {
+                // invalid handles don't point to a player
+                let target = target.and_then(|target| parser_state.user_id_for_entity(target));
+                self.targets.push(HealTarget {
+                    tick,
+                    medic,
+                    target,
+                });
+            }
+        }
+    }
+}
+
+impl MessageHandler for HealTargetAnalyser {
+    type Output = Vec<HealTarget>;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        matches!(message_type, MessageType::PacketEntities)
+    }
+
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
+        if let Message::PacketEntities(message) = message {
+            for entity in &message.entities {
+                self.handle_entity(entity, tick, parser_state);
+            }
+        }
+    }
+
+    fn into_output(self, _state: &ParserState) -> Self::Output {
+        self.targets
+    }
+}
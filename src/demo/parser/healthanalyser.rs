@@ -0,0 +1,89 @@
+use crate::demo::data::DemoTick;
+use crate::demo::message::packetentities::{EntityId, PacketEntity, UpdateType};
+use crate::demo::message::{Message, MessageType};
+use crate::demo::parser::analyser::UserId;
+use crate::demo::parser::handler::{BorrowMessageHandler, MessageHandler};
+use crate::demo::sendprop::SendPropIdentifier;
+use crate::ParserState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+/// The health of a player after it changed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HealthSample {
+    pub tick: DemoTick,
+    pub user: UserId,
+    pub health: u16,
+}
+
+/// An analyser that records the health of every player each time it changes, sorted by tick.
+///
+/// Combine it with the [`Analyser`](crate::demo::parser::analyser::Analyser) in a tuple to get the health
+/// alongside the [`MatchState`](crate::MatchState).
+#[derive(Default, Debug)]
+pub struct HealthAnalyser {
+    samples: Vec<HealthSample>,
+    health: HashMap<EntityId, u16>,
+}
+
+impl HealthAnalyser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn handle_entity(&mut self, entity: &PacketEntity, tick: DemoTick, parser_state: &ParserState) {
+        const HEALTH: SendPropIdentifier = SendPropIdentifier::new("DT_BasePlayer", "m_iHealth");
+
+        if entity.update_type == UpdateType::Delete {
+            self.health.remove(&entity.entity_index);
+            return;
+        }
+
+        let class_name = parser_state
+            .server_classes
+            .get(usize::from(entity.server_class))
+            .map(|class| class.name.as_str());
+        if class_name != Some("CTFPlayer") {
+            return;
+        }
+
+        // delta updates only contain the health when it changed, so we only need to record those
+        let health = match entity.get_prop_by_identifier(&HEALTH, parser_state) {
+            Some(prop) => i64::try_from(&prop.value).unwrap_or_default() as u16,
+            None => return,
+        };
+        if self.health.insert(entity.entity_index, health) == Some(health) {
+            return;
+        }
+        if let Some(user) = parser_state.user_id_for_entity(entity.entity_index) {
+            self.samples.push(HealthSample { tick, user, health });
+        }
+    }
+}
+
+impl MessageHandler for HealthAnalyser {
+    type Output = Vec<HealthSample>;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        matches!(message_type, MessageType::PacketEntities)
+    }
+
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
+        if let Message::PacketEntities(message) = message {
+            for entity in &message.entities {
+                self.handle_entity(entity, tick, parser_state);
+            }
+        }
+    }
+
+    fn into_output(self, _state: &ParserState) -> Self::Output {
+        self.samples
+    }
+}
+
+impl BorrowMessageHandler for HealthAnalyser {
+    fn borrow_output(&self, _state: &ParserState) -> &Self::Output {
+        &self.samples
+    }
+}
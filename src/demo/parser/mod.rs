@@ -1,24 +1,56 @@
 use crate::demo::data::DemoTick;
 use bitbuffer::{BitError, BitRead, BitWrite, BitWriteStream, LittleEndian};
 
+pub use self::customeventanalyser::{CustomEvent, CustomEventAnalyser, CustomEventDecoder};
 pub use self::messagetypeanalyser::MessageTypeAnalyser;
 
 use crate::demo::header::Header;
+use crate::demo::message::packetentities::{EntityId, PacketEntity};
+use crate::demo::message::Message;
 
-use crate::demo::packet::Packet;
+use crate::demo::packet::datatable::{FlatPropsCache, SendTable, ServerClass};
+use crate::demo::packet::message::MessagePacketMeta;
+use crate::demo::packet::{Packet, PacketType};
 use crate::demo::parser::analyser::Analyser;
 pub use crate::demo::parser::analyser::MatchState;
+use crate::demo::parser::entitystateanalyser::EntityStateAnalyser;
 pub use crate::demo::parser::handler::{DemoHandler, MessageHandler, NullHandler};
-pub use crate::demo::parser::state::ParserState;
+pub use crate::demo::parser::state::{DuplicateEnterPolicy, ParserState, UnknownDeltaPolicy};
 use crate::Stream;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 
 pub mod analyser;
+pub mod buildinganalyser;
+pub mod chargeanalyser;
+pub mod classanalyser;
+pub mod coalesce;
+pub mod consoleanalyser;
+pub mod customeventanalyser;
+pub mod disguiseanalyser;
+pub mod entitydumphandler;
+pub mod entitystateanalyser;
 pub mod error;
+pub mod flaganalyser;
 pub mod gamestateanalyser;
+pub mod groundentityanalyser;
 pub mod handler;
+pub mod healtargetanalyser;
+pub mod healthanalyser;
 pub mod messagetypeanalyser;
 pub mod player_summary_analyzer;
+pub mod positionanalyser;
+pub mod propwatchhandler;
+pub mod punchangleanalyser;
+pub mod roundtimeranalyser;
+pub mod scoreboardanalyser;
+pub mod shotanalyser;
 pub mod state;
+pub mod tauntanalyser;
+pub mod teamscoreanalyser;
+pub mod tickbaseanalyser;
+pub mod uberanalyser;
+pub mod weapons;
 
 pub use self::error::*;
 use crate::demo::parser::handler::BorrowMessageHandler;
@@ -70,6 +102,20 @@ impl<'a> DemoParser<'a, Analyser> {
     pub fn new_all(stream: Stream<'a>) -> DemoParser<Analyser> {
         DemoParser::new_all_with_analyser(stream, Analyser::new())
     }
+
+    /// Parse the demo until the end of the first full round, for quickly generating a preview.
+    ///
+    /// The stages of a multi-stage map don't count as a full round, the returned state contains
+    /// the rounds for every stage of the first full round.
+    pub fn parse_first_round(self) -> Result<(Header, MatchState)> {
+        let (header, mut ticker) = self.ticker()?;
+        while ticker.tick()? {
+            if ticker.state().rounds.iter().any(|round| round.full_round) {
+                break;
+            }
+        }
+        Ok((header, ticker.into_state()))
+    }
 }
 
 impl<'a, A: MessageHandler> DemoParser<'a, A> {
@@ -87,6 +133,74 @@ impl<'a, A: MessageHandler> DemoParser<'a, A> {
         }
     }
 
+    /// Re-use flattened send table props between parsers with a shared cache
+    pub fn with_flat_props_cache(mut self, cache: FlatPropsCache) -> Self {
+        self.handler.state_handler.flat_props_cache = Some(cache);
+        self
+    }
+
+    /// Limit the number of decoded static baselines kept in memory, evicting the least recently used ones
+    pub fn with_baseline_cache_limit(mut self, limit: usize) -> Self {
+        self.handler
+            .state_handler
+            .set_baseline_cache_limit(Some(limit));
+        self
+    }
+
+    /// Set how an entity entering at the index of an entity that is still live is handled
+    pub fn with_duplicate_enter_policy(mut self, policy: DuplicateEnterPolicy) -> Self {
+        self.handler.state_handler.duplicate_enter_policy = policy;
+        self
+    }
+
+    /// Set how entity updates that are a delta from a tick without received entity updates are handled,
+    /// by default the updates are applied and a warning is recorded
+    pub fn with_unknown_delta_policy(mut self, policy: UnknownDeltaPolicy) -> Self {
+        self.handler.state_handler.unknown_delta_policy = policy;
+        self
+    }
+
+    /// Skip the remaining entity updates from a packet when encountering malformed entity data
+    /// and skip game events of unknown type instead of failing the parse.
+    ///
+    /// Since the skipped entity updates are missing from the entity state, all delta entity updates
+    /// are skipped until the next full entity update.
+    /// Any skipped data is recorded as a warning in the parser state
+    pub fn with_lenient_mode(mut self) -> Self {
+        self.handler.state_handler.lenient = true;
+        self
+    }
+
+    /// Reconstruct the full prop state of an entity at a tick by replaying the entity updates
+    /// from the start of the demo, for debugging.
+    ///
+    /// Returns `None` if the entity doesn't exist at the tick, the analyser of the parser isn't used.
+    pub fn entity_state_at(
+        mut self,
+        entity: EntityId,
+        tick: DemoTick,
+    ) -> Result<Option<PacketEntity>> {
+        let header = Header::read(&mut self.stream)?;
+        let mut handler = DemoHandler::with_analyser(EntityStateAnalyser::new(entity, tick));
+        handler.state_handler.lenient = self.handler.state_handler.lenient;
+        handler.state_handler.duplicate_enter_policy =
+            self.handler.state_handler.duplicate_enter_policy;
+        handler.state_handler.unknown_delta_policy =
+            self.handler.state_handler.unknown_delta_policy;
+        handler.state_handler.flat_props_cache = self.handler.state_handler.flat_props_cache;
+        handler.handle_header(&header);
+
+        let mut packets = RawPacketStream::new(self.stream);
+        while let Some(packet) = packets.next(&handler.state_handler)? {
+            // message packets are ordered by tick, so no updates before the tick will follow
+            if matches!(&packet, Packet::Message(message_packet) if message_packet.tick > tick) {
+                break;
+            }
+            handler.handle_packet(packet)?;
+        }
+        Ok(handler.into_output())
+    }
+
     pub fn parse(self) -> Result<(Header, A::Output)> {
         let (header, mut ticker) = self.ticker()?;
         while ticker.tick()? {
@@ -95,6 +209,101 @@ impl<'a, A: MessageHandler> DemoParser<'a, A> {
         Ok((header, ticker.into_state()))
     }
 
+    /// Parse the demo, skipping message packets that fail to parse or handle instead of aborting the parse
+    ///
+    /// The errors of the skipped packets are returned alongside the output of the analyser,
+    /// which only contains the data from the packets that could be parsed.
+    /// Errors outside of message packets, such as a corrupt header or data tables, still abort the parse
+    /// since the rest of the demo can't be parsed without them.
+    ///
+    /// Since a skipped packet can contain entity updates, the entity state is incomplete after skipping a packet.
+    /// All delta entity updates are skipped until the next full entity update, which for most demos means
+    /// that the analyser receives no more entity updates after the first skipped packet.
+    pub fn parse_lossy(self) -> Result<(Header, A::Output, Vec<ParseError>)> {
+        let (header, mut ticker) = self.ticker()?;
+        let mut errors = Vec::new();
+        while let Some(packet) = ticker
+            .packets
+            .next_lossy(&ticker.handler.state_handler, &mut errors)?
+        {
+            let packet_type = packet.packet_type();
+            match ticker.handler.handle_packet(packet) {
+                Err(e) if packet_type == PacketType::Message => {
+                    ticker
+                        .handler
+                        .state_handler
+                        .set_entity_state_incomplete(true);
+                    errors.push(e);
+                }
+                result => result?,
+            }
+        }
+        Ok((header, ticker.into_state(), errors))
+    }
+
+    /// Parse the demo, only passing the messages from packets with a tick in the range `start..end` to the analyser
+    ///
+    /// All packets before `start` are still parsed to build up the entity and string table state,
+    /// string tables, data tables and signon packets from before `start` are always passed to the analyser
+    /// since they contain the users and game setup the analyser needs to correctly handle the messages in the range.
+    /// Other messages from before `start` are passed to [`MessageHandler::handle_message_before_range`],
+    /// which the [`Analyser`] uses to track the teams and classes of players that spawned before the range.
+    /// Parsing stops at the first packet at or after `end`.
+    pub fn parse_range(self, start: u32, end: u32) -> Result<(Header, A::Output)> {
+        let (header, mut ticker) = self.ticker()?;
+        while let Some(packet) = ticker.packets.next(&ticker.handler.state_handler)? {
+            let is_message = packet.packet_type() == PacketType::Message;
+            let tick = u32::from(packet.tick());
+            if is_message && tick >= end {
+                break;
+            } else if is_message && tick < start {
+                ticker.handler.handle_packet_before_range(packet)?;
+            } else {
+                ticker.handler.handle_packet(packet)?;
+            }
+        }
+        Ok((header, ticker.into_state()))
+    }
+
+    /// Parse the demo while reporting the progress as the fraction of the demo that has been parsed, from `0.0` to `1.0`
+    ///
+    /// The callback is called once after every packet
+    pub fn parse_with_progress(self, mut callback: impl FnMut(f32)) -> Result<(Header, A::Output)> {
+        let (header, mut ticker) = self.ticker()?;
+        while ticker.tick()? {
+            callback(ticker.progress());
+        }
+        Ok((header, ticker.into_state()))
+    }
+
+    /// Read only the send tables and server classes of the demo, stopping as soon as the data tables are parsed
+    ///
+    /// Only the signon packets before the data tables are read, none of the game packets are parsed.
+    pub fn parse_schema_only(mut self) -> Result<(Header, DemoSchema)> {
+        let header = Header::read(&mut self.stream)?;
+        self.handler.handle_header(&header);
+
+        let mut packets = RawPacketStream::new(self.stream);
+        while let Some(packet) = packets.next(&self.handler.state_handler)? {
+            if let Packet::DataTables(_) = packet {
+                // the send tables are only flattened when entities are parsed
+                self.handler.state_handler.set_parse_all(true);
+                self.handler.handle_packet(packet)?;
+                break;
+            }
+            self.handler.handle_packet(packet)?;
+        }
+
+        let state = self.handler.state_handler;
+        Ok((
+            header,
+            DemoSchema {
+                send_tables: state.send_tables,
+                server_classes: state.server_classes,
+            },
+        ))
+    }
+
     /// A Ticker provides a way to step trough the demo packet by packet
     /// while allowing to see the intermediate states
     pub fn ticker(mut self) -> Result<(Header, DemoTicker<'a, A>)> {
@@ -106,6 +315,46 @@ impl<'a, A: MessageHandler> DemoParser<'a, A> {
         };
         Ok((header, ticker))
     }
+
+    /// Iterate over all messages of the demo, parsing the demo packet by packet while iterating.
+    ///
+    /// Messages are only kept in memory until they are yielded, so memory usage doesn't grow with the length of the demo.
+    /// After iterating, the output of the analyser can be retrieved with [`MessageIterator::into_state`].
+    pub fn into_message_iter(self) -> Result<(Header, MessageIterator<'a, A>)> {
+        self.into_message_iter_range(0, u32::MAX)
+    }
+
+    /// Iterate over the messages of the demo, only yielding the messages from packets with a tick
+    /// in the range `start..end`.
+    ///
+    /// All packets are still parsed since the entity and string table state depends on them,
+    /// but messages outside the range that aren't required for the parser state or analyser are skipped
+    /// without decoding them.
+    pub fn into_message_iter_range(
+        mut self,
+        start: u32,
+        end: u32,
+    ) -> Result<(Header, MessageIterator<'a, A>)> {
+        let header = Header::read(&mut self.stream)?;
+        self.handler.handle_header(&header);
+        self.handler.state_handler.set_parse_all(true);
+        let iter = MessageIterator {
+            handler: self.handler,
+            packets: RawPacketStream::new(self.stream),
+            start,
+            end,
+            buffer: VecDeque::new(),
+        };
+        Ok((header, iter))
+    }
+}
+
+/// The data model of a demo, the server classes and the send tables describing the props of the classes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DemoSchema {
+    /// The send tables for every server class, in the same order as the server classes
+    pub send_tables: Vec<SendTable>,
+    pub server_classes: Vec<ServerClass>,
 }
 
 #[derive(Clone)]
@@ -128,30 +377,92 @@ impl<'a> RawPacketStream<'a> {
         self.stream.pos()
     }
 
+    /// Get the fraction of the stream that has been read, from `0.0` to `1.0`
+    pub fn progress(&self) -> f32 {
+        if self.ended {
+            1.0
+        } else {
+            self.stream.pos() as f32 / self.stream.bit_len() as f32
+        }
+    }
+
+    /// Read the tick of the next packet without parsing it
+    pub fn peek_tick(&self) -> Option<DemoTick> {
+        let mut stream = self.stream.clone();
+        stream.skip_bits(8).ok()?;
+        stream.read::<u32>().ok().map(DemoTick::from)
+    }
+
     pub fn next(&mut self, state: &ParserState) -> Result<Option<Packet<'a>>> {
         if self.ended {
             Ok(None)
         } else {
+            let result = Packet::parse(&mut self.stream, state);
+            self.handle_parse_result(result)
+        }
+    }
+
+    fn handle_parse_result(&mut self, result: Result<Packet<'a>>) -> Result<Option<Packet<'a>>> {
+        match result {
+            Ok(packet @ Packet::Stop(_)) => {
+                self.ended = true;
+                Ok(Some(packet))
+            }
+            Ok(packet) => Ok(Some(packet)),
+            Err(ParseError::ReadError(BitError::NotEnoughData { .. })) => {
+                self.ended = true;
+                self.incomplete = true;
+                Ok(None)
+            }
+            Err(e) => {
+                self.ended = true;
+                Err(e)
+            }
+        }
+    }
+
+    /// Read the next packet, skipping over any message packets that fail to parse
+    ///
+    /// The errors of the skipped packets are added to `errors`, and the entity state is marked as incomplete
+    /// since the skipped packets can contain entity updates
+    pub fn next_lossy(
+        &mut self,
+        state: &ParserState,
+        errors: &mut Vec<ParseError>,
+    ) -> Result<Option<Packet<'a>>> {
+        while !self.ended {
+            let start = self.stream.pos();
+            let packet_stream = self.stream.clone();
             match Packet::parse(&mut self.stream, state) {
-                Ok(packet @ Packet::Stop(_)) => {
-                    self.ended = true;
-                    Ok(Some(packet))
-                }
-                Ok(packet) => Ok(Some(packet)),
-                Err(ParseError::ReadError(BitError::NotEnoughData { .. })) => {
-                    self.ended = true;
-                    self.incomplete = true;
-                    Ok(None)
-                }
-                Err(e) => {
-                    self.ended = true;
-                    Err(e)
-                }
+                // malformed data can run out of the packet, only a packet that doesn't fit in the demo is truncated
+                Err(e) => match message_packet_length(packet_stream) {
+                    Some(length) if self.stream.set_pos(start + length).is_ok() => {
+                        state.set_entity_state_incomplete(true);
+                        errors.push(e);
+                    }
+                    _ => return self.handle_parse_result(Err(e)),
+                },
+                result => return self.handle_parse_result(result),
             }
         }
+        Ok(None)
     }
 }
 
+/// Get the length in bits of the message packet at the start of the stream, without parsing the messages in the packet
+///
+/// Returns `None` if the packet isn't a message packet or the packet is truncated
+fn message_packet_length(mut stream: Stream) -> Option<usize> {
+    if stream.read::<PacketType>().ok()? != PacketType::Message {
+        return None;
+    }
+    stream.read::<DemoTick>().ok()?;
+    stream.read::<MessagePacketMeta>().ok()?;
+    let length: u32 = stream.read().ok()?;
+    stream.skip_bits(length as usize * 8).ok()?;
+    Some(stream.pos())
+}
+
 #[derive(Clone)]
 pub struct DemoTicker<'a, A: MessageHandler> {
     handler: DemoHandler<'a, A>,
@@ -177,6 +488,16 @@ impl<'a, A: MessageHandler> DemoTicker<'a, A> {
     pub fn into_state(self) -> A::Output {
         self.handler.into_output()
     }
+
+    /// Get the warnings that have been recorded while parsing, most warnings are only recorded in lenient mode
+    pub fn warnings(&self) -> Vec<ParseWarning> {
+        self.handler.get_parser_state().warnings()
+    }
+
+    /// Get the fraction of the demo that has been parsed, from `0.0` to `1.0`
+    pub fn progress(&self) -> f32 {
+        self.packets.progress()
+    }
 }
 
 impl<'a, A: MessageHandler + BorrowMessageHandler> DemoTicker<'a, A> {
@@ -213,3 +534,79 @@ pub struct Tick<'a, State> {
     pub parser_state: &'a ParserState,
     pub tick: DemoTick,
 }
+
+pub struct MessageIterator<'a, A: MessageHandler> {
+    handler: DemoHandler<'a, A>,
+    packets: RawPacketStream<'a>,
+    start: u32,
+    end: u32,
+    buffer: VecDeque<(DemoTick, Message<'a>)>,
+}
+
+impl<'a, A: MessageHandler> MessageIterator<'a, A> {
+    fn in_range(&self, tick: DemoTick) -> bool {
+        (self.start..self.end).contains(&u32::from(tick))
+    }
+
+    fn next_packet(&mut self) -> Result<bool> {
+        let in_range = self
+            .packets
+            .peek_tick()
+            .map(|tick| self.in_range(tick))
+            .unwrap_or(true);
+        self.handler.state_handler.set_parse_all(in_range);
+
+        let packet = match self.packets.next(&self.handler.state_handler)? {
+            Some(packet) => packet,
+            None => return Ok(false),
+        };
+
+        match &packet {
+            // message packets are ordered by tick, so nothing in range will follow
+            Packet::Message(message_packet) if u32::from(message_packet.tick) >= self.end => {
+                self.packets.ended = true;
+                return Ok(false);
+            }
+            Packet::Message(message_packet) | Packet::Signon(message_packet)
+                if self.in_range(message_packet.tick) =>
+            {
+                let tick = message_packet.tick;
+                self.buffer.extend(
+                    message_packet
+                        .messages
+                        .iter()
+                        .cloned()
+                        .map(|message| (tick, message)),
+                );
+            }
+            _ => {}
+        }
+
+        self.handler.handle_packet(packet)?;
+        Ok(true)
+    }
+
+    pub fn into_state(self) -> A::Output {
+        self.handler.into_output()
+    }
+}
+
+impl<'a, A: MessageHandler> Iterator for MessageIterator<'a, A> {
+    type Item = Result<(DemoTick, Message<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(message) = self.buffer.pop_front() {
+                return Some(Ok(message));
+            }
+            match self.next_packet() {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(e) => {
+                    self.packets.ended = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
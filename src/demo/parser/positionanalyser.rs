@@ -0,0 +1,198 @@
+use crate::demo::data::DemoTick;
+use crate::demo::message::packetentities::{EntityId, PacketEntitiesMessage, UpdateType};
+use crate::demo::message::{Message, MessageType};
+use crate::demo::packet::datatable::{ParseSendTable, ServerClass};
+use crate::demo::packet::message::MessagePacketMeta;
+use crate::demo::packet::stringtable::StringTableEntry;
+use crate::demo::parser::analyser::{Class, UserId};
+use crate::demo::parser::gamestateanalyser::{GameStateAnalyser, PlayerState};
+use crate::demo::parser::handler::MessageHandler;
+use crate::demo::vector::Vector;
+use crate::ParserState;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct PositionSample {
+    pub tick: DemoTick,
+    pub position: Vector,
+    pub class: Class,
+}
+
+/// The top-down movement of a player while playing a single class
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlayerPath {
+    pub user: UserId,
+    pub class: Class,
+    /// The `x` and `y` coordinates of the player with the tick of the sample
+    pub points: Vec<(f32, f32, DemoTick)>,
+}
+
+/// Convert the recorded positions into 2d paths for drawing on a top-down map.
+///
+/// A new path is started every time a player changes class.
+pub fn to_paths(positions: &BTreeMap<UserId, Vec<PositionSample>>) -> Vec<PlayerPath> {
+    let mut paths: Vec<PlayerPath> = Vec::new();
+    for (user, samples) in positions {
+        let mut current: Option<PlayerPath> = None;
+        for sample in samples {
+            let path = match &mut current {
+                Some(path) if path.class == sample.class => path,
+                _ => {
+                    paths.extend(current.take());
+                    current.insert(PlayerPath {
+                        user: *user,
+                        class: sample.class,
+                        points: Vec::new(),
+                    })
+                }
+            };
+            path.points
+                .push((sample.position.x, sample.position.y, sample.tick));
+        }
+        paths.extend(current);
+    }
+    paths
+}
+
+/// An analyser that records the position of every alive player over time.
+///
+/// Players are only recorded while they are in the PVS of the recording player, the position of
+/// a player outside the PVS isn't updated and would otherwise be recorded at the spot they left the PVS.
+/// Spectators are never recorded.
+///
+/// By default a sample is recorded for every tick, use [`with_interval`](Self::with_interval) and
+/// [`with_distance_threshold`](Self::with_distance_threshold) to reduce the number of samples.
+#[derive(Debug)]
+pub struct PositionAnalyser {
+    game_state: GameStateAnalyser,
+    interval: u32,
+    distance_threshold: Option<f32>,
+    positions: BTreeMap<UserId, Vec<PositionSample>>,
+    outside_pvs: HashSet<EntityId>,
+}
+
+impl Default for PositionAnalyser {
+    fn default() -> Self {
+        PositionAnalyser {
+            game_state: GameStateAnalyser::default(),
+            interval: 1,
+            distance_threshold: None,
+            positions: BTreeMap::new(),
+            outside_pvs: HashSet::new(),
+        }
+    }
+}
+
+impl PositionAnalyser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only record a sample for a player once every `interval` ticks
+    pub fn with_interval(mut self, interval: u32) -> Self {
+        self.interval = interval.max(1);
+        self
+    }
+
+    /// Also record a sample, regardless of the interval, when a player moved more than `distance`
+    /// units since the previous sample
+    pub fn with_distance_threshold(mut self, distance: f32) -> Self {
+        self.distance_threshold = Some(distance);
+        self
+    }
+
+    fn update_pvs(&mut self, message: &PacketEntitiesMessage) {
+        for entity in &message.entities {
+            match entity.update_type {
+                UpdateType::Leave => self.outside_pvs.insert(entity.entity_index),
+                _ => self.outside_pvs.remove(&entity.entity_index),
+            };
+        }
+        for removed in &message.removed_entities {
+            self.outside_pvs.remove(removed);
+        }
+    }
+
+    fn record_positions(&mut self, tick: DemoTick) {
+        for player in &self.game_state.state.players {
+            if !player.team.is_player() || self.outside_pvs.contains(&player.entity_id()) {
+                continue;
+            }
+            let user_id = match (&player.info, player.state) {
+                (Some(info), PlayerState::Alive) => info.user_id,
+                _ => continue,
+            };
+            let samples = self.positions.entry(user_id).or_default();
+
+            let should_record = match samples.last() {
+                Some(last) => {
+                    u32::from(tick).saturating_sub(last.tick.into()) >= self.interval
+                        || self
+                            .distance_threshold
+                            .map(|threshold| (player.position - last.position).length() > threshold)
+                            .unwrap_or_default()
+                }
+                None => true,
+            };
+
+            if should_record {
+                samples.push(PositionSample {
+                    tick,
+                    position: player.position,
+                    class: player.class,
+                });
+            }
+        }
+    }
+}
+
+impl MessageHandler for PositionAnalyser {
+    type Output = BTreeMap<UserId, Vec<PositionSample>>;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        matches!(message_type, MessageType::PacketEntities)
+    }
+
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
+        self.game_state.handle_message(message, tick, parser_state);
+        if let Message::PacketEntities(message) = message {
+            self.update_pvs(message);
+            self.record_positions(tick);
+        }
+    }
+
+    fn handle_string_entry(
+        &mut self,
+        table: &str,
+        index: usize,
+        entry: &StringTableEntry,
+        parser_state: &ParserState,
+    ) {
+        self.game_state
+            .handle_string_entry(table, index, entry, parser_state)
+    }
+
+    fn handle_data_tables(
+        &mut self,
+        tables: &[ParseSendTable],
+        server_classes: &[ServerClass],
+        parser_state: &ParserState,
+    ) {
+        self.game_state
+            .handle_data_tables(tables, server_classes, parser_state)
+    }
+
+    fn handle_packet_meta(
+        &mut self,
+        tick: DemoTick,
+        meta: &MessagePacketMeta,
+        parser_state: &ParserState,
+    ) {
+        self.game_state.handle_packet_meta(tick, meta, parser_state)
+    }
+
+    fn into_output(self, _state: &ParserState) -> Self::Output {
+        self.positions
+    }
+}
@@ -0,0 +1,82 @@
+use crate::demo::data::DemoTick;
+use crate::demo::message::packetentities::{EntityId, PacketEntity, UpdateType};
+use crate::demo::message::{Message, MessageType};
+use crate::demo::parser::analyser::UserId;
+use crate::demo::parser::handler::MessageHandler;
+use crate::demo::sendprop::{SendPropIdentifier, SendPropValue};
+use crate::ParserState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// The values of the watched props that were updated for an entity in a tick
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WatchedProps {
+    pub tick: DemoTick,
+    pub entity: EntityId,
+    /// The user controlling the entity, if the entity is a player
+    pub user: Option<UserId>,
+    pub props: Vec<(SendPropIdentifier, SendPropValue)>,
+}
+
+/// A handler that collects the values of a configured set of props from all entities.
+///
+/// Only the props sent in an update are recorded, the props of an entity entering the pvs include the values
+/// from the baseline.
+#[derive(Debug)]
+pub struct PropWatchHandler {
+    watched: HashSet<SendPropIdentifier>,
+    updates: Vec<WatchedProps>,
+}
+
+impl PropWatchHandler {
+    /// Create a handler watching the given `(table, prop)` names, such as `("DT_BasePlayer", "m_iHealth")`
+    pub fn new(watched: &[(&str, &str)]) -> Self {
+        PropWatchHandler {
+            watched: watched
+                .iter()
+                .map(|(table, prop)| SendPropIdentifier::new(table, prop))
+                .collect(),
+            updates: Vec::new(),
+        }
+    }
+
+    fn handle_entity(&mut self, entity: &PacketEntity, tick: DemoTick, parser_state: &ParserState) {
+        if entity.update_type == UpdateType::Delete {
+            return;
+        }
+
+        let props: Vec<_> = entity
+            .props(parser_state)
+            .filter(|prop| self.watched.contains(&prop.identifier))
+            .map(|prop| (prop.identifier, prop.value))
+            .collect();
+        if !props.is_empty() {
+            self.updates.push(WatchedProps {
+                tick,
+                entity: entity.entity_index,
+                user: parser_state.user_id_for_entity(entity.entity_index),
+                props,
+            });
+        }
+    }
+}
+
+impl MessageHandler for PropWatchHandler {
+    type Output = Vec<WatchedProps>;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        matches!(message_type, MessageType::PacketEntities)
+    }
+
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
+        if let Message::PacketEntities(message) = message {
+            for entity in &message.entities {
+                self.handle_entity(entity, tick, parser_state);
+            }
+        }
+    }
+
+    fn into_output(self, _state: &ParserState) -> Self::Output {
+        self.updates
+    }
+}
@@ -0,0 +1,72 @@
+use crate::demo::data::DemoTick;
+use crate::demo::message::packetentities::PacketEntity;
+use crate::demo::message::{Message, MessageType};
+use crate::demo::parser::analyser::UserId;
+use crate::demo::parser::handler::MessageHandler;
+use crate::demo::sendprop::SendPropIdentifier;
+use crate::demo::vector::Vector;
+use crate::ParserState;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// The view punch of a player, the offset of the view angles caused by taking damage or by firing some weapons
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct PunchAngle {
+    pub tick: DemoTick,
+    pub user: UserId,
+    /// The pitch, yaw and roll offset in degrees
+    pub angle: Vector,
+}
+
+/// An analyser that records the view punch of players every time it changes to a non-zero value.
+///
+/// The punch angle is part of the local player data, which isn't always send for every player
+/// in demos recorded from a player perspective. After being punched the angle decays back to zero
+/// over the next ticks.
+#[derive(Default, Debug)]
+pub struct PunchAngleAnalyser {
+    angles: Vec<PunchAngle>,
+}
+
+impl PunchAngleAnalyser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn handle_entity(&mut self, entity: &PacketEntity, tick: DemoTick, parser_state: &ParserState) {
+        const PUNCH_ANGLE: SendPropIdentifier =
+            SendPropIdentifier::new("DT_Local", "m_vecPunchAngle");
+
+        let prop = match entity.get_prop_by_identifier(&PUNCH_ANGLE, parser_state) {
+            Some(prop) => prop,
+            None => return,
+        };
+        let angle = match Vector::try_from(&prop.value) {
+            Ok(angle) if angle.length() > 0.0 => angle,
+            _ => return,
+        };
+        if let Some(user) = parser_state.user_id_for_entity(entity.entity_index) {
+            self.angles.push(PunchAngle { tick, user, angle });
+        }
+    }
+}
+
+impl MessageHandler for PunchAngleAnalyser {
+    type Output = Vec<PunchAngle>;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        matches!(message_type, MessageType::PacketEntities)
+    }
+
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
+        if let Message::PacketEntities(message) = message {
+            for entity in &message.entities {
+                self.handle_entity(entity, tick, parser_state);
+            }
+        }
+    }
+
+    fn into_output(self, _state: &ParserState) -> Self::Output {
+        self.angles
+    }
+}
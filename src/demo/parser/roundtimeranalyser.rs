@@ -0,0 +1,232 @@
+use crate::demo::data::{DemoTick, ServerTick};
+use crate::demo::message::packetentities::{EntityId, PacketEntity, UpdateType};
+use crate::demo::message::{Message, MessageType};
+use crate::demo::parser::handler::MessageHandler;
+use crate::demo::sendprop::SendPropIdentifier;
+use crate::ParserState;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+/// The state of the round timer as shown in the hud
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct RoundTimerSample {
+    pub tick: DemoTick,
+    /// The timer entity, maps with multiple timers such as KOTH have a timer for every team
+    pub timer: EntityId,
+    /// Seconds left on the timer
+    pub remaining: f32,
+    /// Whether the timer is counting down the setup time instead of the round time
+    pub setup: bool,
+    pub paused: bool,
+}
+
+#[derive(Debug, Default)]
+struct Timer {
+    end_time: f32,
+    time_remaining: f32,
+    paused: bool,
+    disabled: bool,
+    show_in_hud: bool,
+    setup: bool,
+    last_sample: Option<RoundTimerSample>,
+}
+
+impl Timer {
+    fn remaining(&self, time: f32) -> f32 {
+        if self.paused {
+            self.time_remaining
+        } else {
+            (self.end_time - time).max(0.0)
+        }
+    }
+}
+
+/// An analyser that reads the round timer from the `team_round_timer` entities.
+///
+/// While the timer is running the remaining time is derived from the end time of the timer and the server time,
+/// a sample is recorded every time the remaining whole seconds change and when the timer is paused or resumed.
+/// Only enabled timers that are shown in the hud are recorded, every timer is sampled separately.
+#[derive(Default, Debug)]
+pub struct RoundTimerAnalyser {
+    samples: Vec<RoundTimerSample>,
+    timers: BTreeMap<EntityId, Timer>,
+    server_tick: Option<ServerTick>,
+    interval_per_tick: f32,
+}
+
+impl RoundTimerAnalyser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn handle_entity(&mut self, entity: &PacketEntity, parser_state: &ParserState) {
+        const END_TIME: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TeamRoundTimer", "m_flTimerEndTime");
+        const TIME_REMAINING: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TeamRoundTimer", "m_flTimeRemaining");
+        const PAUSED: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TeamRoundTimer", "m_bTimerPaused");
+        const DISABLED: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TeamRoundTimer", "m_bIsDisabled");
+        const SHOW_IN_HUD: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TeamRoundTimer", "m_bShowInHUD");
+        const STATE: SendPropIdentifier = SendPropIdentifier::new("DT_TeamRoundTimer", "m_nState");
+
+        if entity.update_type == UpdateType::Delete {
+            self.timers.remove(&entity.entity_index);
+            return;
+        }
+
+        let class_name = parser_state
+            .server_classes
+            .get(usize::from(entity.server_class))
+            .map(|class| class.name.as_str());
+        if class_name != Some("CTeamRoundTimer") {
+            return;
+        }
+
+        let timer = self.timers.entry(entity.entity_index).or_default();
+        for prop in entity.props(parser_state) {
+            match prop.identifier {
+                END_TIME => timer.end_time = f32::try_from(&prop.value).unwrap_or_default(),
+                TIME_REMAINING => {
+                    timer.time_remaining = f32::try_from(&prop.value).unwrap_or_default()
+                }
+                PAUSED => timer.paused = i64::try_from(&prop.value).unwrap_or_default() > 0,
+                DISABLED => timer.disabled = i64::try_from(&prop.value).unwrap_or_default() > 0,
+                SHOW_IN_HUD => {
+                    timer.show_in_hud = i64::try_from(&prop.value).unwrap_or_default() > 0
+                }
+                // RT_STATE_SETUP
+                STATE => timer.setup = i64::try_from(&prop.value).unwrap_or_default() == 0,
+                _ => {}
+            }
+        }
+    }
+
+    fn sample(&mut self, tick: DemoTick) {
+        let time = match self.server_tick {
+            Some(server_tick) => u32::from(server_tick) as f32 * self.interval_per_tick,
+            None => return,
+        };
+        for (entity, timer) in self.timers.iter_mut() {
+            if timer.disabled || !timer.show_in_hud {
+                continue;
+            }
+            let remaining = timer.remaining(time);
+            let changed = match &timer.last_sample {
+                Some(last) => {
+                    last.remaining.ceil() != remaining.ceil()
+                        || last.setup != timer.setup
+                        || last.paused != timer.paused
+                }
+                None => true,
+            };
+            if changed {
+                let sample = RoundTimerSample {
+                    tick,
+                    timer: *entity,
+                    remaining,
+                    setup: timer.setup,
+                    paused: timer.paused,
+                };
+                timer.last_sample = Some(sample);
+                self.samples.push(sample);
+            }
+        }
+    }
+}
+
+impl MessageHandler for RoundTimerAnalyser {
+    type Output = Vec<RoundTimerSample>;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        matches!(
+            message_type,
+            MessageType::PacketEntities | MessageType::NetTick | MessageType::ServerInfo
+        )
+    }
+
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
+        match message {
+            Message::PacketEntities(message) => {
+                for entity in &message.entities {
+                    self.handle_entity(entity, parser_state);
+                }
+            }
+            Message::NetTick(message) => self.server_tick = Some(message.tick),
+            Message::ServerInfo(message) => self.interval_per_tick = message.interval_per_tick,
+            _ => return,
+        }
+        self.sample(tick);
+    }
+
+    fn into_output(self, _state: &ParserState) -> Self::Output {
+        self.samples
+    }
+}
+
+#[test]
+fn test_koth_timers() {
+    use crate::demo::packet::datatable::{ClassId, SendTableName, ServerClass, ServerClassName};
+    use crate::demo::sendprop::{SendProp, SendPropValue};
+
+    let mut state = ParserState::new(24, |_| false, false);
+    state.server_classes = vec![ServerClass {
+        id: ClassId::from(0),
+        name: ServerClassName::from("CTeamRoundTimer"),
+        data_table: SendTableName::from("DT_TeamRoundTimer"),
+    }];
+    let prop = |name: &str, value: SendPropValue| SendProp {
+        index: 0,
+        identifier: SendPropIdentifier::new("DT_TeamRoundTimer", name),
+        value,
+    };
+    // both teams have a koth timer shown in the hud, only the timer of the team owning the point is running
+    let timer = |index: u32, end_time: f32, paused: bool| PacketEntity {
+        server_class: ClassId::from(0),
+        entity_index: EntityId::from(index),
+        props: vec![
+            prop("m_flTimerEndTime", SendPropValue::Float(end_time)),
+            prop("m_flTimeRemaining", SendPropValue::Float(180.0)),
+            prop("m_bTimerPaused", SendPropValue::Integer(paused as i64)),
+            prop("m_bShowInHUD", SendPropValue::Integer(1)),
+            prop("m_nState", SendPropValue::Integer(1)),
+        ],
+        in_pvs: false,
+        update_type: UpdateType::Preserve,
+        serial_number: 0,
+        delay: None,
+        delta: None,
+        baseline_index: 0,
+    };
+
+    let mut analyser = RoundTimerAnalyser::new();
+    analyser.interval_per_tick = 0.5;
+    analyser.handle_entity(&timer(10, 180.0, false), &state);
+    analyser.handle_entity(&timer(11, 0.0, true), &state);
+    for tick in 0..20u32 {
+        analyser.server_tick = Some(ServerTick::from(tick));
+        analyser.sample(DemoTick::from(tick));
+    }
+
+    let remaining = |entity: u32| {
+        analyser
+            .samples
+            .iter()
+            .filter(|sample| sample.timer == EntityId::from(entity))
+            .map(|sample| sample.remaining)
+            .collect::<Vec<_>>()
+    };
+    // one sample per second for the running timer
+    assert_eq!(
+        (171..=180)
+            .rev()
+            .map(|second| second as f32)
+            .collect::<Vec<_>>(),
+        remaining(10)
+    );
+    // the paused timer doesn't get a sample every time the running timer is sampled
+    assert_eq!(vec![180.0], remaining(11));
+}
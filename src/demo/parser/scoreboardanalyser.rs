@@ -0,0 +1,118 @@
+use crate::demo::data::DemoTick;
+use crate::demo::message::packetentities::{EntityId, PacketEntity};
+use crate::demo::message::{Message, MessageType};
+use crate::demo::parser::analyser::UserId;
+use crate::demo::parser::handler::MessageHandler;
+use crate::ParserState;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+/// The score and deaths of a player as shown on the scoreboard
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ScoreboardEntry {
+    pub score: u16,
+    pub deaths: u16,
+}
+
+/// The score and deaths of a player after either of them changed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ScoreboardSample {
+    pub tick: DemoTick,
+    pub user: UserId,
+    pub score: u16,
+    pub deaths: u16,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Scoreboard {
+    /// Every change in the score or deaths of a player
+    pub samples: Vec<ScoreboardSample>,
+    /// The scoreboard at the end of the demo, players that disconnected keep the last values they had
+    pub scores: BTreeMap<UserId, ScoreboardEntry>,
+}
+
+/// An analyser that reads the score and deaths of every player from the player resource entity.
+///
+/// These are the values the game itself keeps for the scoreboard, instead of values summed from the game events.
+/// The values are reset when the game is restarted, such as when a tournament match starts after the warmup.
+#[derive(Default, Debug)]
+pub struct ScoreboardAnalyser {
+    scoreboard: Scoreboard,
+    entries: HashMap<EntityId, ScoreboardEntry>,
+}
+
+impl ScoreboardAnalyser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn handle_entity(&mut self, entity: &PacketEntity, tick: DemoTick, parser_state: &ParserState) {
+        let class_name = parser_state
+            .server_classes
+            .get(usize::from(entity.server_class))
+            .map(|class| class.name.as_str());
+        if class_name != Some("CTFPlayerResource") {
+            return;
+        }
+
+        let mut changed = Vec::new();
+        for prop in entity.props(parser_state) {
+            // the values for every player are stored in an array indexed by the entity id of the player
+            if let Some((table_name, prop_name)) = prop.identifier.names() {
+                if let Ok(player_id) = u32::from_str(prop_name.as_str()) {
+                    let entity_id = EntityId::from(player_id);
+                    let value = i64::try_from(&prop.value).unwrap_or_default() as u16;
+                    let entry = self.entries.entry(entity_id).or_default();
+                    let field = match table_name.as_str() {
+                        "m_iScore" => &mut entry.score,
+                        "m_iDeaths" => &mut entry.deaths,
+                        _ => continue,
+                    };
+                    if *field != value {
+                        *field = value;
+                        if !changed.contains(&entity_id) {
+                            changed.push(entity_id);
+                        }
+                    }
+                }
+            }
+        }
+
+        for entity_id in changed {
+            if let (Some(user), Some(entry)) = (
+                parser_state.user_id_for_entity(entity_id),
+                self.entries.get(&entity_id),
+            ) {
+                self.scoreboard.samples.push(ScoreboardSample {
+                    tick,
+                    user,
+                    score: entry.score,
+                    deaths: entry.deaths,
+                });
+                self.scoreboard.scores.insert(user, *entry);
+            }
+        }
+    }
+}
+
+impl MessageHandler for ScoreboardAnalyser {
+    type Output = Scoreboard;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        matches!(message_type, MessageType::PacketEntities)
+    }
+
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
+        if let Message::PacketEntities(message) = message {
+            for entity in &message.entities {
+                self.handle_entity(entity, tick, parser_state);
+            }
+        }
+    }
+
+    fn into_output(self, _state: &ParserState) -> Self::Output {
+        self.scoreboard
+    }
+}
@@ -0,0 +1,142 @@
+use crate::demo::data::DemoTick;
+use crate::demo::message::packetentities::{EntityId, PacketEntity, UpdateType};
+use crate::demo::message::{Message, MessageType};
+use crate::demo::parser::analyser::UserId;
+use crate::demo::parser::gamestateanalyser::ENTITY_INDEX_MASK;
+use crate::demo::parser::handler::MessageHandler;
+use crate::demo::sendprop::SendPropIdentifier;
+use crate::ParserState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+/// Server classes of the weapons that fire hitscan bullets
+const HITSCAN_WEAPONS: &[&str] = &[
+    "CTFChargedSMG",
+    "CTFMinigun",
+    "CTFPEPBrawlerBlaster",
+    "CTFPistol",
+    "CTFPistol_Scout",
+    "CTFPistol_ScoutPrimary",
+    "CTFPistol_ScoutSecondary",
+    "CTFRevolver",
+    "CTFSMG",
+    "CTFScatterGun",
+    "CTFShotgun",
+    "CTFShotgun_HWG",
+    "CTFShotgun_Pyro",
+    "CTFShotgun_Revenge",
+    "CTFShotgun_Soldier",
+    "CTFSniperRifle",
+    "CTFSniperRifleClassic",
+    "CTFSniperRifleDecap",
+    "CTFSodaPopper",
+];
+
+/// An approximate tick at which a player fired a hitscan weapon
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Shot {
+    pub tick: DemoTick,
+    pub user: UserId,
+}
+
+#[derive(Debug, Default)]
+struct Weapon {
+    owner: Option<EntityId>,
+    last_fire_time: Option<f32>,
+}
+
+/// An analyser that estimates when players fire their hitscan weapons from the attack timing of the weapons.
+///
+/// A shot is recorded every time the last fire time of a weapon changes, this is only an approximation:
+///
+/// - The weapon state is only send once per snapshot, if a weapon fires multiple times between
+///   two snapshots only a single shot is recorded. This mostly affects the minigun.
+/// - The tick of the shot is the tick of the snapshot, not the exact tick the weapon fired.
+/// - Fire times from before the demo started or before the weapon was first seen are not recorded as shots.
+/// - The fire time is part of the local weapon data, which isn't always send for every player
+///   in demos recorded from a player perspective.
+#[derive(Default, Debug)]
+pub struct ShotAnalyser {
+    shots: Vec<Shot>,
+    weapons: HashMap<EntityId, Weapon>,
+}
+
+impl ShotAnalyser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn handle_entity(&mut self, entity: &PacketEntity, tick: DemoTick, parser_state: &ParserState) {
+        const OWNER: SendPropIdentifier =
+            SendPropIdentifier::new("DT_BaseCombatWeapon", "m_hOwner");
+        const LAST_FIRE_TIME: SendPropIdentifier =
+            SendPropIdentifier::new("DT_LocalTFWeaponData", "m_flLastFireTime");
+
+        if entity.update_type == UpdateType::Delete {
+            self.weapons.remove(&entity.entity_index);
+            return;
+        }
+
+        let class_name = parser_state
+            .server_classes
+            .get(usize::from(entity.server_class))
+            .map(|class| class.name.as_str());
+        if !class_name
+            .map(|name| HITSCAN_WEAPONS.contains(&name))
+            .unwrap_or_default()
+        {
+            return;
+        }
+
+        let weapon = self.weapons.entry(entity.entity_index).or_default();
+        let mut fire_time = None;
+        for prop in entity.props(parser_state) {
+            match prop.identifier {
+                OWNER => {
+                    let handle = i64::try_from(&prop.value).unwrap_or_default();
+                    weapon.owner = Some(EntityId::from(handle as u32 & ENTITY_INDEX_MASK));
+                }
+                LAST_FIRE_TIME => fire_time = f32::try_from(&prop.value).ok(),
+                _ => {}
+            }
+        }
+
+        if let Some(fire_time) = fire_time {
+            // the first fire time we see is from before the weapon entered the demo
+            let fired = weapon
+                .last_fire_time
+                .map(|last| fire_time > last)
+                .unwrap_or_default();
+            weapon.last_fire_time = Some(fire_time);
+            if fired {
+                if let Some(user) = weapon
+                    .owner
+                    .and_then(|owner| parser_state.user_id_for_entity(owner))
+                {
+                    self.shots.push(Shot { tick, user });
+                }
+            }
+        }
+    }
+}
+
+impl MessageHandler for ShotAnalyser {
+    type Output = Vec<Shot>;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        matches!(message_type, MessageType::PacketEntities)
+    }
+
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
+        if let Message::PacketEntities(message) = message {
+            for entity in &message.entities {
+                self.handle_entity(entity, tick, parser_state);
+            }
+        }
+    }
+
+    fn into_output(self, _state: &ParserState) -> Self::Output {
+        self.shots
+    }
+}
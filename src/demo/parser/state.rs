@@ -1,6 +1,6 @@
 use fnv::FnvHashMap;
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
 use crate::demo::gamevent::GameEventDefinition;
 
@@ -10,19 +10,24 @@ use crate::demo::message::packetentities::{
 use crate::demo::message::stringtable::StringTableMeta;
 use crate::demo::message::{Message, MessageType};
 use crate::demo::packet::datatable::{
-    ClassId, ParseSendTable, SendTable, SendTableName, ServerClass,
+    flatten_tables, ClassId, FlatPropsCache, ParseSendTable, SendTable, SendTableName, ServerClass,
 };
 use crate::demo::packet::stringtable::StringTableEntry;
 
-use crate::demo::data::DemoTick;
-use crate::demo::sendprop::{SendProp, SendPropIdentifier};
+use crate::demo::data::{DemoTick, ServerTick, UserId, UserInfo};
+use crate::demo::parser::ParseWarning;
+use crate::demo::sendprop::{apply_prop_update, SendProp, SendPropIdentifier};
 use crate::nullhasher::NullHasherBuilder;
 use crate::{Result, Stream};
 use serde::{Deserialize, Serialize};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::sync::Arc;
 #[cfg(feature = "trace")]
 use tracing::warn;
 
+/// The number of entity updates the server keeps around to delta against
+const ENTITY_TICK_HISTORY: usize = 90;
+
 #[derive(Default, Clone, Serialize, Deserialize)]
 pub struct DemoMeta {
     pub version: u16,
@@ -30,10 +35,35 @@ pub struct DemoMeta {
     pub interval_per_tick: f32,
 }
 
+/// How to handle entity updates that are a delta from a tick for which no entity updates were received
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UnknownDeltaPolicy {
+    /// Apply the updates to the current entity state and record a warning
+    #[default]
+    Apply,
+    /// Skip the updates and record a warning, delta updates are skipped until the next full entity update
+    Skip,
+    /// Fail the parse with [`ParseError::UnknownDeltaTick`](crate::ParseError::UnknownDeltaTick)
+    Error,
+}
+
+/// How to handle an entity entering at the index of an entity that is still live
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DuplicateEnterPolicy {
+    /// The enter is a new entity if the serial number differs from the live entity, and a continuation
+    /// of the live entity if the serial number matches
+    #[default]
+    Serial,
+    /// Always treat the enter as a new entity
+    Reset,
+    /// Always treat the enter as a continuation of the live entity
+    Continue,
+}
+
 #[derive(Clone)]
 pub struct ParserState {
     pub static_baselines: HashMap<ClassId, StaticBaseline, NullHasherBuilder>,
-    pub parsed_static_baselines: RefCell<HashMap<ClassId, Vec<SendProp>, NullHasherBuilder>>,
+    parsed_static_baselines: RefCell<StaticBaselineCache>,
     pub event_definitions: Vec<GameEventDefinition>,
     pub string_tables: Vec<StringTableMeta>,
     pub entity_classes: HashMap<EntityId, ClassId, NullHasherBuilder>,
@@ -42,12 +72,81 @@ pub struct ParserState {
     pub server_classes: Vec<ServerClass>,
     pub instance_baselines: [Baseline; 2],
     pub demo_meta: DemoMeta,
+    pub flat_props_cache: Option<FlatPropsCache>,
+    /// Recover from malformed entity or game event data where possible instead of failing the parse
+    pub lenient: bool,
+    warnings: RefCell<Vec<ParseWarning>>,
+    pub duplicate_enter_policy: DuplicateEnterPolicy,
+    pub unknown_delta_policy: UnknownDeltaPolicy,
+    /// Serial numbers of the live entities
+    entity_serials: HashMap<EntityId, u32, NullHasherBuilder>,
+    /// The user of every player entity, from the `userinfo` string table
+    user_ids: HashMap<EntityId, UserId, NullHasherBuilder>,
+    server_tick: Option<ServerTick>,
+    /// Server ticks for which entity updates have been received, used to validate delta updates
+    entity_ticks: VecDeque<ServerTick>,
+    /// Set when entity updates have been skipped since the last full entity update
+    entity_state_incomplete: Cell<bool>,
     analyser_handles: fn(message_type: MessageType) -> bool,
     handle_entities: bool,
     parse_all: bool,
     pub protocol_version: u32,
 }
 
+/// Hit and miss counts for the cache of decoded static baselines
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BaselineCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+/// Decoded static baselines, evicting the least recently used baseline once the limit is reached
+#[derive(Clone, Default)]
+struct StaticBaselineCache {
+    entries: HashMap<ClassId, (Vec<SendProp>, u64), NullHasherBuilder>,
+    limit: Option<usize>,
+    clock: u64,
+    stats: BaselineCacheStats,
+}
+
+impl StaticBaselineCache {
+    fn get(&mut self, class_id: ClassId) -> Option<Vec<SendProp>> {
+        self.clock += 1;
+        match self.entries.get_mut(&class_id) {
+            Some((props, last_used)) => {
+                *last_used = self.clock;
+                self.stats.hits += 1;
+                Some(props.clone())
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    fn insert(&mut self, class_id: ClassId, props: Vec<SendProp>) {
+        if let Some(limit) = self.limit {
+            if limit == 0 {
+                return;
+            }
+            while self.entries.len() >= limit {
+                let oldest = self
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, (_, last_used))| *last_used)
+                    .map(|(class_id, _)| *class_id);
+                if let Some(oldest) = oldest {
+                    self.entries.remove(&oldest);
+                    self.stats.evictions += 1;
+                }
+            }
+        }
+        self.entries.insert(class_id, (props, self.clock));
+    }
+}
+
 #[derive(Clone)]
 pub struct StaticBaseline {
     pub class_id: ClassId,
@@ -79,7 +178,7 @@ impl<'a> ParserState {
     ) -> Self {
         ParserState {
             static_baselines: HashMap::with_hasher(NullHasherBuilder),
-            parsed_static_baselines: RefCell::new(HashMap::with_hasher(NullHasherBuilder)),
+            parsed_static_baselines: RefCell::default(),
             event_definitions: Vec::new(),
             string_tables: Vec::new(),
             entity_classes: HashMap::with_hasher(NullHasherBuilder),
@@ -87,6 +186,16 @@ impl<'a> ParserState {
             server_classes: Vec::new(),
             instance_baselines: [Baseline::default(), Baseline::default()],
             demo_meta: DemoMeta::default(),
+            flat_props_cache: None,
+            lenient: false,
+            warnings: RefCell::default(),
+            duplicate_enter_policy: DuplicateEnterPolicy::default(),
+            unknown_delta_policy: UnknownDeltaPolicy::default(),
+            entity_serials: HashMap::with_hasher(NullHasherBuilder),
+            user_ids: HashMap::with_hasher(NullHasherBuilder),
+            server_tick: None,
+            entity_ticks: VecDeque::with_capacity(ENTITY_TICK_HISTORY),
+            entity_state_incomplete: Cell::new(false),
             analyser_handles,
             handle_entities: analyser_handles(MessageType::PacketEntities) || parse_all,
             parse_all,
@@ -94,13 +203,103 @@ impl<'a> ParserState {
         }
     }
 
+    pub fn add_warning(&self, warning: ParseWarning) {
+        self.warnings.borrow_mut().push(warning);
+    }
+
+    /// Get the warnings that have been recorded while parsing, most warnings are only recorded in lenient mode
+    pub fn warnings(&self) -> Vec<ParseWarning> {
+        self.warnings.borrow().clone()
+    }
+
+    /// Get the entities that were referenced by entity updates without being known while parsing in lenient mode
+    pub fn ghost_entities(&self) -> Vec<EntityId> {
+        self.warnings
+            .borrow()
+            .iter()
+            .filter_map(|warning| match warning {
+                ParseWarning::GhostEntity(entity) => Some(*entity),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Get the user id of the player controlling a player entity
+    ///
+    /// Player entities are re-used when a player leaves and another player joins,
+    /// this returns the latest mapping from the `userinfo` string table that has been parsed so far.
+    pub fn user_id_for_entity(&self, entity: EntityId) -> Option<UserId> {
+        self.user_ids.get(&entity).copied()
+    }
+
+    /// Check if an entity update creates a new entity, rather than updating the live entity at the same index.
+    ///
+    /// Only entering entities can be new, if another entity is still live at the index
+    /// the [`DuplicateEnterPolicy`] decides if the enter is a new entity.
+    /// This has to be called before the parser state has handled the update.
+    pub fn is_new_entity(&self, entity: &PacketEntity) -> bool {
+        if entity.update_type != UpdateType::Enter {
+            return false;
+        }
+        match self.entity_serials.get(&entity.entity_index) {
+            None => true,
+            Some(serial) => match self.duplicate_enter_policy {
+                DuplicateEnterPolicy::Serial => *serial != entity.serial_number,
+                DuplicateEnterPolicy::Reset => true,
+                DuplicateEnterPolicy::Continue => false,
+            },
+        }
+    }
+
+    /// Check if entity updates for the provided server tick have been received,
+    /// always true if no ticked entity updates have been received yet since there is nothing to check against
+    pub fn is_known_delta_tick(&self, tick: ServerTick) -> bool {
+        self.entity_ticks.is_empty() || self.entity_ticks.contains(&tick)
+    }
+
+    /// Check if entity updates have been skipped since the last full entity update.
+    ///
+    /// While the entity state is incomplete, delta entity updates are skipped since they would build on the missing state.
+    pub fn entity_state_incomplete(&self) -> bool {
+        self.entity_state_incomplete.get()
+    }
+
+    pub(crate) fn set_entity_state_incomplete(&self, incomplete: bool) {
+        self.entity_state_incomplete.set(incomplete);
+    }
+
+    /// Limit the number of decoded static baselines that are kept around, by default all are kept
+    pub fn set_baseline_cache_limit(&mut self, limit: Option<usize>) {
+        let cache = self.parsed_static_baselines.get_mut();
+        cache.limit = limit;
+        cache.entries.clear();
+    }
+
+    /// Get the hit and miss counts of the decoded static baseline cache
+    pub fn baseline_cache_stats(&self) -> BaselineCacheStats {
+        self.parsed_static_baselines.borrow().stats
+    }
+
+    /// Get the decoded props of the static baseline for a server class
+    ///
+    /// The static baseline is only decoded the first time it's requested, later calls re-use the decoded props
+    /// unless it has been evicted from the cache
     pub fn get_static_baseline(
         &self,
         class_id: ClassId,
         send_table: &SendTable,
     ) -> Result<Vec<SendProp>> {
+        if let Some(props) = self.parsed_static_baselines.borrow_mut().get(class_id) {
+            return Ok(props);
+        }
         match self.static_baselines.get(&class_id) {
-            Some(static_baseline) => static_baseline.parse(send_table),
+            Some(static_baseline) => {
+                let props = static_baseline.parse(send_table)?;
+                self.parsed_static_baselines
+                    .borrow_mut()
+                    .insert(class_id, props.clone());
+                Ok(props)
+            }
             None => {
                 #[cfg(feature = "trace")]
                 warn!(
@@ -110,24 +309,6 @@ impl<'a> ParserState {
                 Ok(Vec::new())
             }
         }
-        // let mut cached = self.parsed_static_baselines.borrow_mut();
-        // Ok(match cached.entry(class_id) {
-        //     Entry::Occupied(entry) => entry.get().as_slice(),
-        //     Entry::Vacant(entry) => match self.static_baselines.get(&class_id) {
-        //         Some(static_baseline) => {
-        //             let props = static_baseline.parse(send_table)?;
-        //             entry.insert(props).as_slice()
-        //         }
-        //         None => {
-        //             #[cfg(feature = "trace")]
-        //             warn!(
-        //                 class_id = display(class_id),
-        //                 "class without static baseline"
-        //             );
-        //             &[]
-        //         }
-        //     },
-        // })
     }
 
     pub fn get_baseline(
@@ -164,23 +345,31 @@ impl<'a> ParserState {
         server_classes: Vec<ServerClass>,
     ) -> Result<()> {
         if self.handle_entities {
+            let flat_props = match &self.flat_props_cache {
+                Some(cache) => cache.get_or_flatten(&parse_tables)?,
+                None => Arc::new(flatten_tables(&parse_tables)?),
+            };
             let mut send_tables: FnvHashMap<SendTableName, SendTable> = parse_tables
                 .iter()
                 .map(|parse_table| {
-                    let flat = parse_table.flatten_props(&parse_tables);
-                    Ok((
+                    (
                         parse_table.name.clone(),
                         SendTable {
                             name: parse_table.name.clone(),
                             needs_decoder: parse_table.needs_decoder,
                             raw_props: parse_table.props.clone(),
-                            flattened_props: flat?,
+                            flattened_props: flat_props
+                                .get(&parse_table.name)
+                                .map(Arc::clone)
+                                .unwrap_or_else(|| Arc::new([])),
                         },
-                    ))
+                    )
                 })
-                .collect::<Result<_>>()?;
+                .collect();
 
             self.server_classes = server_classes;
+            // decoded baselines are only valid for the send tables they were decoded with
+            self.parsed_static_baselines.get_mut().entries.clear();
 
             self.send_tables.reserve(self.server_classes.len());
 
@@ -201,6 +390,14 @@ impl<'a> ParserState {
         self.string_tables.push(table);
     }
 
+    /// Enable or disable parsing of messages not needed by the parser state or analyser.
+    ///
+    /// Once enabled, entities will keep being parsed since entity deltas depend on all previous updates
+    pub(crate) fn set_parse_all(&mut self, parse_all: bool) {
+        self.parse_all = parse_all;
+        self.handle_entities |= parse_all;
+    }
+
     pub fn should_parse_message(&self, message_type: MessageType) -> bool {
         self.parse_all
             || if message_type == MessageType::PacketEntities {
@@ -232,14 +429,33 @@ impl<'a> ParserState {
             Message::GameEventList(message) => {
                 self.event_definitions = message.event_list;
             }
+            Message::NetTick(message) => {
+                self.server_tick = Some(message.tick);
+            }
             Message::PacketEntities(ent_message) => {
+                if let Some(server_tick) = self.server_tick {
+                    if self.entity_ticks.len() == ENTITY_TICK_HISTORY {
+                        self.entity_ticks.pop_front();
+                    }
+                    self.entity_ticks.push_back(server_tick);
+                }
+
                 for removed in ent_message.removed_entities.iter() {
                     self.entity_classes.remove(removed);
+                    self.entity_serials.remove(removed);
                 }
 
                 for entity in ent_message.entities.iter() {
-                    if entity.update_type == UpdateType::Delete {
-                        self.entity_classes.remove(&entity.entity_index);
+                    match entity.update_type {
+                        UpdateType::Delete => {
+                            self.entity_classes.remove(&entity.entity_index);
+                            self.entity_serials.remove(&entity.entity_index);
+                        }
+                        UpdateType::Enter => {
+                            self.entity_serials
+                                .insert(entity.entity_index, entity.serial_number);
+                        }
+                        _ => {}
                     }
                     self.entity_classes
                         .insert(entity.entity_index, entity.server_class);
@@ -278,17 +494,34 @@ impl<'a> ParserState {
         }
     }
 
-    pub fn handle_string_entry(
-        &mut self,
-        table: &str,
-        _index: usize,
-        entry: &StringTableEntry<'a>,
-    ) {
-        if table == "instancebaseline" {
+    pub fn handle_string_entry(&mut self, table: &str, index: usize, entry: &StringTableEntry<'a>) {
+        if table == "userinfo" {
+            let text = entry.text.as_ref().map(|s| s.as_ref());
+            match UserInfo::parse_from_string_table(
+                index as u16,
+                text,
+                entry.extra_data.as_ref().map(|data| data.data.clone()),
+            ) {
+                Ok(Some(user_info)) => {
+                    self.user_ids
+                        .insert(user_info.entity_id, user_info.player_info.user_id);
+                }
+                // the entry is cleared when the player leaves
+                Ok(None) => {
+                    if let Ok(entity_id) = UserInfo::entity_id_for_entry(index as u16, text) {
+                        self.user_ids.remove(&entity_id);
+                    }
+                }
+                Err(_) => {}
+            }
+        } else if table == "instancebaseline" {
             if let (Some(extra), Ok(class_id)) = (&entry.extra_data, entry.text().parse()) {
                 let baseline = StaticBaseline::new(class_id, extra.data.to_owned());
                 self.static_baselines.insert(class_id, baseline);
-                self.parsed_static_baselines.borrow_mut().remove(&class_id);
+                self.parsed_static_baselines
+                    .get_mut()
+                    .entries
+                    .remove(&class_id);
             }
         }
     }
@@ -357,26 +590,20 @@ impl Baseline {
     }
 }
 
+/// An entity in the instance baselines
+///
+/// The props are shared between both instance baselines and only copied once an update is applied to them
 #[derive(Clone)]
 pub struct BaselineEntity {
     pub entity_id: EntityId,
     pub server_class: ClassId,
-    pub props: Vec<SendProp>,
+    pub props: Arc<Vec<SendProp>>,
     pub serial: u32,
 }
 
 impl BaselineEntity {
-    fn mut_prop_by_identifier(&mut self, index: &SendPropIdentifier) -> Option<&mut SendProp> {
-        self.props.iter_mut().find(|prop| prop.identifier == *index)
-    }
-
     pub fn apply_update(&mut self, props: &[SendProp]) {
-        for prop in props {
-            match self.mut_prop_by_identifier(&prop.identifier) {
-                Some(existing_prop) => existing_prop.value = prop.value.clone(),
-                None => self.props.push(prop.clone()),
-            }
-        }
+        apply_prop_update(Arc::make_mut(&mut self.props), props);
     }
 }
 
@@ -385,7 +612,7 @@ impl From<PacketEntity> for BaselineEntity {
         BaselineEntity {
             entity_id: entity.entity_index,
             server_class: entity.server_class,
-            props: entity.props,
+            props: Arc::new(entity.props),
             serial: entity.serial_number,
         }
     }
@@ -396,7 +623,7 @@ impl From<BaselineEntity> for PacketEntity {
         PacketEntity {
             server_class: baseline.server_class,
             entity_index: baseline.entity_id,
-            props: baseline.props,
+            props: Arc::try_unwrap(baseline.props).unwrap_or_else(|props| (*props).clone()),
             in_pvs: false,
             update_type: UpdateType::Enter,
             serial_number: baseline.serial,
@@ -406,3 +633,83 @@ impl From<BaselineEntity> for PacketEntity {
         }
     }
 }
+
+#[test]
+fn test_duplicate_enter_policy() {
+    use crate::demo::message::packetentities::PacketEntitiesMessage;
+
+    let entity = |update_type: UpdateType, serial_number: u32| PacketEntity {
+        server_class: ClassId::from(0),
+        entity_index: EntityId::from(4u32),
+        props: vec![],
+        in_pvs: true,
+        update_type,
+        serial_number,
+        delay: None,
+        delta: None,
+        baseline_index: 0,
+    };
+
+    let mut state = ParserState::new(24, |_| false, false);
+    assert!(state.is_new_entity(&entity(UpdateType::Enter, 1)));
+
+    state.handle_message(
+        Message::PacketEntities(PacketEntitiesMessage {
+            entities: vec![entity(UpdateType::Enter, 1)],
+            ..PacketEntitiesMessage::default()
+        }),
+        DemoTick::from(1u32),
+    );
+    assert!(!state.is_new_entity(&entity(UpdateType::Preserve, 1)));
+    assert!(!state.is_new_entity(&entity(UpdateType::Enter, 1)));
+    assert!(state.is_new_entity(&entity(UpdateType::Enter, 2)));
+
+    state.duplicate_enter_policy = DuplicateEnterPolicy::Reset;
+    assert!(state.is_new_entity(&entity(UpdateType::Enter, 1)));
+    state.duplicate_enter_policy = DuplicateEnterPolicy::Continue;
+    assert!(!state.is_new_entity(&entity(UpdateType::Enter, 2)));
+
+    state.duplicate_enter_policy = DuplicateEnterPolicy::Serial;
+    state.handle_message(
+        Message::PacketEntities(PacketEntitiesMessage {
+            entities: vec![entity(UpdateType::Delete, 1)],
+            ..PacketEntitiesMessage::default()
+        }),
+        DemoTick::from(2u32),
+    );
+    assert!(state.is_new_entity(&entity(UpdateType::Enter, 1)));
+}
+
+#[test]
+fn test_user_id_for_entity() {
+    use crate::demo::data::userinfo::PlayerInfo;
+
+    let mut state = ParserState::new(24, |_| false, false);
+    let user_info = UserInfo {
+        entity_id: EntityId::from(3u32),
+        player_info: PlayerInfo {
+            name: "player".into(),
+            user_id: UserId::from(12u16),
+            steam_id: "[U:1:1]".into(),
+            ..PlayerInfo::default()
+        },
+    };
+    // the entry text is the player slot, which is one less than the entity index
+    let entry = StringTableEntry {
+        text: Some("2".into()),
+        ..user_info.encode_to_string_table().unwrap()
+    };
+    state.handle_string_entry("userinfo", 2, &entry);
+    assert_eq!(
+        Some(UserId::from(12u16)),
+        state.user_id_for_entity(EntityId::from(3u32))
+    );
+
+    // the entry is cleared once the player leaves
+    let cleared = StringTableEntry {
+        text: entry.text.clone(),
+        extra_data: None,
+    };
+    state.handle_string_entry("userinfo", 2, &cleared);
+    assert_eq!(None, state.user_id_for_entity(EntityId::from(3u32)));
+}
@@ -0,0 +1,81 @@
+use crate::demo::data::DemoTick;
+use crate::demo::message::packetentities::{EntityId, PacketEntity};
+use crate::demo::message::{Message, MessageType};
+use crate::demo::parser::analyser::UserId;
+use crate::demo::parser::handler::MessageHandler;
+use crate::demo::sendprop::SendPropIdentifier;
+use crate::ParserState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+/// `TF_COND_TAUNTING` in the player condition bits
+const COND_TAUNTING: i64 = 1 << 7;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TauntChange {
+    pub tick: DemoTick,
+    pub user: UserId,
+    /// Whether the player started (`true`) or stopped (`false`) taunting
+    pub taunting: bool,
+}
+
+/// An analyser that tracks when players start and stop taunting, based on the taunting player condition.
+///
+/// Kills made with a taunt can be found with [`Death::is_taunt_kill`](crate::demo::parser::analyser::Death::is_taunt_kill).
+#[derive(Default, Debug)]
+pub struct TauntAnalyser {
+    changes: Vec<TauntChange>,
+    taunting: HashMap<EntityId, bool>,
+}
+
+impl TauntAnalyser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn handle_entity(&mut self, entity: &PacketEntity, tick: DemoTick, parser_state: &ParserState) {
+        const PLAYER_COND: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFPlayerShared", "m_nPlayerCond");
+
+        let user = match parser_state.user_id_for_entity(entity.entity_index) {
+            Some(user) => user,
+            None => return,
+        };
+
+        for prop in entity.props(parser_state) {
+            if prop.identifier == PLAYER_COND {
+                let cond = i64::try_from(&prop.value).unwrap_or_default();
+                let taunting = cond & COND_TAUNTING != 0;
+                let previous = self.taunting.insert(entity.entity_index, taunting);
+                if taunting != previous.unwrap_or_default() {
+                    self.changes.push(TauntChange {
+                        tick,
+                        user,
+                        taunting,
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl MessageHandler for TauntAnalyser {
+    type Output = Vec<TauntChange>;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        matches!(message_type, MessageType::PacketEntities)
+    }
+
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
+        if let Message::PacketEntities(message) = message {
+            for entity in &message.entities {
+                self.handle_entity(entity, tick, parser_state);
+            }
+        }
+    }
+
+    fn into_output(self, _state: &ParserState) -> Self::Output {
+        self.changes
+    }
+}
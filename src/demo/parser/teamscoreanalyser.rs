@@ -0,0 +1,90 @@
+use crate::demo::data::DemoTick;
+use crate::demo::message::packetentities::{EntityId, PacketEntity};
+use crate::demo::message::{Message, MessageType};
+use crate::demo::parser::analyser::Team;
+use crate::demo::parser::handler::MessageHandler;
+use crate::demo::sendprop::SendPropIdentifier;
+use crate::ParserState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TeamScore {
+    pub tick: DemoTick,
+    pub team: Team,
+    pub score: u16,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamScores {
+    /// Every change in the score of a team
+    pub scores: Vec<TeamScore>,
+    pub red_score: u16,
+    pub blue_score: u16,
+}
+
+/// An analyser that reads the team scores from the team entities.
+///
+/// For most game modes the score is the number of rounds won,
+/// in stopwatch mode it's the number of points captured in the current half instead.
+#[derive(Default, Debug)]
+pub struct TeamScoreAnalyser {
+    scores: TeamScores,
+    teams: HashMap<EntityId, Team>,
+}
+
+impl TeamScoreAnalyser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn handle_entity(&mut self, entity: &PacketEntity, tick: DemoTick, parser_state: &ParserState) {
+        const TEAM: SendPropIdentifier = SendPropIdentifier::new("DT_Team", "m_iTeamNum");
+        const SCORE: SendPropIdentifier = SendPropIdentifier::new("DT_Team", "m_iScore");
+
+        if let Some(prop) = entity.get_prop_by_identifier(&TEAM, parser_state) {
+            let team = Team::new(i64::try_from(&prop.value).unwrap_or_default());
+            self.teams.insert(entity.entity_index, team);
+        }
+
+        let team = match self.teams.get(&entity.entity_index) {
+            Some(team) => *team,
+            None => return,
+        };
+
+        if let Some(prop) = entity.get_prop_by_identifier(&SCORE, parser_state) {
+            let score = i64::try_from(&prop.value).unwrap_or_default() as u16;
+            let current = match team {
+                Team::Red => &mut self.scores.red_score,
+                Team::Blue => &mut self.scores.blue_score,
+                _ => return,
+            };
+            if *current != score {
+                *current = score;
+                self.scores.scores.push(TeamScore { tick, team, score });
+            }
+        }
+    }
+}
+
+impl MessageHandler for TeamScoreAnalyser {
+    type Output = TeamScores;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        matches!(message_type, MessageType::PacketEntities)
+    }
+
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
+        if let Message::PacketEntities(message) = message {
+            for entity in &message.entities {
+                self.handle_entity(entity, tick, parser_state);
+            }
+        }
+    }
+
+    fn into_output(self, _state: &ParserState) -> Self::Output {
+        self.scores
+    }
+}
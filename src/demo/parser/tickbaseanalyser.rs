@@ -0,0 +1,83 @@
+use crate::demo::data::{DemoTick, ServerTick};
+use crate::demo::message::packetentities::{EntityId, PacketEntity};
+use crate::demo::message::{Message, MessageType};
+use crate::demo::parser::handler::MessageHandler;
+use crate::demo::sendprop::SendPropIdentifier;
+use crate::ParserState;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// The difference between the tick base of the local player and the server tick
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TickBaseOffset {
+    pub tick: DemoTick,
+    /// The tick base minus the server tick of the packet
+    pub offset: i32,
+}
+
+/// An analyser that compares the tick base of the local player against the server tick,
+/// for detecting prediction offsets in demos recorded from a player perspective.
+///
+/// Only the tick base of the client that recorded the demo is used, for SourceTV demos
+/// that is the SourceTV bot which doesn't simulate any commands.
+/// An offset is recorded every time it changes.
+#[derive(Default, Debug)]
+pub struct TickBaseAnalyser {
+    offsets: Vec<TickBaseOffset>,
+    server_tick: ServerTick,
+    local_player: Option<EntityId>,
+}
+
+impl TickBaseAnalyser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn handle_entity(&mut self, entity: &PacketEntity, tick: DemoTick, parser_state: &ParserState) {
+        const TICK_BASE: SendPropIdentifier =
+            SendPropIdentifier::new("DT_LocalPlayerExclusive", "m_nTickBase");
+
+        if Some(entity.entity_index) != self.local_player {
+            return;
+        }
+
+        if let Some(prop) = entity.get_prop_by_identifier(&TICK_BASE, parser_state) {
+            let tick_base = i64::try_from(&prop.value).unwrap_or_default();
+            let offset = (tick_base - u32::from(self.server_tick) as i64) as i32;
+            if self.offsets.last().map(|last| last.offset) != Some(offset) {
+                self.offsets.push(TickBaseOffset { tick, offset });
+            }
+        }
+    }
+}
+
+impl MessageHandler for TickBaseAnalyser {
+    type Output = Vec<TickBaseOffset>;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        matches!(
+            message_type,
+            MessageType::PacketEntities | MessageType::NetTick | MessageType::ServerInfo
+        )
+    }
+
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
+        match message {
+            Message::NetTick(message) => self.server_tick = message.tick,
+            // the entity of a player is one more than their slot
+            Message::ServerInfo(message) => {
+                self.local_player = Some(EntityId::from(message.player_slot as u32 + 1))
+            }
+            Message::PacketEntities(message) => {
+                for entity in &message.entities {
+                    self.handle_entity(entity, tick, parser_state);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn into_output(self, _state: &ParserState) -> Self::Output {
+        self.offsets
+    }
+}
@@ -0,0 +1,157 @@
+use crate::demo::data::DemoTick;
+use crate::demo::message::{Message, MessageType};
+use crate::demo::packet::datatable::{ParseSendTable, ServerClass};
+use crate::demo::packet::message::MessagePacketMeta;
+use crate::demo::packet::stringtable::StringTableEntry;
+use crate::demo::parser::analyser::{Class, Team};
+use crate::demo::parser::gamestateanalyser::{GameStateAnalyser, PlayerState};
+use crate::demo::parser::handler::MessageHandler;
+use crate::ParserState;
+use serde::{Deserialize, Serialize};
+
+/// Charge percentage at which a medic can deploy their uber
+const FULL_CHARGE: u8 = 100;
+
+/// A period during which one team had a fully charged medic while the other team didn't
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct UberAdvantage {
+    pub team: Team,
+    pub start_tick: DemoTick,
+    pub end_tick: DemoTick,
+}
+
+/// An analyser that detects uber advantages from the charge level of the medics.
+///
+/// An advantage lasts from the moment one team has a full charge while the other doesn't, until the charge
+/// is used or lost, or the other team gets a full charge as well.
+#[derive(Default, Debug)]
+pub struct UberAnalyser {
+    game_state: GameStateAnalyser,
+    current: Option<(Team, DemoTick)>,
+    last_tick: DemoTick,
+    advantages: Vec<UberAdvantage>,
+}
+
+impl UberAnalyser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn team_charged(&self, team: Team) -> bool {
+        self.game_state.state.players.iter().any(|player| {
+            player.team == team
+                && player.class == Class::Medic
+                && player.state == PlayerState::Alive
+                && player.charge >= FULL_CHARGE
+        })
+    }
+
+    fn update(&mut self, tick: DemoTick, red_charged: bool, blue_charged: bool) {
+        let advantage = match (red_charged, blue_charged) {
+            (true, false) => Some(Team::Red),
+            (false, true) => Some(Team::Blue),
+            _ => None,
+        };
+
+        if self.current.map(|(team, _)| team) != advantage {
+            self.end_advantage(tick);
+            self.current = advantage.map(|team| (team, tick));
+        }
+        self.last_tick = tick;
+    }
+
+    fn end_advantage(&mut self, tick: DemoTick) {
+        if let Some((team, start_tick)) = self.current.take() {
+            self.advantages.push(UberAdvantage {
+                team,
+                start_tick,
+                end_tick: tick,
+            });
+        }
+    }
+}
+
+impl MessageHandler for UberAnalyser {
+    type Output = Vec<UberAdvantage>;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        GameStateAnalyser::does_handle(message_type)
+    }
+
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
+        self.game_state.handle_message(message, tick, parser_state);
+        if let Message::PacketEntities(_) = message {
+            let red_charged = self.team_charged(Team::Red);
+            let blue_charged = self.team_charged(Team::Blue);
+            self.update(tick, red_charged, blue_charged);
+        }
+    }
+
+    fn handle_string_entry(
+        &mut self,
+        table: &str,
+        index: usize,
+        entry: &StringTableEntry,
+        parser_state: &ParserState,
+    ) {
+        self.game_state
+            .handle_string_entry(table, index, entry, parser_state)
+    }
+
+    fn handle_data_tables(
+        &mut self,
+        tables: &[ParseSendTable],
+        server_classes: &[ServerClass],
+        parser_state: &ParserState,
+    ) {
+        self.game_state
+            .handle_data_tables(tables, server_classes, parser_state)
+    }
+
+    fn handle_packet_meta(
+        &mut self,
+        tick: DemoTick,
+        meta: &MessagePacketMeta,
+        parser_state: &ParserState,
+    ) {
+        self.game_state.handle_packet_meta(tick, meta, parser_state)
+    }
+
+    fn into_output(mut self, _state: &ParserState) -> Self::Output {
+        self.end_advantage(self.last_tick);
+        self.advantages
+    }
+}
+
+#[test]
+fn test_uber_advantage() {
+    let state = ParserState::new(24, |_| false, false);
+    let mut analyser = UberAnalyser::new();
+
+    // red gets charged while blue still has to build after dropping their uber
+    analyser.update(DemoTick::from(10u32), true, true);
+    analyser.update(DemoTick::from(20u32), true, false);
+    analyser.update(DemoTick::from(30u32), true, false);
+    // red uses their uber
+    analyser.update(DemoTick::from(40u32), false, false);
+    // both teams charged, no advantage
+    analyser.update(DemoTick::from(50u32), true, true);
+    analyser.update(DemoTick::from(60u32), false, true);
+
+    assert_eq!(
+        vec![
+            UberAdvantage {
+                team: Team::Red,
+                start_tick: DemoTick::from(20u32),
+                end_tick: DemoTick::from(40u32),
+            },
+            UberAdvantage {
+                team: Team::Blue,
+                start_tick: DemoTick::from(60u32),
+                end_tick: DemoTick::from(60u32),
+            },
+        ],
+        analyser.into_output(&state)
+    );
+}
@@ -0,0 +1,203 @@
+/// Get the display name for the weapon identifier of a kill, such as `tf_projectile_rocket` or `quake_rl`
+///
+/// Reskins get their own display name, such as the Black Rose for `black_rose` instead of the Knife.
+/// Returns `None` for identifiers that aren't known.
+pub fn normalize_weapon(weapon: &str) -> Option<&'static str> {
+    Some(match weapon {
+        // scout
+        "scattergun" => "Scattergun",
+        "force_a_nature" => "Force-A-Nature",
+        "shortstop" => "Shortstop",
+        "soda_popper" => "Soda Popper",
+        "pep_brawlerblaster" => "Baby Face's Blaster",
+        "back_scatter" => "Back Scatter",
+        "pistol_scout" => "Pistol",
+        "the_winger" => "Winger",
+        "pep_pistol" => "Pretty Boy's Pocket Pistol",
+        "guillotine" => "Flying Guillotine",
+        "bat" => "Bat",
+        "sandman" => "Sandman",
+        "ball" => "Sandman",
+        "holy_mackerel" => "Holy Mackerel",
+        "candy_cane" => "Candy Cane",
+        "boston_basher" => "Boston Basher",
+        "scout_sword" => "Three-Rune Blade",
+        "warfan" => "Fan O'War",
+        "atomizer" => "Atomizer",
+        "wrap_assassin" => "Wrap Assassin",
+        "unarmed_combat" => "Unarmed Combat",
+        "lava_bat" => "Sun-on-a-Stick",
+        // soldier
+        "tf_projectile_rocket" => "Rocket Launcher",
+        "quake_rl" => "Original",
+        "rocketlauncher_directhit" => "Direct Hit",
+        "blackbox" => "Black Box",
+        "liberty_launcher" => "Liberty Launcher",
+        "cow_mangler" => "Cow Mangler 5000",
+        "airstrike" => "Air Strike",
+        "shotgun_soldier" => "Shotgun",
+        "righteous_bison" => "Righteous Bison",
+        "panic_attack" => "Panic Attack",
+        "mantreads" => "Mantreads",
+        "shovel" => "Shovel",
+        "pickaxe" => "Equalizer",
+        "unique_pickaxe_escape" => "Escape Plan",
+        "paintrain" => "Pain Train",
+        "disciplinary_action" => "Disciplinary Action",
+        "market_gardener" => "Market Gardener",
+        "demokatana" => "Half-Zatoichi",
+        // pyro
+        "flamethrower" => "Flame Thrower",
+        "backburner" => "Backburner",
+        "degreaser" => "Degreaser",
+        "phlogistinator" => "Phlogistinator",
+        "rainblower" => "Rainblower",
+        "dragons_fury" => "Dragon's Fury",
+        "deflect_rocket" => "Deflected Rocket",
+        "deflect_promode" => "Deflected Grenade",
+        "shotgun_pyro" => "Shotgun",
+        "flaregun" => "Flare Gun",
+        "detonator" => "Detonator",
+        "scorch_shot" => "Scorch Shot",
+        "manmelter" => "Manmelter",
+        "fireaxe" => "Fire Axe",
+        "axtinguisher" => "Axtinguisher",
+        "powerjack" => "Powerjack",
+        "back_scratcher" => "Back Scratcher",
+        "sledgehammer" => "Homewrecker",
+        "lollichop" => "Lollichop",
+        "thirddegree" => "Third Degree",
+        "annihilator" => "Neon Annihilator",
+        // demoman
+        "tf_projectile_pipe" => "Grenade Launcher",
+        "loch_n_load" => "Loch-n-Load",
+        "loose_cannon" => "Loose Cannon",
+        "iron_bomber" => "Iron Bomber",
+        "tf_projectile_pipe_remote" => "Stickybomb Launcher",
+        "sticky_resistance" => "Scottish Resistance",
+        "quickiebomb_launcher" => "Quickiebomb Launcher",
+        "bottle" => "Bottle",
+        "sword" => "Eyelander",
+        "headtaker" => "Horseless Headless Horsemann's Headtaker",
+        "nessieclub" => "Nessie's Nine Iron",
+        "claidheamohmor" => "Claidheamh Mòr",
+        "persian_persuader" => "Persian Persuader",
+        "ullapool_caber" => "Ullapool Caber",
+        "battleaxe" => "Scotsman's Skullcutter",
+        "demoshield" => "Chargin' Targe",
+        "splendid_screen" => "Splendid Screen",
+        "tide_turner" => "Tide Turner",
+        // heavy
+        "minigun" => "Minigun",
+        "natascha" => "Natascha",
+        "brass_beast" => "Brass Beast",
+        "tomislav" => "Tomislav",
+        "long_heatmaker" => "Huo-Long Heater",
+        "iron_curtain" => "Iron Curtain",
+        "shotgun_hwg" => "Shotgun",
+        "family_business" => "Family Business",
+        "fists" => "Fists",
+        "gloves" => "Killing Gloves of Boxing",
+        "gloves_running_urgently" => "Gloves of Running Urgently",
+        "steel_fists" => "Fists of Steel",
+        "warrior_spirit" => "Warrior's Spirit",
+        "eviction_notice" => "Eviction Notice",
+        "holiday_punch" => "Holiday Punch",
+        // engineer
+        "obj_sentrygun" => "Sentry Gun",
+        "obj_sentrygun2" => "Sentry Gun Level 2",
+        "obj_sentrygun3" => "Sentry Gun Level 3",
+        "obj_minisentry" => "Mini-Sentry",
+        "wrangler_kill" => "Wrangler",
+        "frontier_justice" => "Frontier Justice",
+        "frontier_kill" => "Frontier Justice",
+        "widowmaker" => "Widowmaker",
+        "pomson" => "Pomson 6000",
+        "rescue_ranger" => "Rescue Ranger",
+        "shotgun_primary" => "Shotgun",
+        "wrench" => "Wrench",
+        "gunslinger" => "Gunslinger",
+        "robot_arm_combo_kill" => "Gunslinger",
+        "southern_hospitality" => "Southern Hospitality",
+        "jag" => "Jag",
+        "eureka_effect" => "Eureka Effect",
+        "short_circuit" => "Short Circuit",
+        "giger_counter" => "Giger Counter",
+        // medic
+        "syringegun_medic" => "Syringe Gun",
+        "blutsauger" => "Blutsauger",
+        "crusaders_crossbow" => "Crusader's Crossbow",
+        "proto_syringe" => "Overdose",
+        "bonesaw" => "Bonesaw",
+        "ubersaw" => "Ubersaw",
+        "battleneedle" => "Vita-Saw",
+        "amputator" => "Amputator",
+        "solemn_vow" => "Solemn Vow",
+        // sniper
+        "sniperrifle" => "Sniper Rifle",
+        "machina" => "Machina",
+        "the_classic" => "Classic",
+        "bazaar_bargain" => "Bazaar Bargain",
+        "awper_hand" => "AWPer Hand",
+        "pro_rifle" => "Hitman's Heatmaker",
+        "tf_projectile_arrow" => "Huntsman",
+        "compound_bow" => "Fortified Compound",
+        "smg" => "SMG",
+        "pro_smg" => "Cleaner's Carbine",
+        "club" => "Kukri",
+        "tribalkukri" => "Tribalman's Shiv",
+        "bushwacka" => "Bushwacka",
+        "shahanshah" => "Shahanshah",
+        // spy
+        "revolver" => "Revolver",
+        "ambassador" => "Ambassador",
+        "letranger" => "L'Etranger",
+        "enforcer" => "Enforcer",
+        "diamondback" => "Diamondback",
+        "knife" => "Knife",
+        "eternal_reward" => "Your Eternal Reward",
+        "kunai" => "Conniver's Kunai",
+        "big_earner" => "Big Earner",
+        "spy_cicle" => "Spy-cicle",
+        "black_rose" => "Black Rose",
+        "sharp_dresser" => "Sharp Dresser",
+        // multi-class
+        "pistol" => "Pistol",
+        "maxgun" => "Lugermorph",
+        "the_capper" => "C.A.P.P.E.R",
+        "fryingpan" => "Frying Pan",
+        "saxxy" => "Saxxy",
+        "prinny_machete" => "Prinny Machete",
+        // damage not caused by a weapon
+        "bleed_kill" => "Bleed",
+        "player" => "Player",
+        "world" => "World",
+        "worldspawn" => "World",
+        "trigger_hurt" => "Environment",
+        _ => return None,
+    })
+}
+
+#[test]
+fn test_normalize_weapon() {
+    assert_eq!(Some("Scattergun"), normalize_weapon("scattergun"));
+    assert_eq!(
+        Some("Rocket Launcher"),
+        normalize_weapon("tf_projectile_rocket")
+    );
+    assert_eq!(
+        Some("Stickybomb Launcher"),
+        normalize_weapon("tf_projectile_pipe_remote")
+    );
+    assert_eq!(
+        Some("Sentry Gun Level 3"),
+        normalize_weapon("obj_sentrygun3")
+    );
+    // reskins
+    assert_eq!(Some("Original"), normalize_weapon("quake_rl"));
+    assert_eq!(Some("Black Rose"), normalize_weapon("black_rose"));
+    assert_eq!(Some("Lugermorph"), normalize_weapon("maxgun"));
+    assert_eq!(Some("Rainblower"), normalize_weapon("rainblower"));
+    assert_eq!(None, normalize_weapon("tf_weapon_unknown"));
+    assert_eq!(None, normalize_weapon(""));
+}
@@ -383,7 +383,7 @@ impl BitWrite<LittleEndian> for SendPropFlags {
 }
 
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum FloatDefinition {
     Coord,
     CoordMP,
@@ -426,7 +426,7 @@ impl FloatDefinition {
 }
 
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SendPropDefinition {
     pub identifier: SendPropIdentifier,
     pub parse_definition: SendPropParseDefinition,
@@ -445,7 +445,7 @@ impl TryFrom<&RawSendPropDefinition> for SendPropDefinition {
 }
 
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SendPropParseDefinition {
     NormalVarInt {
         changes_often: bool,
@@ -1216,6 +1216,112 @@ impl Debug for SendProp {
     }
 }
 
+/// Merge updated props into a set of props, overwriting the values of existing props and adding new ones
+///
+/// Props read from a demo are sorted by their flattened index, which is kept as an invariant by inserting
+/// new props at their sorted position, so existing props can be found by a binary search.
+/// Sets that aren't sorted, such as hand constructed props, are merged with a linear search instead.
+pub(crate) fn apply_prop_update(props: &mut Vec<SendProp>, update: &[SendProp]) {
+    if !props.windows(2).all(|pair| pair[0].index < pair[1].index) {
+        return apply_prop_update_unsorted(props, update);
+    }
+    for (i, prop) in update.iter().enumerate() {
+        match props.binary_search_by_key(&prop.index, |existing| existing.index) {
+            Ok(pos) if props[pos].identifier == prop.identifier => {
+                props[pos].value = prop.value.clone();
+            }
+            Ok(_) => return apply_prop_update_unsorted(props, &update[i..]),
+            Err(pos) => props.insert(pos, prop.clone()),
+        }
+    }
+}
+
+fn apply_prop_update_unsorted(props: &mut Vec<SendProp>, update: &[SendProp]) {
+    for prop in update {
+        match props
+            .iter_mut()
+            .find(|existing| existing.identifier == prop.identifier)
+        {
+            Some(existing) => existing.value = prop.value.clone(),
+            None => props.push(prop.clone()),
+        }
+    }
+}
+
+#[test]
+fn test_apply_prop_update() {
+    fn prop(index: u32, name: &str, value: i64) -> SendProp {
+        SendProp {
+            index,
+            identifier: SendPropIdentifier::new("DT_Test", name),
+            value: SendPropValue::Integer(value),
+        }
+    }
+
+    fn linear_update(props: &mut Vec<SendProp>, update: &[SendProp]) {
+        for prop in update {
+            match props
+                .iter_mut()
+                .find(|existing| existing.identifier == prop.identifier)
+            {
+                Some(existing) => existing.value = prop.value.clone(),
+                None => props.push(prop.clone()),
+            }
+        }
+    }
+
+    fn assert_same_props(mut expected: Vec<SendProp>, mut actual: Vec<SendProp>) {
+        expected.sort_by_key(|prop| prop.index);
+        actual.sort_by_key(|prop| prop.index);
+        assert_eq!(expected, actual);
+    }
+
+    let sorted = vec![prop(1, "a", 1), prop(3, "c", 3), prop(5, "e", 5)];
+    let update = vec![
+        prop(0, "z", 10),
+        prop(3, "c", 30),
+        prop(4, "d", 40),
+        prop(9, "i", 90),
+    ];
+
+    let mut expected = sorted.clone();
+    linear_update(&mut expected, &update);
+    let mut actual = sorted;
+    apply_prop_update(&mut actual, &update);
+    assert!(actual.windows(2).all(|pair| pair[0].index < pair[1].index));
+    assert_same_props(expected, actual);
+
+    // new props are inserted at their sorted position
+    let mut actual = Vec::new();
+    apply_prop_update(
+        &mut actual,
+        &[prop(4, "d", 4), prop(2, "b", 2), prop(7, "g", 7)],
+    );
+    assert_eq!(
+        vec![prop(2, "b", 2), prop(4, "d", 4), prop(7, "g", 7)],
+        actual
+    );
+
+    let unsorted = vec![prop(5, "e", 5), prop(1, "a", 1), prop(3, "c", 3)];
+    let mut expected = unsorted.clone();
+    linear_update(&mut expected, &update);
+    let mut actual = unsorted;
+    apply_prop_update(&mut actual, &update);
+    assert_same_props(expected, actual);
+
+    // props without a meaningful index are still matched by identifier
+    let unindexed = vec![prop(0, "a", 1), prop(0, "b", 2)];
+    let update = vec![prop(0, "b", 20), prop(0, "c", 30)];
+    let mut expected = unindexed.clone();
+    linear_update(&mut expected, &update);
+    let mut actual = unindexed;
+    apply_prop_update(&mut actual, &update);
+    assert_eq!(expected.len(), actual.len());
+    for prop in expected {
+        assert!(actual.contains(&prop));
+    }
+}
+
 pub fn read_var_int(stream: &mut Stream, signed: bool) -> ReadResult<i32> {
     let abs_int = crate::demo::message::stringtable::read_var_int(stream)? as i32;
 
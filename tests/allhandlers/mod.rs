@@ -0,0 +1,92 @@
+//! The composite of all shipped analysers, shared between the handler tests and the `allhandlers` example
+
+use tf_demo_parser::demo::parser::analyser::Analyser;
+use tf_demo_parser::demo::parser::buildinganalyser::BuildingAnalyser;
+use tf_demo_parser::demo::parser::chargeanalyser::ChargeAnalyser;
+use tf_demo_parser::demo::parser::classanalyser::ClassAnalyser;
+use tf_demo_parser::demo::parser::consoleanalyser::ConsoleAnalyser;
+use tf_demo_parser::demo::parser::disguiseanalyser::DisguiseAnalyser;
+use tf_demo_parser::demo::parser::flaganalyser::FlagAnalyser;
+use tf_demo_parser::demo::parser::gamestateanalyser::GameStateAnalyser;
+use tf_demo_parser::demo::parser::groundentityanalyser::GroundEntityAnalyser;
+use tf_demo_parser::demo::parser::healtargetanalyser::HealTargetAnalyser;
+use tf_demo_parser::demo::parser::healthanalyser::HealthAnalyser;
+use tf_demo_parser::demo::parser::positionanalyser::PositionAnalyser;
+use tf_demo_parser::demo::parser::punchangleanalyser::PunchAngleAnalyser;
+use tf_demo_parser::demo::parser::roundtimeranalyser::RoundTimerAnalyser;
+use tf_demo_parser::demo::parser::scoreboardanalyser::ScoreboardAnalyser;
+use tf_demo_parser::demo::parser::shotanalyser::ShotAnalyser;
+use tf_demo_parser::demo::parser::tauntanalyser::TauntAnalyser;
+use tf_demo_parser::demo::parser::teamscoreanalyser::TeamScoreAnalyser;
+use tf_demo_parser::demo::parser::tickbaseanalyser::TickBaseAnalyser;
+use tf_demo_parser::demo::parser::uberanalyser::UberAnalyser;
+
+/// All shipped analysers, combined into a single handler by nesting tuples
+///
+/// Tuples combine at most four handlers, so the last group nests two more tuples.
+pub type AllHandlers = (
+    (Analyser, GameStateAnalyser, ClassAnalyser, PositionAnalyser),
+    (
+        ShotAnalyser,
+        ChargeAnalyser,
+        HealTargetAnalyser,
+        ScoreboardAnalyser,
+    ),
+    (
+        TeamScoreAnalyser,
+        RoundTimerAnalyser,
+        BuildingAnalyser,
+        TauntAnalyser,
+    ),
+    (
+        (
+            PunchAngleAnalyser,
+            HealthAnalyser,
+            FlagAnalyser,
+            DisguiseAnalyser,
+        ),
+        (
+            GroundEntityAnalyser,
+            UberAnalyser,
+            TickBaseAnalyser,
+            ConsoleAnalyser,
+        ),
+    ),
+);
+
+pub fn all_handlers() -> AllHandlers {
+    (
+        (
+            Analyser::new(),
+            GameStateAnalyser::new(),
+            ClassAnalyser::new(),
+            PositionAnalyser::new(),
+        ),
+        (
+            ShotAnalyser::new(),
+            ChargeAnalyser::new(),
+            HealTargetAnalyser::new(),
+            ScoreboardAnalyser::new(),
+        ),
+        (
+            TeamScoreAnalyser::new(),
+            RoundTimerAnalyser::new(),
+            BuildingAnalyser::new(),
+            TauntAnalyser::new(),
+        ),
+        (
+            (
+                PunchAngleAnalyser::new(),
+                HealthAnalyser::new(),
+                FlagAnalyser::new(),
+                DisguiseAnalyser::new(),
+            ),
+            (
+                GroundEntityAnalyser::new(),
+                UberAnalyser::new(),
+                TickBaseAnalyser::new(),
+                ConsoleAnalyser::new(),
+            ),
+        ),
+    )
+}
@@ -217,3 +217,62 @@ fn entity_test(input_file: &str, snapshot_file: &str) {
         );
     }
 }
+
+#[test]
+fn static_baseline_cache_test() {
+    use tf_demo_parser::demo::parser::gamestateanalyser::GameStateAnalyser;
+
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, mut ticker) =
+        DemoParser::new_with_analyser(demo.get_stream(), GameStateAnalyser::new())
+            .ticker()
+            .unwrap();
+    while ticker.tick().unwrap() {}
+
+    let state = ticker.parser_state();
+    assert!(!state.static_baselines.is_empty());
+    for (class_id, static_baseline) in state.static_baselines.iter() {
+        let send_table = &state.send_tables[usize::from(*class_id)];
+        let decoded = static_baseline.parse(send_table).unwrap();
+        // the first call might already be cached from parsing the entities
+        assert_eq!(
+            decoded,
+            state.get_static_baseline(*class_id, send_table).unwrap()
+        );
+        assert_eq!(
+            decoded,
+            state.get_static_baseline(*class_id, send_table).unwrap()
+        );
+    }
+}
+
+#[test]
+fn static_baseline_cache_stats_test() {
+    use tf_demo_parser::demo::parser::gamestateanalyser::GameStateAnalyser;
+
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, mut ticker) =
+        DemoParser::new_with_analyser(demo.get_stream(), GameStateAnalyser::new())
+            .ticker()
+            .unwrap();
+    while ticker.tick().unwrap() {}
+    let stats = ticker.parser_state().baseline_cache_stats();
+    assert!(stats.hits > 0);
+    assert!(stats.misses > 0);
+    assert_eq!(0, stats.evictions);
+    let unbounded = ticker.into_state();
+
+    let demo = Demo::new(&file);
+    let (_, mut ticker) =
+        DemoParser::new_with_analyser(demo.get_stream(), GameStateAnalyser::new())
+            .with_baseline_cache_limit(1)
+            .ticker()
+            .unwrap();
+    while ticker.tick().unwrap() {}
+    let limited_stats = ticker.parser_state().baseline_cache_stats();
+    assert!(limited_stats.evictions > 0);
+    assert!(limited_stats.hits < stats.hits);
+    assert_eq!(unbounded, ticker.into_state());
+}
@@ -0,0 +1,135 @@
+use std::fs;
+use test_case::test_case;
+
+use tf_demo_parser::demo::parser::analyser::Team;
+use tf_demo_parser::demo::parser::flaganalyser::FlagStatus;
+use tf_demo_parser::{Demo, DemoParser};
+
+mod allhandlers;
+
+use allhandlers::all_handlers;
+
+fn is_sorted_by_key<T, K: PartialOrd>(items: &[T], key: impl Fn(&T) -> K) -> bool {
+    items.windows(2).all(|pair| key(&pair[0]) <= key(&pair[1]))
+}
+
+#[test_case("test_data/gully.dem"; "gully")]
+#[test_case("test_data/comp.dem"; "comp")]
+fn all_handlers_test(input_file: &str) {
+    let file = fs::read(input_file).expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, expected_state) = DemoParser::new(demo.get_stream()).parse().unwrap();
+
+    let (
+        header,
+        (
+            (state, game_state, class_timeline, positions),
+            (shots, charges, heal_targets, scoreboard),
+            (team_scores, round_timer, buildings, taunts),
+            ((punch_angles, health, flags, disguises), (ground, ubers, tick_base, console)),
+        ),
+    ) = DemoParser::new_with_analyser(demo.get_stream(), all_handlers())
+        .parse()
+        .unwrap();
+
+    // running other handlers alongside doesn't change the match state
+    pretty_assertions::assert_eq!(expected_state, state);
+
+    assert!(!state.users.is_empty());
+    assert_eq!(header.map, state.map);
+    assert!((state.interval_per_tick - 0.015).abs() < 0.0001);
+    assert!(!state.deaths.is_empty());
+    assert!(is_sorted_by_key(&state.deaths, |death| death.tick));
+    assert!(state
+        .deaths
+        .iter()
+        .all(|death| death.tick <= state.end_tick));
+    assert!(state
+        .deaths
+        .iter()
+        .all(|death| state.users.contains_key(&death.victim)));
+    assert!(!state.rounds.is_empty());
+    assert!(is_sorted_by_key(&state.rounds, |round| round.end_tick));
+    assert!(state.rounds.iter().all(|round| round.length > 0.0));
+    assert!(is_sorted_by_key(&state.chat, |message| message.tick));
+    assert!(is_sorted_by_key(&state.spawns, |spawn| spawn.tick));
+
+    assert!(!game_state.players.is_empty());
+    assert!(game_state.tick <= state.end_tick);
+
+    assert!(!class_timeline.classes.is_empty());
+    assert!(is_sorted_by_key(&class_timeline.classes, |sample| sample.tick));
+    assert!(class_timeline
+        .classes
+        .iter()
+        .all(|sample| state.users.contains_key(&sample.user)));
+
+    assert!(!positions.is_empty());
+    for (user, samples) in &positions {
+        assert!(state.users.contains_key(user));
+        assert!(is_sorted_by_key(samples, |sample| sample.tick));
+    }
+
+    assert!(!shots.is_empty());
+    assert!(is_sorted_by_key(&shots, |shot| shot.tick));
+    assert!(shots
+        .iter()
+        .all(|shot| state.users.contains_key(&shot.user)));
+
+    assert!(!charges.is_empty());
+    assert!(charges
+        .iter()
+        .all(|sample| (0.0..=100.0).contains(&sample.level)));
+    assert!(!heal_targets.is_empty());
+    assert!(is_sorted_by_key(&heal_targets, |target| target.tick));
+
+    assert!(!scoreboard.samples.is_empty());
+    assert!(is_sorted_by_key(&scoreboard.samples, |sample| sample.tick));
+
+    assert!(is_sorted_by_key(&team_scores.scores, |score| score.tick));
+    assert!(!round_timer.is_empty());
+    assert!(round_timer.iter().all(|sample| sample.remaining >= 0.0));
+    assert!(is_sorted_by_key(&buildings, |building| building.tick));
+    assert!(buildings
+        .iter()
+        .all(|building| building.health <= building.max_health));
+    assert!(is_sorted_by_key(&taunts, |taunt| taunt.tick));
+
+    assert!(!punch_angles.is_empty());
+    assert!(is_sorted_by_key(&punch_angles, |punch| punch.tick));
+
+    assert!(!health.is_empty());
+    assert!(is_sorted_by_key(&health, |sample| sample.tick));
+    assert!(health
+        .iter()
+        .all(|sample| state.users.contains_key(&sample.user)));
+
+    assert!(is_sorted_by_key(&flags, |flag| flag.tick));
+    assert!(flags
+        .iter()
+        .all(|flag| flag.carrier.is_none() || flag.status == FlagStatus::Carried));
+
+    assert!(is_sorted_by_key(&disguises, |change| change.tick));
+    assert!(disguises
+        .iter()
+        .all(|change| state.users.contains_key(&change.user)));
+
+    assert!(is_sorted_by_key(&ground, |change| change.tick));
+    assert!(ground
+        .iter()
+        .all(|change| state.users.contains_key(&change.user)));
+
+    assert!(is_sorted_by_key(&ubers, |uber| uber.start_tick));
+    assert!(ubers.iter().all(
+        |uber| uber.start_tick <= uber.end_tick && matches!(uber.team, Team::Red | Team::Blue)
+    ));
+
+    assert!(is_sorted_by_key(&tick_base, |offset| offset.tick));
+
+    // output sent during signon carries the tick from before the demo started syncing
+    let synced_console: Vec<_> = console
+        .iter()
+        .filter(|output| output.tick <= state.end_tick)
+        .collect();
+    assert!(is_sorted_by_key(&synced_console, |output| output.tick));
+}
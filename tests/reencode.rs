@@ -3,13 +3,14 @@ use test_case::test_case;
 
 use bitbuffer::{BitRead, BitReadBuffer, BitReadStream, BitWrite, BitWriteStream, LittleEndian};
 use std::collections::HashMap;
+use tf_demo_parser::demo::data::DemoTick;
 use tf_demo_parser::demo::header::Header;
 use tf_demo_parser::demo::message::Message;
 use tf_demo_parser::demo::packet::datatable::SendTableName;
 use tf_demo_parser::demo::packet::Packet;
 use tf_demo_parser::demo::parser::{DemoHandler, Encode, NullHandler, RawPacketStream};
 use tf_demo_parser::demo::sendprop::{RawSendPropDefinition, SendPropIdentifier, SendPropName};
-use tf_demo_parser::{Demo, Parse};
+use tf_demo_parser::{Demo, DemoParser, Parse};
 
 #[test_case("test_data/small.dem"; "small.dem")]
 #[test_case("test_data/gully.dem"; "gully.dem")]
@@ -139,3 +140,105 @@ fn re_encode_test(input_file: &str) {
         handler.handle_packet(packet).unwrap();
     }
 }
+
+/// Re-encode a demo with all game event messages and the game event list removed
+fn strip_game_events(file: &[u8]) -> Vec<u8> {
+    let demo = Demo::new(file);
+    let mut out_buffer = Vec::with_capacity(file.len());
+    {
+        let mut out_stream = BitWriteStream::new(&mut out_buffer, LittleEndian);
+
+        let mut stream = demo.get_stream();
+        let header = Header::read(&mut stream).unwrap();
+        header.write(&mut out_stream).unwrap();
+
+        let mut packets = RawPacketStream::new(stream);
+        let mut handler = DemoHandler::parse_all_with_analyser(NullHandler);
+
+        while let Some(mut packet) = packets.next(&handler.state_handler).unwrap() {
+            if let Packet::Message(msg) | Packet::Signon(msg) = &mut packet {
+                msg.messages.retain(|message| {
+                    !matches!(message, Message::GameEventList(_) | Message::GameEvent(_))
+                });
+            }
+            packet
+                .encode(&mut out_stream, &handler.state_handler)
+                .unwrap();
+            handler.handle_packet(packet).unwrap();
+        }
+    }
+    out_buffer
+}
+
+#[test]
+fn no_game_events_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let stripped = strip_game_events(&file);
+
+    let demo = Demo::new(&stripped);
+    let (_, state) = DemoParser::new(demo.get_stream()).parse().unwrap();
+    let demo = Demo::new(&file);
+    let (_, full_state) = DemoParser::new(demo.get_stream()).parse().unwrap();
+
+    assert_eq!(None, state.event_list_tick);
+    assert!(state.deaths.is_empty());
+    assert!(state.rounds.is_empty());
+    assert!(state.captures.is_empty());
+    assert!(state.win_panels.is_empty());
+    assert!(state.class_stats.is_empty());
+    // data from outside of game events is still there
+    assert_eq!(full_state.chat, state.chat);
+    assert_eq!(full_state.users.len(), state.users.len());
+    assert_eq!(full_state.end_tick, state.end_tick);
+}
+
+#[test]
+fn map_change_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let mut out_buffer = Vec::with_capacity(file.len());
+    let change_tick = DemoTick::from(10_000u32);
+    {
+        let mut out_stream = BitWriteStream::new(&mut out_buffer, LittleEndian);
+
+        let mut stream = demo.get_stream();
+        let header = Header::read(&mut stream).unwrap();
+        header.write(&mut out_stream).unwrap();
+
+        let mut packets = RawPacketStream::new(stream);
+        let mut handler = DemoHandler::parse_all_with_analyser(NullHandler);
+        let mut server_info = None;
+
+        // re-send the server info with a different map partway through the demo
+        while let Some(mut packet) = packets.next(&handler.state_handler).unwrap() {
+            match &mut packet {
+                Packet::Signon(msg) => {
+                    server_info = server_info.or_else(|| {
+                        msg.messages
+                            .iter()
+                            .find(|message| matches!(message, Message::ServerInfo(_)))
+                            .cloned()
+                    });
+                }
+                Packet::Message(msg) if msg.tick >= change_tick => {
+                    if let Some(Message::ServerInfo(mut info)) = server_info.take() {
+                        info.map = "cp_process_final".into();
+                        msg.messages.insert(0, Message::ServerInfo(info));
+                    }
+                }
+                _ => {}
+            }
+            packet
+                .encode(&mut out_stream, &handler.state_handler)
+                .unwrap();
+            handler.handle_packet(packet).unwrap();
+        }
+    }
+
+    let demo = Demo::new(&out_buffer);
+    let (_, state) = DemoParser::new(demo.get_stream()).parse().unwrap();
+    assert_eq!(1, state.map_changes.len());
+    assert_eq!("cp_process_final", state.map_changes[0].map);
+    assert!(state.map_changes[0].tick >= change_tick);
+    assert_eq!("cp_process_final", state.map);
+}
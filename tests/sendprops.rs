@@ -1,9 +1,12 @@
 use std::fs;
+use std::sync::Arc;
 use test_case::test_case;
 
 use fnv::FnvHashMap;
 use std::collections::{HashMap, HashSet};
-use tf_demo_parser::demo::packet::datatable::{ParseSendTable, SendTableName, ServerClass};
+use tf_demo_parser::demo::packet::datatable::{
+    FlatPropsCache, ParseSendTable, SendTable, SendTableName, ServerClass,
+};
 use tf_demo_parser::demo::parser::MessageHandler;
 use tf_demo_parser::demo::sendprop::{SendPropIdentifier, SendPropName};
 use tf_demo_parser::{Demo, DemoParser, MessageType, ParserState};
@@ -94,3 +97,49 @@ fn flatten_test(input_file: &str, snapshot_file: &str) {
         pretty_assertions::assert_eq!(expected[table], flat_props[table]);
     }
 }
+
+pub struct SendTableAnalyser;
+
+impl MessageHandler for SendTableAnalyser {
+    type Output = Vec<SendTable>;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        message_type == MessageType::PacketEntities
+    }
+
+    fn into_output(self, state: &ParserState) -> Self::Output {
+        state.send_tables.clone()
+    }
+}
+
+#[test_case("test_data/gully.dem"; "gully.dem")]
+fn flatten_cache_test(input_file: &str) {
+    let file = fs::read(input_file).expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, expected) = DemoParser::new_with_analyser(demo.get_stream(), SendTableAnalyser)
+        .parse()
+        .expect("Failed to parse");
+
+    let cache = FlatPropsCache::new();
+    let (_, cold) = DemoParser::new_with_analyser(demo.get_stream(), SendTableAnalyser)
+        .with_flat_props_cache(cache.clone())
+        .parse()
+        .expect("Failed to parse");
+    assert_eq!(1, cache.len());
+    let (_, warm) = DemoParser::new_with_analyser(demo.get_stream(), SendTableAnalyser)
+        .with_flat_props_cache(cache.clone())
+        .parse()
+        .expect("Failed to parse");
+    assert_eq!(1, cache.len());
+
+    assert_eq!(expected.len(), cold.len());
+    assert_eq!(expected.len(), warm.len());
+    for ((expected, cold), warm) in expected.iter().zip(cold.iter()).zip(warm.iter()) {
+        assert_eq!(expected.name, cold.name);
+        assert_eq!(expected.name, warm.name);
+        pretty_assertions::assert_eq!(expected.flattened_props, cold.flattened_props);
+        pretty_assertions::assert_eq!(expected.flattened_props, warm.flattened_props);
+        // the warm parse re-uses the props from the cache instead of copying them
+        assert!(Arc::ptr_eq(&cold.flattened_props, &warm.flattened_props));
+    }
+}
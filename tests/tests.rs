@@ -1,8 +1,66 @@
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
+use std::sync::{Mutex, OnceLock};
 use test_case::test_case;
 
-use tf_demo_parser::demo::parser::gamestateanalyser::{GameState, GameStateAnalyser};
-use tf_demo_parser::{Demo, DemoParser, MatchState};
+use tf_demo_parser::demo::data::DemoTick;
+use tf_demo_parser::demo::gameevent_gen::GameEvent;
+use tf_demo_parser::demo::header::Header;
+use tf_demo_parser::demo::message::packetentities::{EntityId, UpdateType};
+use tf_demo_parser::demo::message::{Message, MessageType};
+use tf_demo_parser::demo::packet::PacketType;
+use tf_demo_parser::demo::parser::analyser::{
+    Analyser, BalanceReason, BuildingEventType, Class, DamageSource, Death, EventType, Hold,
+    MatchFormat, ObjectType, ObjectiveTime, OutputFormat, Team, UserId,
+};
+use tf_demo_parser::demo::parser::buildinganalyser::{health_timelines, BuildingAnalyser};
+use tf_demo_parser::demo::parser::chargeanalyser::ChargeAnalyser;
+use tf_demo_parser::demo::parser::classanalyser::{ClassAnalyser, ClassDiscrepancy};
+use tf_demo_parser::demo::parser::consoleanalyser::{ConsoleAnalyser, ConsoleOutputKind};
+use tf_demo_parser::demo::parser::disguiseanalyser::{Disguise, DisguiseAnalyser};
+use tf_demo_parser::demo::parser::entitydumphandler::{EntityDump, EntityDumpHandler};
+use tf_demo_parser::demo::parser::flaganalyser::{FlagAnalyser, FlagStatus};
+use tf_demo_parser::demo::parser::gamestateanalyser::{
+    BuildingClass, GameState, GameStateAnalyser,
+};
+use tf_demo_parser::demo::parser::groundentityanalyser::{
+    GroundEntityAnalyser, GroundEntityChange, GroundKind,
+};
+use tf_demo_parser::demo::parser::healtargetanalyser::HealTargetAnalyser;
+use tf_demo_parser::demo::parser::healthanalyser::HealthAnalyser;
+use tf_demo_parser::demo::parser::positionanalyser::{to_paths, PositionAnalyser};
+use tf_demo_parser::demo::parser::propwatchhandler::PropWatchHandler;
+use tf_demo_parser::demo::parser::punchangleanalyser::PunchAngleAnalyser;
+use tf_demo_parser::demo::parser::roundtimeranalyser::RoundTimerAnalyser;
+use tf_demo_parser::demo::parser::scoreboardanalyser::ScoreboardAnalyser;
+use tf_demo_parser::demo::parser::shotanalyser::ShotAnalyser;
+use tf_demo_parser::demo::parser::tauntanalyser::TauntAnalyser;
+use tf_demo_parser::demo::parser::teamscoreanalyser::TeamScoreAnalyser;
+use tf_demo_parser::demo::parser::tickbaseanalyser::TickBaseAnalyser;
+use tf_demo_parser::demo::parser::weapons::normalize_weapon;
+use tf_demo_parser::demo::parser::{DemoHandler, MessageHandler, RawPacketStream};
+use tf_demo_parser::demo::sendprop::{SendPropIdentifier, SendPropValue};
+use tf_demo_parser::{Demo, DemoParser, MatchState, ParserState};
+
+/// Parse a demo from `test_data` into a new `MatchState`
+fn parse_match(file: &str) -> MatchState {
+    let file = fs::read(format!("test_data/{}", file)).expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, state) = DemoParser::new(demo.get_stream()).parse().unwrap();
+    state
+}
+
+/// Get the `MatchState` of a demo from `test_data`, each demo is only parsed once for all tests
+fn match_state(file: &str) -> &'static MatchState {
+    static STATES: Mutex<BTreeMap<String, &'static OnceLock<MatchState>>> =
+        Mutex::new(BTreeMap::new());
+    let state = *STATES
+        .lock()
+        .unwrap()
+        .entry(file.to_string())
+        .or_insert_with(|| Box::leak(Box::default()));
+    state.get_or_init(|| parse_match(file))
+}
 
 #[test_case("small.dem", "small.json"; "small.dem")]
 #[test_case("gully.dem", "gully.json"; "gully.dem")]
@@ -17,7 +75,7 @@ use tf_demo_parser::{Demo, DemoParser, MatchState};
 fn snapshot_test(input_file: &str, snapshot_file: &str) {
     let file = fs::read(format!("test_data/{}", input_file)).expect("Unable to read file");
     let demo = Demo::new(&file);
-    let (_, state) = DemoParser::new(demo.get_stream()).parse().unwrap();
+    let state = match_state(input_file);
     //
     // fs::write(
     //     format!("test_data/{}", snapshot_file),
@@ -31,12 +89,37 @@ fn snapshot_test(input_file: &str, snapshot_file: &str) {
             .as_slice(),
     )
     .unwrap();
-    pretty_assertions::assert_eq!(expected, state);
+    // the snapshots don't need to be updated for every release
+    let expected = MatchState {
+        parser_version: state.parser_version.clone(),
+        ..expected
+    };
+    pretty_assertions::assert_eq!(expected, *state);
 
     let (_, state) = DemoParser::new_all(demo.get_stream()).parse().unwrap();
     pretty_assertions::assert_eq!(expected, state);
 }
 
+#[test_case("small.dem"; "small.dem")]
+#[test_case("gully.dem"; "gully.dem")]
+#[test_case("comp.dem"; "comp.dem")]
+#[test_case("decal.dem"; "decal.dem")]
+#[test_case("protocol23.dem"; "protocol23.dem")]
+fn serde_roundtrip_test(input_file: &str) {
+    let state = match_state(input_file);
+
+    let json = serde_json::to_string(&state).unwrap();
+    let deserialized: MatchState = serde_json::from_str(&json).unwrap();
+    pretty_assertions::assert_eq!(*state, deserialized);
+
+    assert_eq!(state.rounds.len(), deserialized.rounds.len());
+    for (original, round) in state.rounds.iter().zip(&deserialized.rounds) {
+        assert_eq!(original.winner, round.winner);
+        assert_eq!(original.end_tick, round.end_tick);
+        assert_eq!(original.length, round.length);
+    }
+}
+
 #[test_case("small.dem", "small_game_state.json"; "small.dem")]
 #[test_case("gully.dem", "gully_game_state.json"; "gully.dem")]
 fn game_state_test(input_file: &str, snapshot_file: &str) {
@@ -61,3 +144,1789 @@ fn game_state_test(input_file: &str, snapshot_file: &str) {
     pretty_assertions::assert_eq!(expected.players, state.players);
     pretty_assertions::assert_eq!(expected, state);
 }
+
+#[test]
+fn objective_time_test() {
+    let state = match_state("comp.dem");
+
+    let capper = state.objective_time(UserId::from(71u16));
+    assert!(capper.capturing_ticks > 0);
+    assert_eq!(
+        ObjectiveTime::default(),
+        state.objective_time(UserId::from(1u16))
+    );
+}
+
+#[test]
+fn damage_dealt_test() {
+    let state = match_state("comp.dem");
+
+    let damage_dealt = |user: u16| state.users[&UserId::from(user)].damage_dealt;
+    // a soldier that took 1943 self damage from rocket jumps and 334 world damage, neither is counted
+    assert_eq!(7436, damage_dealt(55));
+    // a medic that took 5015 world damage
+    assert_eq!(203, damage_dealt(78));
+    assert_eq!(11361, damage_dealt(56));
+    // the stv bot
+    assert_eq!(0, damage_dealt(5));
+    assert_eq!(16519, state.world_damage);
+}
+
+#[test]
+fn class_stats_test() {
+    let state = match_state("comp.dem");
+
+    let breakdown = state.class_breakdown();
+    let stats = |user: u16, class: Class| {
+        breakdown
+            .get(&(UserId::from(user), class))
+            .map(|stats| (stats.damage, stats.healing))
+    };
+    // damage dealt before the first spawn of the player is counted as `Other`
+    assert_eq!(Some((7165, 0)), stats(55, Class::Soldier));
+    assert_eq!(Some((271, 0)), stats(55, Class::Other));
+    assert_eq!(None, stats(55, Class::Scout));
+    assert_eq!(Some((3892, 641)), stats(59, Class::Engineer));
+    assert_eq!(Some((188, 4756)), stats(74, Class::Medic));
+    assert_eq!(Some((6490, 120)), stats(70, Class::Heavy));
+
+    for (user_id, user) in &state.users {
+        let class_damage: u32 = breakdown
+            .iter()
+            .filter(|((class_user, _), _)| class_user == user_id)
+            .map(|(_, stats)| stats.damage)
+            .sum();
+        assert_eq!(user.damage_dealt, class_damage);
+    }
+}
+
+#[test]
+fn weapon_damage_test() {
+    let state = match_state("comp.dem");
+
+    let totals = state.weapon_damage_totals();
+    // rocket launcher
+    assert_eq!(Some(&13763), totals.get(&DamageSource::Weapon(22)));
+    // knife
+    assert_eq!(Some(&21654), totals.get(&DamageSource::Weapon(7)));
+    assert_eq!(Some(&16519), totals.get(&DamageSource::World));
+    let player_damage: u32 = totals
+        .iter()
+        .filter(|(source, _)| **source != DamageSource::World)
+        .map(|(_, damage)| *damage)
+        .sum();
+    assert_eq!(106713, player_damage);
+}
+
+#[test]
+fn event_list_tick_test() {
+    let state = match_state("small.dem");
+
+    // the event list is received during signon
+    assert_eq!(Some(DemoTick::from(236u32)), state.event_list_tick);
+}
+
+#[test]
+fn accuracy_test() {
+    let file = fs::read("test_data/comp.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, state) = DemoParser::new_with_analyser(demo.get_stream(), GameStateAnalyser::new())
+        .parse()
+        .unwrap();
+
+    let hitscan_player = state
+        .players
+        .iter()
+        .max_by_key(|player| player.stats.shots_fired)
+        .unwrap();
+    assert!(hitscan_player.stats.shots_hit > 0);
+    let accuracy = hitscan_player.stats.accuracy().unwrap();
+    assert!((0.0..=1.0).contains(&accuracy));
+}
+
+#[test]
+fn ground_entity_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, changes) =
+        DemoParser::new_with_analyser(demo.get_stream(), GroundEntityAnalyser::new())
+            .parse()
+            .unwrap();
+
+    let jump: Vec<_> = changes
+        .iter()
+        .filter(|change| change.user == UserId::from(3u16))
+        .skip_while(|change| u32::from(change.tick) < 632)
+        .take(2)
+        .cloned()
+        .collect();
+    assert_eq!(
+        vec![
+            GroundEntityChange {
+                tick: DemoTick::from(632u32),
+                user: UserId::from(3u16),
+                ground: GroundKind::None,
+            },
+            GroundEntityChange {
+                tick: DemoTick::from(665u32),
+                user: UserId::from(3u16),
+                ground: GroundKind::World,
+            }
+        ],
+        jump
+    );
+}
+
+#[test]
+fn position_downsample_test() {
+    let file = fs::read("test_data/comp.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, positions) =
+        DemoParser::new_with_analyser(demo.get_stream(), PositionAnalyser::new().with_interval(10))
+            .parse()
+            .unwrap();
+
+    assert!(!positions.is_empty());
+    for samples in positions.values() {
+        let first = u32::from(samples.first().unwrap().tick);
+        let last = u32::from(samples.last().unwrap().tick);
+        let span = last - first;
+        assert!(samples.len() as u32 <= span / 10 + 1);
+        assert!(samples.len() as u32 >= span / 10 / 2);
+        assert!(samples
+            .windows(2)
+            .all(|pair| u32::from(pair[1].tick) - u32::from(pair[0].tick) >= 10));
+    }
+}
+
+#[test]
+fn building_upgrade_test() {
+    let file = fs::read("test_data/decal.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, states) = DemoParser::new_with_analyser(demo.get_stream(), BuildingAnalyser::new())
+        .parse()
+        .unwrap();
+
+    let (index, upgraded) = states
+        .iter()
+        .enumerate()
+        .find(|(_, state)| state.kind == BuildingClass::Sentry && state.level == 3)
+        .expect("no level 3 sentry");
+    assert_eq!(3270, u32::from(upgraded.tick));
+    assert_eq!(UserId::from(14u16), upgraded.builder);
+
+    let previous = states[..index]
+        .iter()
+        .rev()
+        .find(|state| state.entity == upgraded.entity)
+        .unwrap();
+    assert_eq!(2, previous.level);
+    assert_eq!(upgraded.position, previous.position);
+}
+
+#[test]
+fn building_health_test() {
+    let file = fs::read("test_data/decal.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, states) = DemoParser::new_with_analyser(demo.get_stream(), BuildingAnalyser::new())
+        .parse()
+        .unwrap();
+
+    let sentry = EntityId::from(1049u32);
+    let timeline = &health_timelines(&states)[&sentry];
+    let health_at = |tick: u32| {
+        timeline
+            .iter()
+            .rev()
+            .find(|(sample_tick, _)| u32::from(*sample_tick) <= tick)
+            .map(|(_, health)| *health)
+            .unwrap()
+    };
+    // damaged while fully upgraded and then repaired
+    assert_eq!(216, health_at(54000));
+    assert_eq!(104, health_at(54208));
+    assert_eq!(216, health_at(54289));
+    assert!(states
+        .iter()
+        .filter(|state| state.entity == sentry && u32::from(state.tick) <= 54289)
+        .all(|state| state.level == 3 && state.max_health == 216));
+}
+
+#[test]
+fn pause_test() {
+    let file = fs::read("test_data/decal.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (header, state) = DemoParser::new(demo.get_stream()).parse().unwrap();
+
+    assert_eq!(2, state.pauses.len());
+    let paused = state.paused_time();
+    assert!(paused > 60.0);
+    let duration = state.summarize().duration;
+    assert!((header.duration - paused - duration).abs() < 1.0);
+}
+
+#[test]
+fn weapon_name_test() {
+    let state = match_state("gully.dem");
+
+    for death in &state.deaths {
+        match normalize_weapon(&death.weapon) {
+            Some(name) => assert_eq!(name, death.weapon_name()),
+            None => assert_eq!(death.weapon, death.weapon_name()),
+        }
+    }
+    // all weapons in the demo are known
+    assert!(state
+        .weapon_names()
+        .iter()
+        .all(|weapon| normalize_weapon(weapon).is_some()));
+}
+
+#[test]
+fn assister_test() {
+    let state = match_state("gully.dem");
+
+    let death = state
+        .deaths
+        .iter()
+        .find(|death| death.victim == UserId::from(13u16))
+        .unwrap();
+    assert_eq!(UserId::from(11u16), death.killer);
+    assert_eq!(Some(UserId::from(3u16)), death.assister);
+
+    let assisted = state
+        .deaths
+        .iter()
+        .filter(|death| death.assister.is_some())
+        .count();
+    assert!(assisted > 50);
+    assert!(assisted < state.deaths.len());
+    assert!(state
+        .deaths
+        .iter()
+        .filter_map(|death| death.assister)
+        .all(|assister| state.users.contains_key(&assister)));
+}
+
+#[test]
+fn kill_streaks_test() {
+    let state = match_state("comp.dem");
+
+    let streaks = state.kill_streaks();
+    let kills = state
+        .deaths
+        .iter()
+        .filter(|death| death.killer != death.victim && death.killer != 0)
+        .count();
+    assert_eq!(
+        kills as u32,
+        streaks.iter().map(|streak| streak.count).sum::<u32>()
+    );
+
+    // the longest streak of every player matches the best killstreaks
+    let mut longest: HashMap<UserId, u16> = HashMap::new();
+    for streak in &streaks {
+        assert!(streak.start_tick <= streak.end_tick);
+        let best = longest.entry(streak.player).or_default();
+        *best = (*best).max(streak.count as u16);
+    }
+    assert_eq!(state.best_killstreaks(), longest);
+}
+
+#[test]
+fn kill_feed_test() {
+    let state = match_state("gully.dem");
+
+    let kill_feed = state.kill_feed();
+    assert_eq!(state.deaths.len(), kill_feed.len());
+
+    // the demo starts before the players have spawned, so the first kill has no team information
+    assert_eq!(Team::Other, kill_feed[0].victim.team);
+
+    let entry = &kill_feed[1];
+    let killer = entry.killer.as_ref().unwrap();
+    assert!(!killer.name.is_empty());
+    assert!(!entry.victim.name.is_empty());
+    assert_eq!(state.users[&killer.user].name, killer.name);
+    assert!(matches!(killer.team, Team::Red | Team::Blue));
+    assert!(matches!(entry.victim.team, Team::Red | Team::Blue));
+    assert_ne!(killer.team, entry.victim.team);
+}
+
+#[test]
+fn event_rows_test() {
+    let state = match_state("gully.dem");
+
+    let rows = state.to_event_rows();
+    assert!(rows.windows(2).all(|pair| pair[0].tick <= pair[1].tick));
+
+    let deaths: Vec<_> = rows
+        .iter()
+        .filter(|row| row.event_type == EventType::Death)
+        .collect();
+    assert_eq!(state.deaths.len(), deaths.len());
+    let death = &state.deaths[1];
+    let row = deaths
+        .iter()
+        .find(|row| row.tick == death.tick && row.target == Some(death.victim))
+        .unwrap();
+    assert_eq!(Some(death.killer), row.actor);
+    assert_eq!(Some(death.weapon.as_str()), row.value.as_deref());
+    assert_eq!(
+        u32::from(death.tick) as f32 * state.interval_per_tick,
+        row.seconds
+    );
+    assert_eq!(None, row.class);
+
+    let chat: Vec<_> = rows
+        .iter()
+        .filter(|row| row.event_type == EventType::Chat)
+        .collect();
+    assert_eq!(state.chat.len(), chat.len());
+    assert!(chat.iter().all(|row| row.target.is_none()));
+    assert!(chat.iter().any(|row| row.actor.is_some()));
+
+    let json = serde_json::to_value(&rows[0]).unwrap();
+    assert!(json.get("event_type").unwrap().is_string());
+}
+
+#[test]
+fn class_prop_test() {
+    let file = fs::read("test_data/decal.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, timeline) = DemoParser::new_with_analyser(demo.get_stream(), ClassAnalyser::new())
+        .parse()
+        .unwrap();
+
+    assert!(!timeline.classes.is_empty());
+    assert_eq!(Vec::<ClassDiscrepancy>::new(), timeline.discrepancies);
+}
+
+#[test]
+fn desired_class_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let state = match_state("gully.dem");
+    let intent = state.class_intents[1];
+
+    let demo = Demo::new(&file);
+    let (_, timeline) = DemoParser::new_with_analyser(demo.get_stream(), ClassAnalyser::new())
+        .parse()
+        .unwrap();
+    // the desired class is updated when the player picks a new class
+    assert!(timeline
+        .desired_classes
+        .iter()
+        .any(|sample| sample.user == intent.user
+            && sample.class == intent.class
+            && sample.tick >= intent.tick));
+}
+
+#[test]
+fn disguise_test() {
+    let file = fs::read("test_data/decal.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, changes) = DemoParser::new_with_analyser(demo.get_stream(), DisguiseAnalyser::new())
+        .parse()
+        .unwrap();
+
+    let spy = UserId::from(7u16);
+    let spy_changes: Vec<_> = changes
+        .iter()
+        .filter(|change| change.user == spy)
+        .map(|change| (u32::from(change.tick), change.disguise))
+        .collect();
+    assert_eq!(
+        (
+            1411,
+            Some(Disguise {
+                team: Team::Red,
+                class: Class::Sniper
+            })
+        ),
+        spy_changes[3]
+    );
+    // undisguised
+    assert_eq!((10294, None), spy_changes[4]);
+}
+
+#[test]
+fn taunt_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, changes) = DemoParser::new_with_analyser(demo.get_stream(), TauntAnalyser::new())
+        .parse()
+        .unwrap();
+
+    assert_eq!(8, changes.len());
+    let start = changes
+        .iter()
+        .find(|change| change.taunting)
+        .expect("no taunt found");
+    assert_eq!(62625, u32::from(start.tick));
+    let end = changes
+        .iter()
+        .find(|change| change.user == start.user && !change.taunting)
+        .expect("taunt never ended");
+    assert_eq!(62647, u32::from(end.tick));
+}
+
+#[test]
+fn round_timer_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let state = match_state("gully.dem");
+    let demo = Demo::new(&file);
+    let (_, samples) = DemoParser::new_with_analyser(demo.get_stream(), RoundTimerAnalyser::new())
+        .parse()
+        .unwrap();
+
+    // capturing a point adds time to the timer
+    let first_capture = state.captures[0].tick;
+    let before_capture: Vec<_> = samples
+        .iter()
+        .filter(|sample| sample.tick < first_capture)
+        .collect();
+    assert_eq!(600.0, before_capture[0].remaining);
+    assert!(before_capture.len() > 30);
+    for pair in before_capture.windows(2) {
+        assert!(pair[1].remaining < pair[0].remaining);
+    }
+    let first_round_end = state.rounds[0].end_tick;
+    // the timer is paused when the round ends
+    assert!(samples
+        .iter()
+        .any(|sample| sample.paused && sample.tick > first_round_end));
+
+    let file = fs::read("test_data/comp.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, samples) = DemoParser::new_with_analyser(demo.get_stream(), RoundTimerAnalyser::new())
+        .parse()
+        .unwrap();
+    assert!(samples[0].setup);
+    assert_eq!(70.0, samples[0].remaining);
+    assert!(samples.iter().any(|sample| !sample.setup));
+}
+
+#[test]
+fn prop_watch_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let watched = [
+        ("DT_BasePlayer", "m_iHealth"),
+        ("DT_BasePlayer", "m_lifeState"),
+    ];
+    let (_, updates) =
+        DemoParser::new_with_analyser(demo.get_stream(), PropWatchHandler::new(&watched))
+            .parse()
+            .unwrap();
+
+    let health = SendPropIdentifier::new("DT_BasePlayer", "m_iHealth");
+    let life_state = SendPropIdentifier::new("DT_BasePlayer", "m_lifeState");
+    let player_updates: Vec<_> = updates
+        .iter()
+        .filter(|update| update.user.is_some())
+        .collect();
+    assert!(!player_updates.is_empty());
+    for identifier in [health, life_state] {
+        assert!(player_updates
+            .iter()
+            .any(|update| update.props.iter().any(|(prop, _)| *prop == identifier)));
+    }
+    // nothing but the watched props is collected
+    assert!(updates
+        .iter()
+        .flat_map(|update| update.props.iter())
+        .all(|(prop, _)| *prop == health || *prop == life_state));
+}
+
+#[test]
+fn building_events_test() {
+    let state = match_state("comp.dem");
+
+    assert_eq!(62, state.building_events.len());
+    let first = state.building_events[0];
+    assert_eq!(UserId::from(56u16), first.builder);
+    assert_eq!(ObjectType::Sapper, first.object_type);
+    assert_eq!(BuildingEventType::Destroyed, first.event);
+    assert_eq!(Some(UserId::from(69u16)), first.attacker);
+    assert_eq!(DemoTick::from(9304u32), first.tick);
+    assert!(state
+        .building_events
+        .iter()
+        .any(|event| event.object_type == ObjectType::Sentry));
+    assert!(state
+        .building_events
+        .windows(2)
+        .all(|pair| pair[0].tick <= pair[1].tick));
+}
+
+#[test]
+fn class_and_team_changes_test() {
+    let state = match_state("gully.dem");
+
+    assert!(!state.class_changes.is_empty());
+    assert!(!state.team_changes.is_empty());
+    // every spawn as a new class or on a new team is recorded as a change
+    for spawn in state.spawns.iter() {
+        let last_class = state
+            .class_changes
+            .iter()
+            .rev()
+            .find(|change| change.user == spawn.user && change.tick <= spawn.tick)
+            .expect("no class change before spawn");
+        assert_eq!(spawn.class, last_class.to);
+        if state.users.contains_key(&spawn.user) {
+            let last_team = state
+                .team_changes
+                .iter()
+                .rev()
+                .find(|change| change.user == spawn.user && change.tick <= spawn.tick)
+                .expect("no team change before spawn");
+            assert_eq!(spawn.team, last_team.to);
+        }
+    }
+    assert!(state
+        .class_changes
+        .iter()
+        .all(|change| change.from != change.to));
+    assert!(state
+        .team_changes
+        .windows(2)
+        .all(|pair| pair[0].tick <= pair[1].tick));
+}
+
+#[test]
+fn flag_test() {
+    assert_eq!(FlagStatus::Home, FlagStatus::new(0));
+    assert_eq!(FlagStatus::Carried, FlagStatus::new(1));
+    assert_eq!(FlagStatus::Dropped, FlagStatus::new(2));
+
+    // not a capture the flag map
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, states) = DemoParser::new_with_analyser(demo.get_stream(), FlagAnalyser::new())
+        .parse()
+        .unwrap();
+    assert!(states.is_empty());
+}
+
+#[test]
+fn name_table_test() {
+    let state = match_state("gully.dem");
+
+    let names = state.name_table();
+    for death in &state.deaths {
+        let users = [Some(death.victim), Some(death.killer), death.assister];
+        // a killer of 0 is the world
+        for user in users.into_iter().flatten().filter(|user| *user != 0) {
+            assert!(names.contains_key(&user), "{:?} not resolved", user);
+        }
+    }
+}
+
+#[test]
+fn team_score_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, scores) = DemoParser::new_with_analyser(demo.get_stream(), TeamScoreAnalyser::new())
+        .parse()
+        .unwrap();
+    let state = match_state("gully.dem");
+
+    let round_wins = |team: Team| {
+        state
+            .rounds
+            .iter()
+            .filter(|round| round.winner == team)
+            .count() as u16
+    };
+    assert_eq!(round_wins(Team::Red), scores.red_score);
+    assert_eq!(round_wins(Team::Blue), scores.blue_score);
+    assert_eq!(
+        (scores.red_score + scores.blue_score) as usize,
+        scores.scores.len()
+    );
+}
+
+#[test]
+fn message_iter_range_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, all_messages) = DemoParser::new(demo.get_stream())
+        .into_message_iter_range(0, u32::MAX)
+        .unwrap();
+    let expected: Vec<_> = all_messages
+        .map(Result::unwrap)
+        .filter(|(tick, _)| (1000..2000).contains(&u32::from(*tick)))
+        .collect();
+
+    let demo = Demo::new(&file);
+    let (_, range_messages) = DemoParser::new(demo.get_stream())
+        .into_message_iter_range(1000, 2000)
+        .unwrap();
+    let messages: Vec<_> = range_messages.map(Result::unwrap).collect();
+
+    assert!(!messages.is_empty());
+    assert!(messages
+        .iter()
+        .all(|(tick, _)| (1000..2000).contains(&u32::from(*tick))));
+    assert_eq!(expected.len(), messages.len());
+    assert_eq!(expected, messages);
+}
+
+#[test]
+fn message_iter_test() {
+    let file = fs::read("test_data/comp.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let expected = match_state("comp.dem");
+
+    let (_, mut messages) = DemoParser::new(demo.get_stream())
+        .into_message_iter()
+        .unwrap();
+    let mut deaths = 0;
+    let mut last_tick = DemoTick::default();
+    for message in &mut messages {
+        let (tick, message) = message.unwrap();
+        if let Message::GameEvent(event) = &message {
+            if matches!(event.event, GameEvent::PlayerDeath(_)) {
+                assert!(tick >= last_tick);
+                last_tick = tick;
+                deaths += 1;
+            }
+        }
+    }
+    assert_eq!(expected.deaths.len(), deaths);
+    pretty_assertions::assert_eq!(*expected, messages.into_state());
+}
+
+#[test]
+fn parse_header_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let header = demo.parse_header().unwrap();
+    let (expected, state) = DemoParser::new(demo.get_stream()).parse().unwrap();
+
+    assert_eq!(expected, header);
+    assert_eq!("HL2DEMO", header.demo_type);
+    assert_eq!(state.map, header.map);
+    assert!(header.ticks > 0);
+    assert!(header.duration > 0.0);
+
+    assert!(Demo::new(&file[0..100]).parse_header().is_err());
+}
+
+#[test]
+fn parse_schema_only_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (header, schema) = DemoParser::new(demo.get_stream())
+        .parse_schema_only()
+        .unwrap();
+    assert_eq!(demo.parse_header().unwrap(), header);
+
+    assert_eq!(schema.server_classes.len(), schema.send_tables.len());
+    let player_index = schema
+        .server_classes
+        .iter()
+        .position(|class| class.name.as_str() == "CTFPlayer")
+        .unwrap();
+    let player_table = &schema.send_tables[player_index];
+    assert_eq!(
+        schema.server_classes[player_index].data_table,
+        player_table.name
+    );
+    assert!(player_table
+        .flattened_props
+        .iter()
+        .any(|prop| prop.identifier == SendPropIdentifier::new("DT_BasePlayer", "m_iHealth")));
+}
+
+#[test]
+fn parse_progress_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let expected = match_state("gully.dem");
+
+    let mut progress = Vec::new();
+    let (_, state) = DemoParser::new(demo.get_stream())
+        .parse_with_progress(|fraction| progress.push(fraction))
+        .unwrap();
+
+    assert!(progress.len() > 100);
+    assert!(progress.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert!(progress[0] > 0.0);
+    assert_eq!(Some(&1.0), progress.last());
+    pretty_assertions::assert_eq!(*expected, state);
+}
+
+#[test]
+fn parse_range_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let full = match_state("gully.dem");
+    let (_, state) = DemoParser::new(demo.get_stream())
+        .parse_range(20000, 40000)
+        .unwrap();
+
+    let in_range = |tick: DemoTick| (20000..40000).contains(&u32::from(tick));
+    assert_eq!(full.map, state.map);
+    assert_eq!(full.interval_per_tick, state.interval_per_tick);
+    assert!(!state.deaths.is_empty());
+    assert!(state.deaths.iter().all(|death| in_range(death.tick)));
+    // players that spawned before the range still have their team
+    let expected_deaths: Vec<_> = full
+        .deaths
+        .iter()
+        .filter(|death| in_range(death.tick))
+        .cloned()
+        .map(|death| Death {
+            // respawns after the end of the range aren't parsed
+            respawn_tick: death.respawn_tick.filter(|tick| in_range(*tick)),
+            ..death
+        })
+        .collect();
+    assert_eq!(expected_deaths, state.deaths);
+
+    assert!(!state.chat.is_empty());
+    assert!(state.chat.iter().all(|message| in_range(message.tick)));
+    assert!(state.spawns.iter().all(|spawn| in_range(spawn.tick)));
+    assert!(u32::from(state.end_tick) < 40000);
+
+    // the users are known from the string tables before the range
+    assert!(full.users.keys().eq(state.users.keys()));
+}
+
+#[test]
+fn parse_lossy_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let expected = match_state("gully.dem");
+    let (_, state, errors) = DemoParser::new(demo.get_stream()).parse_lossy().unwrap();
+    assert!(errors.is_empty());
+    pretty_assertions::assert_eq!(*expected, state);
+
+    // find the byte range of a message packet halfway through the demo
+    let mut stream = demo.get_stream();
+    let header: Header = stream.read().unwrap();
+    let mut handler = DemoHandler::default();
+    handler.handle_header(&header);
+    let mut packets = RawPacketStream::new(stream);
+    let mut message_packets = Vec::new();
+    let mut start = packets.pos();
+    while let Some(packet) = packets.next(&handler.state_handler).unwrap() {
+        if packet.packet_type() == PacketType::Message {
+            message_packets.push((start / 8, packets.pos() / 8));
+        }
+        handler.handle_packet(packet).unwrap();
+        start = packets.pos();
+    }
+    let (packet_start, packet_end) = message_packets
+        .into_iter()
+        .skip_while(|(start, _)| *start < file.len() / 2)
+        .find(|(start, end)| end - start > 1000)
+        .unwrap();
+
+    // overwrite the second half of the packet data
+    let mut corrupt = file.clone();
+    let midpoint = (packet_start + packet_end) / 2;
+    corrupt[midpoint..packet_end].fill(0xFF);
+    let demo = Demo::new(&corrupt);
+
+    assert!(DemoParser::new(demo.get_stream()).parse().is_err());
+    let (_, state, errors) = DemoParser::new(demo.get_stream()).parse_lossy().unwrap();
+    assert_eq!(1, errors.len());
+    // only the events from the skipped packet are missing
+    assert!(expected.users.keys().eq(state.users.keys()));
+    assert_eq!(expected.end_tick, state.end_tick);
+    assert!(state.deaths.len() >= expected.deaths.len() - 1);
+}
+
+#[test]
+fn parse_lossy_entities_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, expected) = DemoParser::new_with_analyser(demo.get_stream(), EntityDumpHandler::new())
+        .parse()
+        .unwrap();
+
+    // find a message packet with entity updates halfway through the demo
+    let mut stream = demo.get_stream();
+    let header: Header = stream.read().unwrap();
+    let mut handler = DemoHandler::default();
+    handler.handle_header(&header);
+    let mut packets = RawPacketStream::new(stream);
+    let mut entity_packet = None;
+    let mut start = packets.pos();
+    while let Some(packet) = packets.next(&handler.state_handler).unwrap() {
+        let tick = packet.tick();
+        let has_entities =
+            matches!(expected.at_tick(tick), Some(entity_tick) if entity_tick.entities.len() > 10);
+        if packet.packet_type() == PacketType::Message
+            && start / 8 > file.len() / 2
+            && has_entities
+            && entity_packet.is_none()
+        {
+            entity_packet = Some((tick, start / 8, packets.pos() / 8));
+        }
+        handler.handle_packet(packet).unwrap();
+        start = packets.pos();
+    }
+    let (corrupt_tick, packet_start, packet_end) = entity_packet.unwrap();
+
+    // overwrite the second half of the packet data, which contains the entity updates
+    let mut corrupt = file.clone();
+    corrupt[(packet_start + packet_end) / 2..packet_end].fill(0xFF);
+    let demo = Demo::new(&corrupt);
+
+    let (_, dump, errors) =
+        DemoParser::new_with_analyser(demo.get_stream(), EntityDumpHandler::new())
+            .parse_lossy()
+            .unwrap();
+    assert_eq!(1, errors.len());
+
+    // the entity updates from before the corrupt packet are intact
+    let before = |dump: &EntityDump| {
+        dump.ticks
+            .iter()
+            .filter(|entity_tick| entity_tick.tick < corrupt_tick)
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(before(&expected), before(&dump));
+    // there is no full update after the corrupt packet, so no entity updates are applied on top of the missing state
+    assert!(dump
+        .ticks
+        .iter()
+        .filter(|entity_tick| entity_tick.tick >= corrupt_tick)
+        .all(|entity_tick| entity_tick.entities.is_empty()));
+}
+
+#[test]
+fn class_intent_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let state = match_state("gully.dem");
+
+    let intent = state.class_intents[1];
+    assert_eq!(UserId::from(13u16), intent.user);
+    assert_eq!(Class::Scout, intent.class);
+
+    let demo = Demo::new(&file);
+    let (_, messages) = DemoParser::new(demo.get_stream())
+        .into_message_iter_range(0, u32::MAX)
+        .unwrap();
+    // the first spawn of the player after picking the class
+    let spawn = messages
+        .map(Result::unwrap)
+        .filter(|(tick, _)| u32::from(*tick) >= u32::from(intent.tick))
+        .find_map(|(_, message)| match message {
+            Message::GameEvent(message) => match message.event {
+                GameEvent::PlayerSpawn(spawn) if UserId::from(spawn.user_id) == intent.user => {
+                    Some(spawn)
+                }
+                _ => None,
+            },
+            _ => None,
+        })
+        .expect("no spawn after class intent");
+    assert_eq!(intent.class, Class::new(spawn.class));
+}
+
+#[test]
+fn position_paths_test() {
+    let file = fs::read("test_data/comp.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, positions) =
+        DemoParser::new_with_analyser(demo.get_stream(), PositionAnalyser::new().with_interval(10))
+            .parse()
+            .unwrap();
+
+    let paths = to_paths(&positions);
+    assert!(!paths.is_empty());
+    assert!(paths.iter().any(|path| path.points.len() >= 2));
+    for path in &paths {
+        assert!(positions.contains_key(&path.user));
+        assert!(path
+            .points
+            .windows(2)
+            .all(|pair| u32::from(pair[0].2) < u32::from(pair[1].2)));
+    }
+}
+
+/// Records for every entity update whether the entity is in the PVS after the update
+#[derive(Default)]
+struct PvsAnalyser {
+    changes: Vec<(DemoTick, EntityId, bool)>,
+}
+
+impl MessageHandler for PvsAnalyser {
+    type Output = Vec<(DemoTick, EntityId, bool)>;
+
+    fn does_handle(message_type: MessageType) -> bool {
+        message_type == MessageType::PacketEntities
+    }
+
+    fn handle_message(&mut self, message: &Message, tick: DemoTick, _parser_state: &ParserState) {
+        if let Message::PacketEntities(message) = message {
+            for entity in &message.entities {
+                let in_pvs = entity.update_type != UpdateType::Leave;
+                self.changes.push((tick, entity.entity_index, in_pvs));
+            }
+        }
+    }
+
+    fn into_output(self, _state: &ParserState) -> Self::Output {
+        self.changes
+    }
+}
+
+#[test]
+fn position_pvs_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, (state, positions, pvs_changes)) = DemoParser::new_with_analyser(
+        demo.get_stream(),
+        (
+            Analyser::new(),
+            PositionAnalyser::new(),
+            PvsAnalyser::default(),
+        ),
+    )
+    .parse()
+    .unwrap();
+
+    let mut checked = 0;
+    for (user, samples) in &positions {
+        let entity = state.users[user].entity_id;
+        let mut left: Option<DemoTick> = None;
+        for (tick, _, in_pvs) in pvs_changes.iter().filter(|change| change.1 == entity) {
+            match (left, in_pvs) {
+                (None, false) => left = Some(*tick),
+                (Some(left_tick), true) => {
+                    // no positions are recorded while the player is outside the pvs
+                    assert!(!samples
+                        .iter()
+                        .any(|sample| sample.tick > left_tick && sample.tick < *tick));
+                    checked += 1;
+                    left = None;
+                }
+                _ => {}
+            }
+        }
+    }
+    assert!(checked > 10);
+}
+
+/// Collects the user of every player entity from the parser state at the end of the demo
+struct EntityUsers;
+
+impl MessageHandler for EntityUsers {
+    type Output = HashMap<EntityId, UserId>;
+
+    fn does_handle(_message_type: MessageType) -> bool {
+        false
+    }
+
+    fn into_output(self, state: &ParserState) -> Self::Output {
+        (1..=33u32)
+            .map(EntityId::from)
+            .filter_map(|entity| Some((entity, state.user_id_for_entity(entity)?)))
+            .collect()
+    }
+}
+
+#[test_case("test_data/gully.dem"; "gully")]
+#[test_case("test_data/comp.dem"; "comp")]
+fn user_id_for_entity_test(input_file: &str) {
+    let file = fs::read(input_file).expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, (state, entity_users)) =
+        DemoParser::new_with_analyser(demo.get_stream(), (Analyser::new(), EntityUsers))
+            .parse()
+            .unwrap();
+
+    assert!(entity_users.len() >= 12);
+    // user ids only go up, so the latest user of an entity has the highest user id
+    let mut expected = HashMap::new();
+    for (user_id, info) in &state.users {
+        expected.insert(info.entity_id, *user_id);
+    }
+    assert_eq!(expected, entity_users);
+}
+
+#[test]
+fn chat_from_test() {
+    let state = match_state("comp.dem");
+
+    let messages = state.chat_from(UserId::from(69u16));
+    assert!(messages.len() >= 3);
+    assert!(messages.len() < state.chat.len());
+    assert!(messages.iter().all(|message| message.from == "frying pan"));
+    assert_eq!(messages, state.chat_from_name("frying pan"));
+    assert_eq!("gamers assembly", messages[0].text);
+
+    assert!(state.chat_from(UserId::from(1000u16)).is_empty());
+}
+
+#[test]
+fn chat_sender_name_change_test() {
+    let state = match_state("gully.dem");
+
+    // the player changed their name after sending most of their messages
+    let user = UserId::from(7u16);
+    assert_eq!("freak u ___", state.users[&user].name);
+    let messages = state.chat_from(user);
+    assert_eq!(20, messages.len());
+    assert_eq!(
+        18,
+        messages
+            .iter()
+            .filter(|message| message.from == "anytime will do my love")
+            .count()
+    );
+    assert_eq!(2, state.chat_from_name("freak u ___").len());
+
+    // messages from server plugins have no sender
+    assert!(state
+        .chat
+        .iter()
+        .filter(|message| message.from.is_empty())
+        .all(|message| message.user.is_none()));
+}
+
+#[test]
+fn capture_cappers_test() {
+    let state = match_state("gully.dem");
+
+    assert_eq!(vec![UserId::from(7u16)], state.captures[0].cappers);
+    assert_eq!(
+        vec![
+            UserId::from(5u16),
+            UserId::from(9u16),
+            UserId::from(13u16),
+            UserId::from(14u16)
+        ],
+        state.captures[4].cappers
+    );
+    // all cappers are on the team that captured the point
+    for capture in &state.captures {
+        assert!(!capture.cappers.is_empty());
+        for capper in &capture.cappers {
+            let spawn = state
+                .spawns
+                .iter()
+                .rev()
+                .find(|spawn| spawn.user == *capper && spawn.tick <= capture.tick)
+                .unwrap();
+            assert_eq!(capture.team, spawn.team);
+        }
+    }
+}
+
+#[test]
+fn first_captures_test() {
+    let state = match_state("gully.dem");
+
+    let first_captures = state.first_captures();
+    assert_eq!(5, first_captures.len());
+    // mid is captured by red again later, only the first capture is kept
+    assert_eq!(
+        Some(&(Team::Blue, DemoTick::from(2968u32))),
+        first_captures.get(&2)
+    );
+    assert!(state
+        .captures
+        .iter()
+        .any(|capture| capture.point == 2 && capture.team == Team::Red));
+}
+
+#[test]
+fn win_panel_test() {
+    let state = match_state("gully.dem");
+
+    assert_eq!(state.rounds.len(), state.win_panels.len());
+    let panel = &state.win_panels[0];
+    assert_eq!(0, panel.round);
+    assert_eq!(state.rounds[0].end_tick, panel.tick);
+    assert_eq!(
+        vec![
+            (UserId::from(3u16), 9),
+            (UserId::from(11u16), 5),
+            (UserId::from(8u16), 5)
+        ],
+        panel.players
+    );
+}
+
+#[test]
+fn is_alive_test() {
+    let state = match_state("gully.dem");
+
+    let death = &state.deaths[1];
+    let spawn = state
+        .spawns
+        .iter()
+        .rev()
+        .find(|spawn| spawn.user == death.victim && spawn.tick < death.tick)
+        .expect("no spawn before death");
+
+    let after_spawn = DemoTick::from(u32::from(spawn.tick) + 1);
+    let after_death = DemoTick::from(u32::from(death.tick) + 1);
+    assert!(state.is_alive(death.victim, after_spawn));
+    assert!(state.is_alive(death.victim, DemoTick::from(u32::from(death.tick) - 1)));
+    assert!(!state.is_alive(death.victim, after_death));
+    assert!(!state.is_alive(UserId::from(1000u16), after_spawn));
+}
+
+#[test]
+fn charge_level_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, samples) = DemoParser::new_with_analyser(demo.get_stream(), ChargeAnalyser::new())
+        .parse()
+        .unwrap();
+
+    let levels: Vec<_> = samples
+        .iter()
+        .filter(|sample| sample.medic == UserId::from(8u16))
+        .collect();
+    let full = levels
+        .iter()
+        .position(|sample| sample.level == 1.0)
+        .unwrap();
+    assert_eq!(DemoTick::from(4004u32), levels[full].tick);
+
+    // the charge builds up steadily
+    assert!(levels[..=full]
+        .windows(2)
+        .all(|pair| pair[0].level <= pair[1].level));
+
+    // and drains back to zero after deploying
+    let empty = full
+        + levels[full..]
+            .iter()
+            .position(|sample| sample.level == 0.0)
+            .unwrap();
+    assert!(levels[full..=empty]
+        .windows(2)
+        .all(|pair| pair[0].level >= pair[1].level));
+}
+
+#[test]
+fn tuple_handler_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let expected_state = match_state("gully.dem");
+    let (_, expected_shots) = DemoParser::new_with_analyser(demo.get_stream(), ShotAnalyser::new())
+        .parse()
+        .unwrap();
+
+    let (_, (state, shots)) =
+        DemoParser::new_with_analyser(demo.get_stream(), (Analyser::new(), ShotAnalyser::new()))
+            .parse()
+            .unwrap();
+    assert!(!shots.is_empty());
+    assert_eq!(expected_shots, shots);
+    pretty_assertions::assert_eq!(*expected_state, state);
+}
+
+#[test_case("test_data/gully.dem"; "gully")]
+#[test_case("test_data/comp.dem"; "comp")]
+fn scoreboard_test(input_file: &str) {
+    let file = fs::read(input_file).expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, (state, scoreboard)) = DemoParser::new_with_analyser(
+        demo.get_stream(),
+        (Analyser::new(), ScoreboardAnalyser::new()),
+    )
+    .parse()
+    .unwrap();
+
+    assert!(!scoreboard.samples.is_empty());
+    // deaths right before the end of the demo might not be on the scoreboard yet
+    for (user, entry) in &scoreboard.scores {
+        let deaths = state
+            .deaths
+            .iter()
+            .filter(|death| death.victim == *user)
+            .count() as u16;
+        assert!(deaths.abs_diff(entry.deaths) <= 1);
+    }
+    let total: usize = scoreboard
+        .scores
+        .values()
+        .map(|entry| entry.deaths as usize)
+        .sum();
+    assert!(total <= state.deaths.len());
+    assert!(total + 5 >= state.deaths.len());
+}
+
+#[test]
+fn tick_base_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, offsets) = DemoParser::new_with_analyser(demo.get_stream(), TickBaseAnalyser::new())
+        .parse()
+        .unwrap();
+
+    assert!(offsets.len() > 1000);
+    assert!(offsets.iter().all(|offset| offset.offset.abs() <= 8));
+    assert!(offsets.windows(2).all(|pair| pair[0].tick <= pair[1].tick));
+
+    // the SourceTV bot doesn't simulate any commands
+    let file = fs::read("test_data/comp.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, offsets) = DemoParser::new_with_analyser(demo.get_stream(), TickBaseAnalyser::new())
+        .parse()
+        .unwrap();
+    assert!(offsets.len() <= 1);
+}
+
+#[test]
+fn punch_angle_test() {
+    let file = fs::read("test_data/comp.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, (state, angles)) = DemoParser::new_with_analyser(
+        demo.get_stream(),
+        (Analyser::new(), PunchAngleAnalyser::new()),
+    )
+    .parse()
+    .unwrap();
+
+    assert!(angles.iter().all(|punch| punch.angle.length() > 0.0));
+
+    // players get punched by the damage that kills them
+    let punched_deaths = state
+        .deaths
+        .iter()
+        .filter(|death| {
+            angles.iter().any(|punch| {
+                punch.user == death.victim
+                    && punch.tick <= death.tick
+                    && u32::from(death.tick) - u32::from(punch.tick) < 66
+            })
+        })
+        .count();
+    assert!(punched_deaths * 10 > state.deaths.len() * 9);
+}
+
+#[test]
+fn heal_target_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, targets) = DemoParser::new_with_analyser(demo.get_stream(), HealTargetAnalyser::new())
+        .parse()
+        .unwrap();
+
+    let targets: Vec<_> = targets
+        .iter()
+        .filter(|target| target.medic == UserId::from(8u16))
+        .map(|target| (u32::from(target.tick), target.target.map(u16::from)))
+        .collect();
+    assert_eq!(
+        vec![
+            (0, None),
+            (127, Some(6)),
+            (141, None),
+            (193, Some(3)),
+            (268, None)
+        ],
+        targets[0..5]
+    );
+    // the medic switches heal targets all over the demo
+    let mut healed: Vec<_> = targets.iter().filter_map(|(_, target)| *target).collect();
+    healed.sort();
+    healed.dedup();
+    assert!(healed.len() > 3);
+}
+
+#[test_case(OutputFormat::Json; "json")]
+#[test_case(OutputFormat::PrettyJson; "pretty json")]
+fn output_format_test(format: OutputFormat) {
+    let state = match_state("gully.dem");
+
+    let mut out = Vec::new();
+    state.write(format, &mut out).unwrap();
+    let read: MatchState = serde_json::from_slice(&out).unwrap();
+    assert_eq!(*state, read);
+}
+
+#[cfg(feature = "bincode")]
+#[test]
+fn output_format_bincode_test() {
+    let state = match_state("gully.dem");
+
+    let mut out = Vec::new();
+    state.write(OutputFormat::Bincode, &mut out).unwrap();
+    let read: MatchState = bincode::deserialize(&out).unwrap();
+    assert_eq!(*state, read);
+}
+
+#[test_case(OutputFormat::DeathsCsv, "tick,killer,killer_name,", |state| state.deaths.len(); "deaths csv")]
+#[test_case(OutputFormat::ChatCsv, "tick,kind,from,text", |state| state.chat.len(); "chat csv")]
+fn output_format_csv_test(format: OutputFormat, header: &str, rows: fn(&MatchState) -> usize) {
+    let state = match_state("gully.dem");
+
+    let mut out = Vec::new();
+    state.write(format, &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.starts_with(header));
+    assert!(rows(&state) > 0);
+    assert_eq!(rows(&state) + 1, out.lines().count());
+}
+
+#[test]
+fn output_format_ndjson_test() {
+    let state = match_state("gully.dem");
+
+    let mut out = Vec::new();
+    state.write(OutputFormat::NdJson, &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let records = |name: &str| {
+        let prefix = format!("{{\"{}\":", name);
+        out.lines().filter(|line| line.starts_with(&prefix)).count()
+    };
+    assert_eq!(state.deaths.len(), records("deaths"));
+    assert_eq!(state.chat.len(), records("chat"));
+    assert_eq!(state.rounds.len(), records("rounds"));
+
+    let mut lines = out.lines();
+    let mut read: serde_json::Value = serde_json::from_str(lines.next().unwrap()).unwrap();
+    for line in lines {
+        let record: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(line).unwrap();
+        assert_eq!(1, record.len());
+        for (name, value) in record {
+            read[&name].as_array_mut().unwrap().push(value);
+        }
+    }
+    let read: MatchState = serde_json::from_str(&read.to_string()).unwrap();
+    assert_eq!(*state, read);
+}
+
+#[test]
+fn last_point_holds_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, (state, game_state)) = DemoParser::new_with_analyser(
+        demo.get_stream(),
+        (Analyser::new(), GameStateAnalyser::new()),
+    )
+    .parse()
+    .unwrap();
+
+    assert_eq!(Some(5), game_state.control_point_count);
+    assert!(state.last_point_holds(None).is_empty());
+    let holds = state.last_point_holds(game_state.control_point_count);
+    assert_eq!(10, holds.len());
+    assert_eq!(
+        Hold {
+            team: Team::Red,
+            start_tick: DemoTick::from(3622u32),
+            end_tick: DemoTick::from(5212u32),
+            held: false,
+        },
+        holds[0]
+    );
+    // blue pushes out of their last
+    assert_eq!(
+        Hold {
+            team: Team::Blue,
+            start_tick: DemoTick::from(8844u32),
+            end_tick: DemoTick::from(17803u32),
+            held: true,
+        },
+        holds[1]
+    );
+}
+
+#[test]
+fn histogram_test() {
+    let state = match_state("gully.dem");
+
+    let bins = state.histogram(30.0);
+    let duration = u32::from(state.end_tick) as f32 * state.interval_per_tick;
+    assert_eq!((duration / 30.0).ceil() as usize, bins.len());
+    assert_eq!(duration, bins.last().unwrap().end);
+
+    let kills = state
+        .deaths
+        .iter()
+        .filter(|death| death.killer != death.victim && death.killer != 0)
+        .count();
+    assert_eq!(kills as u32, bins.iter().map(|bin| bin.kills).sum::<u32>());
+    assert_eq!(
+        state.deaths.len() as u32,
+        bins.iter().map(|bin| bin.deaths).sum::<u32>()
+    );
+    assert_eq!(
+        state.captures.len() as u32,
+        bins.iter().map(|bin| bin.captures).sum::<u32>()
+    );
+}
+
+#[test]
+fn round_ending_deaths_test() {
+    let state = match_state("gully.dem");
+
+    let deaths = state.round_ending_deaths();
+    assert!(!deaths.is_empty());
+    for (index, losing_team) in deaths {
+        let death = &state.deaths[index];
+        assert_eq!(losing_team, death.victim_team);
+        let round_index = state
+            .rounds
+            .iter()
+            .position(|round| round.end_tick >= death.tick)
+            .expect("death after the last round");
+        let round = &state.rounds[round_index];
+        assert_ne!(losing_team, round.winner);
+        if round_index > 0 {
+            assert!(death.tick > state.rounds[round_index - 1].end_tick);
+        }
+    }
+}
+
+#[test]
+fn respawn_tick_test() {
+    let state = match_state("comp.dem");
+
+    let mut respawned = 0;
+    for death in &state.deaths {
+        if let Some(respawn_tick) = death.respawn_tick {
+            assert!(respawn_tick > death.tick);
+            respawned += 1;
+        }
+    }
+    assert!(respawned > state.deaths.len() / 2);
+    // players that died at the end of the demo don't respawn
+    assert_eq!(None, state.deaths.last().unwrap().respawn_tick);
+}
+
+#[test_case("gully.dem", MatchFormat::Sixes; "gully.dem")]
+#[test_case("nousers.dem", MatchFormat::Sixes; "nousers.dem")]
+#[test_case("comp.dem", MatchFormat::Highlander; "comp.dem")]
+#[test_case("decal.dem", MatchFormat::Highlander; "decal.dem")]
+#[test_case("small.dem", MatchFormat::Other; "small.dem")]
+fn detect_format_test(input_file: &str, expected: MatchFormat) {
+    let state = match_state(input_file);
+
+    assert_eq!(expected, state.detect_format());
+}
+
+#[test]
+fn weapon_names_test() {
+    let state = match_state("gully.dem");
+
+    let weapons = state.weapon_names();
+    assert!(weapons.contains("tf_projectile_rocket"));
+    assert!(weapons.contains("scattergun"));
+    assert!(weapons.len() < state.deaths.len());
+    for death in &state.deaths {
+        assert!(weapons.contains(&death.weapon));
+    }
+}
+
+#[test]
+fn avg_life_seconds_test() {
+    let state = match_state("gully.dem");
+
+    let lives = state.avg_life_seconds(false);
+    let medic = UserId::from(8u16);
+    assert!(lives[&medic].is_finite());
+    assert!(lives[&medic] > 0.0);
+    for life in lives.values() {
+        assert!(life.is_finite());
+        assert!(*life >= 0.0);
+    }
+
+    let with_final = state.avg_life_seconds(true);
+    assert!(with_final.len() >= lives.len());
+    for life in with_final.values() {
+        assert!(life.is_finite());
+    }
+}
+
+#[test]
+fn parse_first_round_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, state) = DemoParser::new(demo.get_stream())
+        .parse_first_round()
+        .unwrap();
+    let full_state = match_state("gully.dem");
+
+    assert_eq!(1, state.rounds.len());
+    assert_eq!(full_state.rounds[0], state.rounds[0]);
+    assert!(state.end_tick < full_state.end_tick);
+    assert!(state.deaths.len() < full_state.deaths.len());
+}
+
+#[test]
+fn last_action_tick_test() {
+    let state = match_state("comp.dem");
+
+    let last_action = state.last_action_tick().unwrap();
+    assert!(last_action <= state.end_tick);
+    assert!(last_action >= state.deaths.last().unwrap().tick);
+    assert!(last_action >= state.rounds.last().unwrap().end_tick);
+
+    assert_eq!(None, MatchState::default().last_action_tick());
+}
+
+#[test]
+fn team_switch_test() {
+    let state = match_state("comp.dem");
+
+    // the teams are switched at once between the halves, with no autobalance or scramble
+    assert_eq!(18, state.balances.len());
+    for balance in &state.balances {
+        assert_eq!(DemoTick::from(48464u32), balance.tick);
+        assert_eq!(BalanceReason::Switch, balance.reason);
+        assert!(balance.team.is_player());
+    }
+}
+
+#[test]
+fn shot_test() {
+    let file = fs::read("test_data/comp.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, shots) = DemoParser::new_with_analyser(demo.get_stream(), ShotAnalyser::new())
+        .parse()
+        .unwrap();
+    let state = match_state("comp.dem");
+
+    // every kill with a hitscan weapon is preceded by a shot from the killer
+    let hitscan_kills: Vec<_> = state
+        .deaths
+        .iter()
+        .filter(|death| {
+            ["scattergun", "shotgun_soldier", "sniperrifle", "minigun"]
+                .contains(&death.weapon.as_str())
+        })
+        .collect();
+    assert!(!hitscan_kills.is_empty());
+    for death in hitscan_kills {
+        let tick = u32::from(death.tick);
+        assert!(
+            shots.iter().any(|shot| shot.user == death.killer
+                && (tick.saturating_sub(10)..=tick).contains(&u32::from(shot.tick))),
+            "no shot for kill at {:?}",
+            death.tick
+        );
+    }
+}
+
+#[test]
+fn parser_version_test() {
+    let state = match_state("small.dem");
+
+    assert_eq!(env!("CARGO_PKG_VERSION"), state.parser_version);
+    let json = serde_json::to_string(&state).unwrap();
+    assert!(json.contains(&format!(
+        r#""parserVersion":"{}""#,
+        env!("CARGO_PKG_VERSION")
+    )));
+
+    // outputs from before the version was recorded can still be read
+    let old = json.replace(
+        &format!(r#","parserVersion":"{}""#, env!("CARGO_PKG_VERSION")),
+        "",
+    );
+    let old_state: MatchState = serde_json::from_str(&old).unwrap();
+    assert_eq!("", old_state.parser_version);
+}
+
+#[test]
+fn health_samples_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, (state, samples)) =
+        DemoParser::new_with_analyser(demo.get_stream(), (Analyser::new(), HealthAnalyser::new()))
+            .parse()
+            .unwrap();
+
+    assert!(!samples.is_empty());
+    assert!(samples.windows(2).all(|pair| pair[0].tick <= pair[1].tick));
+
+    // the health of a player drops to 0 when they die
+    let death = &state.deaths[1];
+    assert_eq!(DemoTick::from(1526u32), death.tick);
+    assert!(samples.iter().any(|sample| sample.user == death.victim
+        && sample.tick == death.tick
+        && sample.health == 0));
+
+    // combining the analysers doesn't change the match state
+    let expected = match_state("gully.dem");
+    pretty_assertions::assert_eq!(*expected, state);
+}
+
+#[test]
+fn charge_samples_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, (state, samples)) =
+        DemoParser::new_with_analyser(demo.get_stream(), (Analyser::new(), ChargeAnalyser::new()))
+            .parse()
+            .unwrap();
+
+    assert!(samples.windows(2).all(|pair| pair[0].tick <= pair[1].tick));
+    assert!(samples
+        .iter()
+        .any(|sample| sample.medic == UserId::from(8u16)
+            && sample.tick == DemoTick::from(4004u32)
+            && sample.level == 1.0));
+
+    // combining the analysers doesn't change the output of either
+    let (_, expected_samples) =
+        DemoParser::new_with_analyser(demo.get_stream(), ChargeAnalyser::new())
+            .parse()
+            .unwrap();
+    assert_eq!(expected_samples, samples);
+    let expected = match_state("gully.dem");
+    pretty_assertions::assert_eq!(*expected, state);
+}
+
+#[test]
+fn entity_state_at_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let health_at = |tick: u32| {
+        let entity = DemoParser::new(demo.get_stream())
+            .entity_state_at(EntityId::from(4u32), DemoTick::from(tick))
+            .unwrap()
+            .unwrap();
+        entity
+            .props
+            .iter()
+            .find(|prop| prop.identifier == SendPropIdentifier::new("DT_BasePlayer", "m_iHealth"))
+            .map(|prop| prop.value.clone())
+    };
+
+    assert_eq!(Some(SendPropValue::Integer(25)), health_at(2670));
+    assert_eq!(Some(SendPropValue::Integer(28)), health_at(2677));
+    assert_eq!(Some(SendPropValue::Integer(0)), health_at(2679));
+
+    assert_eq!(
+        None,
+        DemoParser::new(demo.get_stream())
+            .entity_state_at(EntityId::from(2000u32), DemoTick::from(2677u32))
+            .unwrap()
+    );
+}
+
+#[test]
+fn entity_dump_test() {
+    let file = fs::read("test_data/gully.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, dump) = DemoParser::new_with_analyser(demo.get_stream(), EntityDumpHandler::new())
+        .parse()
+        .unwrap();
+
+    assert!(dump
+        .ticks
+        .windows(2)
+        .all(|pair| pair[0].tick < pair[1].tick));
+
+    // same values as reconstructed by `entity_state_at`
+    let health = SendPropIdentifier::new("DT_BasePlayer", "m_iHealth");
+    let health_at = |tick: u32| {
+        dump.prop_values(EntityId::from(4u32), health)
+            .take_while(|(update_tick, _)| *update_tick <= DemoTick::from(tick))
+            .last()
+            .map(|(_, value)| value.clone())
+    };
+    assert_eq!(Some(SendPropValue::Integer(25)), health_at(2670));
+    assert_eq!(Some(SendPropValue::Integer(28)), health_at(2677));
+    assert_eq!(Some(SendPropValue::Integer(0)), health_at(2679));
+
+    let entity_tick = dump.at_tick(DemoTick::from(2679u32)).unwrap();
+    assert!(entity_tick
+        .entities
+        .iter()
+        .any(|entity| entity.entity_index == EntityId::from(4u32)));
+
+    let partial = EntityDump {
+        ticks: vec![entity_tick.clone()],
+    };
+    let json = serde_json::to_string(&partial).unwrap();
+    assert_eq!(partial, serde_json::from_str(&json).unwrap());
+}
+
+#[test]
+fn csv_export_test() {
+    let state = match_state("gully.dem");
+
+    let mut deaths = Vec::new();
+    state.deaths_to_csv(&mut deaths).unwrap();
+    let deaths = String::from_utf8(deaths).unwrap();
+    assert_eq!(state.deaths.len() + 1, deaths.lines().count());
+
+    let mut chat = Vec::new();
+    state.chat_to_csv(&mut chat).unwrap();
+    let chat = String::from_utf8(chat).unwrap();
+    assert!(chat.starts_with("tick,kind,from,text\n"));
+    assert!(state
+        .chat
+        .iter()
+        .all(|message| chat.contains(&message.from)));
+}
+
+#[test]
+fn kill_flags_test() {
+    let state = match_state("comp.dem");
+
+    let count = |filter: fn(&&Death) -> bool| state.deaths.iter().filter(filter).count();
+    assert_eq!(53, count(|death| death.crit));
+    assert_eq!(29, count(|death| death.headshot));
+    assert_eq!(23, count(|death| death.backstab));
+    assert_eq!(0, count(|death| death.is_taunt_kill()));
+}
+
+#[test]
+fn domination_test() {
+    let state = match_state("comp.dem");
+
+    let count = |filter: fn(&&Death) -> bool| state.deaths.iter().filter(filter).count();
+    assert_eq!(10, count(|death| death.domination));
+    assert_eq!(3, count(|death| death.assister_domination));
+    assert_eq!(3, count(|death| death.revenge));
+    assert_eq!(2, count(|death| death.assister_revenge));
+
+    // both killers and assisters are counted as dominating
+    let total: u32 = state.users.values().map(|user| user.dominations).sum();
+    assert_eq!(13, total);
+}
+
+#[test_case("nousers.dem", Some(28542); "nousers.dem")]
+#[test_case("comp.dem", Some(332); "comp.dem")]
+#[test_case("gully.dem", None; "gully.dem")]
+fn match_start_tick_test(input_file: &str, expected: Option<u32>) {
+    let state = match_state(input_file);
+
+    assert_eq!(expected.map(DemoTick::from), state.match_start_tick);
+    if let Some(match_start) = state.match_start_tick {
+        // the ready-up happened after the demo started
+        assert!(match_start > DemoTick::from(0u32));
+        assert!(state.deaths.iter().any(|death| death.tick > match_start));
+    }
+}
+
+#[test]
+fn console_output_test() {
+    let file = fs::read("test_data/decal.dem").expect("Unable to read file");
+    let demo = Demo::new(&file);
+    let (_, output) = DemoParser::new_with_analyser(demo.get_stream(), ConsoleAnalyser::new())
+        .parse()
+        .unwrap();
+
+    assert!(output
+        .iter()
+        .any(|line| line.kind == ConsoleOutputKind::Command && line.text == "dsp_player 0"));
+    let pause = output
+        .iter()
+        .find(|line| {
+            line.kind == ConsoleOutputKind::Print && line.text.ends_with("paused the game")
+        })
+        .unwrap();
+    assert_eq!(DemoTick::from(60409u32), pause.tick);
+    assert_eq!("[DALE] lynn paused the game", pause.text);
+}